@@ -59,6 +59,33 @@ fn bench_best_placements(c: &mut Criterion) {
     });
 }
 
+/// `BestPlacements::compute` on one thread and on all of them, to track the speedup.
+fn bench_best_placements_threads(c: &mut Criterion) {
+    let data = load_biggame_bytes();
+    let value = parse_nrbf(&data);
+    let savegame = SaveGame::try_from(&value).unwrap();
+    let map = Map::from(&savegame);
+    let groups = GroupAssignments::from(&map);
+    let freqs = dorfromantische2_rs::tile_frequency::TileFrequencies::from_map(&map);
+    let single = rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .unwrap();
+
+    let mut group = c.benchmark_group("BestPlacements::compute threads (biggame)");
+    group.bench_function("1 thread", |b| {
+        b.iter(|| {
+            single.install(|| {
+                BestPlacements::compute(black_box(&map), black_box(&groups), black_box(&freqs))
+            })
+        })
+    });
+    group.bench_function(format!("{} threads", rayon::current_num_threads()), |b| {
+        b.iter(|| BestPlacements::compute(black_box(&map), black_box(&groups), black_box(&freqs)))
+    });
+    group.finish();
+}
+
 fn bench_full_pipeline(c: &mut Criterion) {
     let data = load_biggame_bytes();
     c.bench_function("full pipeline (biggame)", |b| {
@@ -80,6 +107,7 @@ criterion_group!(
     bench_map_from_savegame,
     bench_group_assignments,
     bench_best_placements,
+    bench_best_placements_threads,
     bench_full_pipeline,
 );
 criterion_main!(benches);
@@ -1,10 +1,13 @@
-//! Print the current quest list with progress and status.
+//! Print the current quest list with progress, status and whether each quest can still be
+//! completed.
 //! Run with: cargo run --example quests -- biggame.sav
 
 use comfy_table::{presets::NOTHING, Cell, CellAlignment, Color, Table};
 use dorfromantische2_rs::group_assignments::GroupAssignments;
 use dorfromantische2_rs::map::{Map, QuestType};
+use dorfromantische2_rs::quest_feasibility::{self, QuestFeasibility};
 use dorfromantische2_rs::raw_data::SaveGame;
+use dorfromantische2_rs::tile_frequency::TileFrequencies;
 use std::io::Cursor;
 
 fn main() {
@@ -23,6 +26,7 @@ fn main() {
 
     let map = Map::from(&savegame);
     let groups = GroupAssignments::from(&map);
+    let freqs = TileFrequencies::from_map(&map);
 
     struct QuestInfo {
         terrain: String,
@@ -31,24 +35,22 @@ fn main() {
         unit_count: u32,
         remaining: i32,
         open_edges: usize,
+        feasibility: QuestFeasibility,
     }
 
-    let mut quests: Vec<QuestInfo> = Vec::new();
-    for group in &groups.groups {
-        for (quest, remaining) in group.remaining_per_quest() {
-            if !quest.active {
-                continue;
-            }
-            quests.push(QuestInfo {
-                terrain: format!("{:?}", quest.terrain),
-                target: quest.target_value,
-                quest_type: quest.quest_type,
-                unit_count: group.unit_count,
-                remaining,
-                open_edges: group.open_edges.len(),
-            });
-        }
-    }
+    let quests: Vec<QuestFeasibility> = quest_feasibility::analyze(&map, &groups, &freqs);
+    let mut quests: Vec<QuestInfo> = quests
+        .into_iter()
+        .map(|feasibility| QuestInfo {
+            terrain: format!("{:?}", feasibility.quest.terrain),
+            target: feasibility.quest.target_value,
+            quest_type: feasibility.quest.quest_type,
+            unit_count: feasibility.units,
+            remaining: feasibility.remaining,
+            open_edges: feasibility.open_edges,
+            feasibility,
+        })
+        .collect();
 
     let terrain_order = |t: &str| match t {
         "House" => 0,
@@ -72,6 +74,8 @@ fn main() {
         Cell::new("Units").set_alignment(CellAlignment::Right),
         Cell::new("Left").set_alignment(CellAlignment::Right),
         Cell::new("Edges").set_alignment(CellAlignment::Right),
+        Cell::new("Gain").set_alignment(CellAlignment::Right),
+        Cell::new("Status"),
     ]);
 
     let mut last_terrain = String::new();
@@ -114,6 +118,16 @@ fn main() {
             Cell::new(q.unit_count).set_alignment(CellAlignment::Right),
             remaining_cell,
             Cell::new(q.open_edges).set_alignment(CellAlignment::Right),
+            Cell::new(match q.feasibility.gain {
+                Some((min, max)) => format!("{min}-{max}"),
+                None => String::new(),
+            })
+            .set_alignment(CellAlignment::Right),
+            if q.feasibility.is_feasible() {
+                Cell::new(q.feasibility.label())
+            } else {
+                Cell::new(q.feasibility.label()).fg(Color::Red)
+            },
         ]);
     }
 
@@ -126,5 +140,12 @@ fn main() {
         .filter(|q| q.remaining > 0 && q.remaining <= 10)
         .count();
 
-    println!("\n{total} active quests, {fulfilled} fulfilled, {easy} close to completion");
+    let lost = quests
+        .iter()
+        .filter(|q| !q.feasibility.is_feasible())
+        .count();
+
+    println!(
+        "\n{total} active quests, {fulfilled} fulfilled, {easy} close to completion, {lost} lost"
+    );
 }
@@ -1,20 +1,23 @@
-use std::time::SystemTime;
+use std::{path::PathBuf, time::SystemTime};
 
 use glam::{UVec2, Vec2};
 use winit::window::Window;
 
 use crate::{
     best_placements::MAX_SHOWN_PLACEMENTS,
-    file_watcher::FileWatcher,
+    file_watcher::{FileWatcher, Loaded},
     game_data::GameData,
     render::bind_groups::BindGroups,
     render::camera::Camera,
     render::gpu::{Buffer, Gpu, SizeOrContent},
-    render::shader,
+    render::shader::{self, TileWindow},
     render::textures::Textures,
+    scorer::{DefaultScorer, PlacementScorer, PointsScorer, WeightedScorer},
+    survival::GameStats,
     tile_frequency,
+    timeline::Recorded,
     ui::input_state::InputState,
-    ui::ui_state::UiState,
+    ui::ui_state::{ScorerChoice, UiState},
 };
 
 pub struct App {
@@ -35,6 +38,8 @@ pub struct App {
     view_buffer: Buffer,
     /// Gpu buffer containing static tile info.
     tiles_buffer: Buffer,
+    /// Part of the map that `tiles_buffer` holds.
+    tiles_window: TileWindow,
     /// Bind group for textures and buffers (TODO Split into two?).
     pub bind_groups: BindGroups,
 
@@ -49,6 +54,12 @@ pub struct App {
 
     // Ui.
     pub ui_state: UiState,
+    /// The ranking the placements currently use.
+    applied_scorer: ScorerChoice,
+    /// The timeline step the map currently shows, `None` for the latest save.
+    applied_timeline_step: Option<usize>,
+    /// Skip the zoom after the running rebuild, it only moves through the same game.
+    keep_view_on_rebuild: bool,
 
     /// Area not covered by UI panels.
     pub visible_rect: egui::Rect,
@@ -57,6 +68,27 @@ pub struct App {
 use crate::coords::PixelPos;
 use crate::hex::{self, COS_30};
 
+fn weights_path() -> PathBuf {
+    let mut weights_path = dirs::config_dir().expect("There is no config directory on this system");
+    weights_path.push("dorfromantische2-rs/weights.toml");
+    weights_path
+}
+
+fn make_scorer(choice: ScorerChoice) -> Box<dyn PlacementScorer> {
+    match choice {
+        ScorerChoice::Default => Box::new(DefaultScorer),
+        ScorerChoice::Points => Box::new(PointsScorer),
+        ScorerChoice::Weighted => {
+            let path = weights_path();
+            let weights = WeightedScorer::load(&path).unwrap_or_else(|error| {
+                log::warn!("{error}, using the default weights");
+                WeightedScorer::default()
+            });
+            Box::new(weights)
+        }
+    }
+}
+
 impl App {
     fn create_view_buffer(gpu: &Gpu) -> Buffer {
         gpu.create_buffer(
@@ -118,6 +150,7 @@ impl App {
             textures,
             view_buffer,
             tiles_buffer,
+            tiles_window: TileWindow::default(),
             bind_groups,
 
             // Camera / viewport.
@@ -131,6 +164,9 @@ impl App {
 
             // Ui.
             ui_state: UiState::default(),
+            applied_scorer: ScorerChoice::Default,
+            applied_timeline_step: None,
+            keep_view_on_rebuild: false,
             visible_rect: egui::Rect::EVERYTHING,
         };
 
@@ -209,48 +245,125 @@ impl App {
             &self.tiles_buffer,
             gpu,
             &self.data.map,
+            self.tiles_window,
             &self.data.group_assignments,
             &self.data.best_placements,
         );
     }
 
     fn handle_map_loader(&mut self, gpu: &Gpu) {
-        if let Some((map, groups, best_placements)) = self.file_watcher.map_loader.take_result() {
-            self.data.tile_frequencies = tile_frequency::TileFrequencies::from_map(&map);
-            self.data.map = map;
-            self.data.group_assignments = groups;
-            self.data.best_placements = best_placements;
-            self.data.invalidate_cache();
-            self.ui_state.show_placements = [false; MAX_SHOWN_PLACEMENTS];
-            self.ui_state.focused_placement = None;
-            // Pre-select placements within 5% of the lowest fit chance.
-            let best_fit = self
-                .data
-                .best_placements
-                .iter_all()
-                .first()
-                .map(|(_, s)| s.fit_chance)
-                .unwrap_or(1.0);
-            let threshold = best_fit + 0.05;
-            for (rank, score) in self.data.best_placements.iter_all() {
-                if rank >= MAX_SHOWN_PLACEMENTS {
-                    break;
+        match self.file_watcher.map_loader.take_result() {
+            Some(Loaded::SaveGame(savegame)) => {
+                let recorded = self.data.timeline.record(&savegame);
+                // A new save brings the view back from an earlier timeline step.
+                let scrubbed = self.applied_timeline_step.take().is_some();
+                self.ui_state.timeline_step = None;
+                // Same game with a few more tiles: update in place and keep the view.
+                if !scrubbed && self.data.update_from(&savegame) {
+                    self.handle_map_changed(gpu);
+                } else {
+                    self.keep_view_on_rebuild = scrubbed && recorded != Recorded::NewGame;
+                    self.data.stats = GameStats::from(&*savegame);
+                    self.file_watcher
+                        .map_loader
+                        .rebuild(*savegame, make_scorer(self.applied_scorer));
                 }
-                if score.fit_chance <= threshold {
-                    self.ui_state.show_placements[rank] = true;
+            }
+            Some(Loaded::Rebuilt(rebuilt)) => {
+                let (map, groups, best_placements) = *rebuilt;
+                self.data.tile_frequencies = tile_frequency::TileFrequencies::from_map(&map);
+                self.data.map = map;
+                self.data.group_assignments = groups;
+                self.data.best_placements = best_placements;
+                self.data.invalidate_cache();
+                self.handle_map_changed(gpu);
+                if !std::mem::take(&mut self.keep_view_on_rebuild) {
+                    self.pending_zoom_fit = 2; // Wait 1 frame for sidebar to settle.
                 }
             }
-            self.pending_zoom_fit = 2; // Wait 1 frame for sidebar to settle.
+            None => {}
+        }
+    }
+
+    fn handle_map_changed(&mut self, gpu: &Gpu) {
+        // Placement ranks changed, so the selection is rebuilt.
+        self.ui_state.show_placements = [false; MAX_SHOWN_PLACEMENTS];
+        self.ui_state.focused_placement = None;
+        // Pre-select placements within 5% of the lowest fit chance.
+        let best_fit = self
+            .data
+            .best_placements
+            .iter_all()
+            .first()
+            .map(|(_, s)| s.fit_chance)
+            .unwrap_or(1.0);
+        let threshold = best_fit + 0.05;
+        for (rank, score) in self.data.best_placements.iter_all() {
+            if rank >= MAX_SHOWN_PLACEMENTS {
+                break;
+            }
+            if score.fit_chance <= threshold {
+                self.ui_state.show_placements[rank] = true;
+            }
+        }
 
-            let map_byte_size = shader::byte_size(&self.data.map);
-            self.tiles_buffer = Self::create_tiles_buffer(gpu, map_byte_size);
-            self.generate_bind_group(gpu);
-            self.write_tiles(gpu);
+        self.upload_tiles(gpu);
 
-            self.input.hover_segment = None;
+        self.input.hover_segment = None;
 
-            // Rebuild map silhouette for viewport detection.
-            self.game_nav.update_map(&self.data.map);
+        // Rebuild map silhouette for viewport detection.
+        self.game_nav.update_map(&self.data.map);
+    }
+
+    /// Upload the tiles in view into a buffer sized for them.
+    fn upload_tiles(&mut self, gpu: &Gpu) {
+        self.tiles_window = TileWindow::in_view(&self.camera, &self.data.map);
+        self.tiles_buffer = Self::create_tiles_buffer(gpu, shader::byte_size(self.tiles_window));
+        self.generate_bind_group(gpu);
+        self.write_tiles(gpu);
+    }
+
+    /// Re-rank the placements when another scorer was picked or its weights should be reloaded.
+    fn handle_scorer_change(&mut self, gpu: &Gpu) {
+        let unchanged =
+            self.ui_state.scorer == self.applied_scorer && !self.ui_state.reload_weights;
+        // A running rebuild uses the applied scorer. Switch once it is done.
+        if unchanged || self.file_watcher.map_loader.in_progress() {
+            return;
+        }
+        self.ui_state.reload_weights = false;
+        self.applied_scorer = self.ui_state.scorer;
+        self.data.set_scorer(make_scorer(self.applied_scorer));
+        self.handle_map_changed(gpu);
+    }
+
+    /// Rebuild the map for the timeline step picked in the ui, once the loader is free.
+    fn handle_timeline_change(&mut self) {
+        let step = self.ui_state.timeline_step;
+        if step == self.applied_timeline_step || self.file_watcher.map_loader.in_progress() {
+            return;
+        }
+        self.applied_timeline_step = step;
+        let savegame = match step {
+            Some(index) => self.data.timeline.savegame_at(index),
+            None => self.data.timeline.latest().cloned(),
+        };
+        let Some(savegame) = savegame else {
+            return;
+        };
+        self.keep_view_on_rebuild = true;
+        self.data.stats = GameStats::from(&savegame);
+        self.file_watcher
+            .map_loader
+            .rebuild(savegame, make_scorer(self.applied_scorer));
+    }
+
+    /// Start the review export asked for in the ui and pick up finished ones.
+    fn handle_review_export(&mut self) {
+        self.file_watcher.review_export.poll();
+        if std::mem::take(&mut self.ui_state.export_review) {
+            self.file_watcher
+                .export_review(self.data.timeline.clone(), make_scorer(self.applied_scorer));
         }
     }
 
@@ -273,8 +386,15 @@ impl App {
         self.game_nav.tick(solver_center, mouse_abs, mouse_idle);
 
         self.file_watcher.handle_file_dialog();
+        self.file_watcher.slot_scanner.poll();
         self.file_watcher.reload_file_if_changed();
         self.handle_map_loader(gpu);
+        self.handle_scorer_change(gpu);
+        self.handle_timeline_change();
+        self.handle_review_export();
+        if TileWindow::in_view(&self.camera, &self.data.map) != self.tiles_window {
+            self.upload_tiles(gpu);
+        }
         self.write_view(gpu);
     }
 }
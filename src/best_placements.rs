@@ -1,11 +1,15 @@
-use std::{cmp::Ordering, collections::BTreeSet};
+use std::{cmp::Ordering, collections::HashMap, ops::Range};
+
+use rayon::prelude::*;
 
 use crate::{
     data::{EdgeMatch, HexPos, Rotation, Terrain, HEX_SIDES},
     group::GroupIndex,
     group_assignments::GroupAssignments,
     map::Map,
-    tile_frequency::TileFrequencies,
+    score::{self, PointsEstimate, ScoreRules},
+    scorer::{DefaultScorer, PlacementScorer},
+    tile_frequency::{FrequencyChange, TileFrequencies},
 };
 
 pub const MAX_SHOWN_PLACEMENTS: usize = 30;
@@ -56,6 +60,8 @@ pub struct PlacementScore {
     pub connection_difficulty: u8,
     /// Rail/River edges from existing tiles pointing at empty neighbors we'd crowd.
     pub crowding: u8,
+    /// Neighbors that will be filled by preplaced tiles once reached.
+    pub preplaced_neighbors: u8,
     /// Effects on groups with >5 tiles.
     pub group_effects: Vec<GroupEffect>,
     pub group_edge_alterations: Vec<GroupEdgeAlteration>,
@@ -65,6 +71,10 @@ pub struct PlacementScore {
     pub fit_unique: u16,
     /// How placing here changes the fit chance for each empty neighbor.
     pub neighbor_fit_effects: Vec<NeighborFitEffect>,
+    /// Points the game would award, and the tiles it would add to the stack.
+    pub points: PointsEstimate,
+    /// `points` plus the value of the gained tiles.
+    pub expected_points: f32,
 }
 
 /// How placing a tile affects the fit chance at one empty neighbor.
@@ -82,7 +92,7 @@ impl PlacementScore {
     fn to_orderable(&self) -> impl Ord {
         // Primary: lower fit_chance = harder to fill = place here first.
         // Convert to fixed-point for Ord (f32 doesn't impl Ord).
-        let fit_key = std::cmp::Reverse((self.fit_chance * 1_000_000.0) as u32);
+        let fit_key = std::cmp::Reverse(fit_key(self.fit_chance));
         (
             fit_key,
             // When fit% is equal, prefer lower difficulty (less constrained).
@@ -97,6 +107,11 @@ impl PlacementScore {
     }
 }
 
+/// The fit chance in fixed point, as the default order compares it.
+fn fit_key(fit_chance: f32) -> u32 {
+    (fit_chance * 1_000_000.0) as u32
+}
+
 impl PartialEq for PlacementScore {
     fn eq(&self, other: &Self) -> bool {
         self.to_orderable() == other.to_orderable()
@@ -117,9 +132,96 @@ impl PartialOrd for PlacementScore {
     }
 }
 
-#[derive(Default)]
 pub struct BestPlacements {
-    best_placements: BTreeSet<PlacementScore>,
+    /// Every legal rotation at every position. The rotations of a position are next to each
+    /// other, best first.
+    placements: Vec<PlacementScore>,
+    /// Rotations of each position in `placements`.
+    positions: HashMap<HexPos, Range<usize>>,
+    /// Index of the best rotation of each position in `placements`, best first, as ranked by
+    /// `scorer`.
+    best: Vec<usize>,
+    fit_cache: FitChanceCache,
+    scorer: Box<dyn PlacementScorer>,
+    /// What the points of a placement are worth, see `PlacementScore::expected_points`.
+    rules: ScoreRules,
+}
+
+impl Default for BestPlacements {
+    fn default() -> Self {
+        Self {
+            placements: Vec::new(),
+            positions: HashMap::new(),
+            best: Vec::new(),
+            fit_cache: FitChanceCache::default(),
+            scorer: Box::new(DefaultScorer),
+            rules: ScoreRules::default(),
+        }
+    }
+}
+
+/// Memoized fit chances per set of edge constraints. The counts behind each chance are kept
+/// exact when tile frequencies change, so the cache survives incremental updates.
+#[derive(Default)]
+pub(crate) struct FitChanceCache {
+    /// Constraints -> (number of fitting tiles, number of fitting patterns).
+    counts: HashMap<[Option<Terrain>; HEX_SIDES], (usize, u16)>,
+}
+
+impl FitChanceCache {
+    pub(crate) fn get(
+        &mut self,
+        freqs: &TileFrequencies,
+        constraints: &[Option<Terrain>; HEX_SIDES],
+    ) -> (f32, u16) {
+        let &mut (count, unique) = self
+            .counts
+            .entry(*constraints)
+            .or_insert_with(|| fit_counts_for_constraints(freqs, constraints));
+        (fit_chance_of_count(freqs, count), unique)
+    }
+
+    /// Take over the counts another cache computed for the same tile frequencies.
+    fn merge(&mut self, other: FitChanceCache) {
+        self.counts.extend(other.counts);
+    }
+
+    /// Apply the count changes reported by `TileFrequencies::update`.
+    fn apply(&mut self, changes: &[FrequencyChange]) {
+        for (constraints, (count, unique)) in &mut self.counts {
+            for change in changes {
+                if !pattern_fits(&change.edges.0, constraints) {
+                    continue;
+                }
+                *count = *count + change.count_after - change.count_before;
+                match (change.count_before, change.count_after) {
+                    (0, _) => *unique += 1,
+                    (_, 0) => *unique -= 1,
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// The fit chances one thread of `BestPlacements::rescore` sees: the shared cache is only
+/// read, new counts go to a cache of the thread and are merged afterwards.
+struct ThreadFitCache<'a> {
+    shared: &'a FitChanceCache,
+    local: &'a mut FitChanceCache,
+}
+
+impl ThreadFitCache<'_> {
+    fn get(
+        &mut self,
+        freqs: &TileFrequencies,
+        constraints: &[Option<Terrain>; HEX_SIDES],
+    ) -> (f32, u16) {
+        match self.shared.counts.get(constraints) {
+            Some(&(count, unique)) => (fit_chance_of_count(freqs, count), unique),
+            None => self.local.get(freqs, constraints),
+        }
+    }
 }
 
 /// Count matching edges for placing the next tile at `pos` with `rotation`.
@@ -156,6 +258,21 @@ fn count_matching_edges(map: &Map, pos: HexPos, rotation: Rotation) -> Option<u8
     Some(matching)
 }
 
+/// Whether `pos` holds a tile or will hold a preplaced tile once it is reached.
+pub(crate) fn is_occupied(map: &Map, pos: HexPos) -> bool {
+    map.tile_key(pos)
+        .and_then(|key| map.rendered_tiles[key])
+        .is_some()
+        || map.preplaced_at(pos).is_some()
+}
+
+/// Count how many neighbors of `pos` are preplaced tiles.
+fn count_preplaced_neighbors(map: &Map, pos: HexPos) -> u8 {
+    (0..HEX_SIDES)
+        .filter(|&side| map.preplaced_at(Map::neighbor_pos_of(pos, side)).is_some())
+        .count() as u8
+}
+
 /// Count how many neighboring edges at `pos` have one of the given terrains.
 fn count_neighbor_terrains(map: &Map, pos: HexPos, wanted: &[Terrain]) -> u8 {
     let mut count = 0;
@@ -181,11 +298,7 @@ fn connection_difficulty(map: &Map, pos: HexPos, rotation: Rotation) -> u8 {
     let mut difficulty = 0;
     for side in 0..HEX_SIDES {
         let neighbor_pos = Map::neighbor_pos_of(pos, side);
-        let has_neighbor = map
-            .tile_key(neighbor_pos)
-            .and_then(|key| map.rendered_tiles[key])
-            .is_some();
-        if has_neighbor {
+        if is_occupied(map, neighbor_pos) {
             continue;
         }
         let my_terrain = map
@@ -202,9 +315,7 @@ fn connection_difficulty(map: &Map, pos: HexPos, rotation: Rotation) -> u8 {
                     if p == pos {
                         return true; // the tile we're placing
                     }
-                    map.tile_key(p)
-                        .and_then(|key| map.rendered_tiles[key])
-                        .is_some()
+                    is_occupied(map, p)
                 })
                 .count() as u8;
             difficulty += occupied;
@@ -249,17 +360,17 @@ fn crowding(map: &Map, pos: HexPos) -> u8 {
 }
 
 /// Check whether placing a tile at `pos` would split a contiguous empty region into
-/// multiple holes. Walks the 6 neighbors and counts runs of occupied/empty tiles around
+/// multiple holes. Preplaced tiles count as occupied. Walks the 6 neighbors and counts runs of occupied/empty tiles around
 /// the hex (wrapping around). More than one run of empty neighbors means the placement
 /// creates a split.
 fn would_create_split(map: &Map, pos: HexPos) -> bool {
-    let occupied: [bool; HEX_SIDES] = std::array::from_fn(|side| {
-        let neighbor_pos = Map::neighbor_pos_of(pos, side);
-        map.tile_key(neighbor_pos)
-            .and_then(|key| map.rendered_tiles[key])
-            .is_some()
-    });
+    splits_empty_neighbors(std::array::from_fn(|side| {
+        is_occupied(map, Map::neighbor_pos_of(pos, side))
+    }))
+}
 
+/// Whether the empty ones among the 6 neighbors of a cell form more than one run.
+pub(crate) fn splits_empty_neighbors(occupied: [bool; HEX_SIDES]) -> bool {
     // Count the number of contiguous runs of empty neighbors, wrapping around.
     let mut empty_runs = 0;
     for side in 0..HEX_SIDES {
@@ -276,7 +387,8 @@ impl BestPlacements {
     /// Test a single placement option.
     fn score_of_next_at(
         map: &Map,
-        _groups: &GroupAssignments,
+        groups: &GroupAssignments,
+        rules: &ScoreRules,
         pos: HexPos,
         rotation: Rotation,
     ) -> Option<PlacementScore> {
@@ -295,43 +407,91 @@ impl BestPlacements {
         };
         let connection_difficulty = connection_difficulty(map, pos, rotation);
         let crowding = crowding(map, pos);
+        let preplaced_neighbors = count_preplaced_neighbors(map, pos);
+        let points = score::simulate(map, groups, &map.next_tile, pos, rotation, rules);
         Some(PlacementScore {
             pos,
             rotation,
             matching_edges,
             connection_difficulty,
             crowding,
+            preplaced_neighbors,
             neighbor_bonus,
             group_effects: Vec::new(),
             group_edge_alterations: Vec::new(),
             fit_chance: 0.0,
             fit_unique: 0,
             neighbor_fit_effects: Vec::new(),
+            expected_points: points.expected(rules),
+            points,
         })
     }
 
     /// Find the placement closest to `pos` within `max_dist` hex distance.
     pub fn find_nearest(&self, pos: HexPos, max_dist: i32) -> Option<&PlacementScore> {
-        self.best_placements
-            .iter()
+        self.iter_best()
             .filter(|s| {
                 (s.pos.x() - pos.x()).abs() <= max_dist && (s.pos.y() - pos.y()).abs() <= max_dist
             })
             .min_by_key(|s| (s.pos.x() - pos.x()).pow(2) + (s.pos.y() - pos.y()).pow(2))
     }
 
-    pub fn iter_all(&self) -> Vec<(usize, &PlacementScore)> {
-        // Top N by score (stable order from BTreeSet).
-        let top: Vec<&PlacementScore> = self
-            .best_placements
+    /// The best rotation of every position, best first.
+    pub fn iter_best(&self) -> impl Iterator<Item = &PlacementScore> {
+        self.best.iter().map(|&index| &self.placements[index])
+    }
+
+    /// The best `n` positions, with their best rotation.
+    pub fn top_n(&self, n: usize) -> impl Iterator<Item = &PlacementScore> {
+        self.iter_best().take(n)
+    }
+
+    /// Every legal rotation at `pos`, best first. Empty if nothing fits there.
+    pub fn by_position(&self, pos: HexPos) -> &[PlacementScore] {
+        self.positions
+            .get(&pos)
+            .map_or(&[], |range| &self.placements[range.clone()])
+    }
+
+    /// The rotations at `pos` that the scorer cannot tell apart from the best one.
+    pub fn tied_rotations(&self, pos: HexPos) -> &[PlacementScore] {
+        let rotations = self.by_position(pos);
+        let ties = rotations
             .iter()
-            .rev()
-            .take(MAX_SHOWN_PLACEMENTS)
-            .collect();
+            .take_while(|score| self.scorer.compare(score, &rotations[0]) == Ordering::Equal)
+            .count();
+        &rotations[..ties]
+    }
+
+    /// Every legal (position, rotation) pair that matches `pred`, best first.
+    pub fn filter(&self, pred: impl Fn(&PlacementScore) -> bool) -> Vec<&PlacementScore> {
+        let mut matching: Vec<&PlacementScore> =
+            self.placements.iter().filter(|score| pred(score)).collect();
+        // Rotations of one position compare equal in the default order.
+        matching.sort_by(|a, b| {
+            self.scorer
+                .compare(b, a)
+                .then_with(|| b.rotation.cmp(&a.rotation))
+        });
+        matching
+    }
+
+    /// Number of legal (position, rotation) pairs.
+    pub fn legal_count(&self) -> usize {
+        self.placements.len()
+    }
+
+    pub fn scorer(&self) -> &dyn PlacementScorer {
+        self.scorer.as_ref()
+    }
+
+    pub fn iter_all(&self) -> Vec<(usize, &PlacementScore)> {
+        // Top N by score.
+        let top: Vec<&PlacementScore> = self.iter_best().take(MAX_SHOWN_PLACEMENTS).collect();
 
         // Append any with group_effects not already in top N.
         let mut result = top.clone();
-        for score in &self.best_placements {
+        for score in self.iter_best() {
             if !score.group_effects.is_empty() && !result.iter().any(|s| std::ptr::eq(*s, score)) {
                 result.push(score);
             }
@@ -348,6 +508,33 @@ struct LargeGroup {
     rank: usize,
 }
 
+/// All open groups, ranked per terrain by unit count (1 = largest).
+fn large_groups_of(groups: &GroupAssignments) -> Vec<LargeGroup> {
+    // Collect all open groups with more than MIN_GROUP_SIZE tiles,
+    // ranked per terrain by unit count (1 = largest).
+    let mut per_terrain: HashMap<Terrain, Vec<(usize, u32)>> = HashMap::new();
+    for (idx, group) in groups.groups.iter().enumerate() {
+        if group.is_closed() {
+            continue;
+        }
+        per_terrain
+            .entry(group.terrain)
+            .or_default()
+            .push((idx, group.unit_count));
+    }
+    let mut large_groups = Vec::new();
+    for (_, mut entries) in per_terrain {
+        entries.sort_by(|a, b| b.1.cmp(&a.1));
+        for (rank_0, &(group_idx, _)) in entries.iter().enumerate() {
+            large_groups.push(LargeGroup {
+                group_idx,
+                rank: rank_0 + 1,
+            });
+        }
+    }
+    large_groups
+}
+
 /// Compute effects on open groups from placing next tile at `pos` with `rotation`.
 /// Accounts for merging: if multiple groups of the same terrain touch this position,
 /// they'd merge into one group.
@@ -362,7 +549,6 @@ fn large_group_effects(
     pos: HexPos,
     rotation: Rotation,
 ) -> Vec<GroupEffect> {
-    use std::collections::HashMap;
     use std::collections::HashSet;
 
     let mut by_terrain: HashMap<Terrain, Vec<&LargeGroup>> = HashMap::new();
@@ -513,7 +699,7 @@ pub fn constraints_at(map: &Map, pos: HexPos) -> [Option<Terrain>; HEX_SIDES] {
 }
 
 /// Count matching edges for a tile profile at constraints.
-fn count_matches(
+pub(crate) fn count_matches(
     profile: &crate::data::EdgeProfile,
     constraints: &[Option<Terrain>; HEX_SIDES],
 ) -> (u8, bool) {
@@ -532,41 +718,70 @@ fn count_matches(
     (matches, legal)
 }
 
-/// Compute the chance that a random tile from the frequency table fits given constraints.
-/// "Fits" means legal (no Suboptimal/Illegal edges).
-/// Returns (chance 0.0-1.0, number of unique fitting patterns).
-pub fn fit_chance_for_constraints(
+/// Whether a tile with this edge profile fits the constraints at any rotation.
+fn pattern_fits(
+    profile: &crate::data::EdgeProfile,
+    constraints: &[Option<Terrain>; HEX_SIDES],
+) -> bool {
+    (0..HEX_SIDES).any(|rot| count_matches(&profile.rotated(rot), constraints).1)
+}
+
+/// Whether a tile with this edge profile matches every constrained side at some rotation.
+pub(crate) fn pattern_fits_perfectly(
+    profile: &crate::data::EdgeProfile,
+    constraints: &[Option<Terrain>; HEX_SIDES],
+) -> bool {
+    let wanted = constraints.iter().flatten().count() as u8;
+    (0..HEX_SIDES).any(|rot| {
+        let (matches, legal) = count_matches(&profile.rotated(rot), constraints);
+        legal && matches == wanted
+    })
+}
+
+/// Count the tiles (and unique patterns) from the frequency table that fit given constraints.
+fn fit_counts_for_constraints(
     freqs: &TileFrequencies,
     constraints: &[Option<Terrain>; HEX_SIDES],
-) -> (f32, u16) {
+) -> (usize, u16) {
     let mut matching_count: usize = 0;
     let mut matching_unique: u16 = 0;
 
     for entry in &freqs.entries {
-        let profile = crate::data::EdgeProfile::from_segments(&entry.segments);
-        let mut counted = false;
-        for rot in 0..HEX_SIDES {
-            let rotated = profile.rotated(rot);
-            let (_matches, legal) = count_matches(&rotated, constraints);
-            if legal && !counted {
-                matching_unique += 1;
-                matching_count += entry.count;
-                counted = true;
-            }
+        if pattern_fits(&entry.edges.0, constraints) {
+            matching_unique += 1;
+            matching_count += entry.count;
         }
     }
+    (matching_count, matching_unique)
+}
 
-    let chance = if freqs.total_tiles > 0 {
+fn fit_chance_of_count(freqs: &TileFrequencies, matching_count: usize) -> f32 {
+    if freqs.total_tiles > 0 {
         matching_count as f32 / freqs.total_tiles as f32
     } else {
         0.0
-    };
-    (chance, matching_unique)
+    }
+}
+
+/// Compute the chance that a random tile from the frequency table fits given constraints.
+/// "Fits" means legal (no Suboptimal/Illegal edges).
+/// Returns (chance 0.0-1.0, number of unique fitting patterns).
+pub fn fit_chance_for_constraints(
+    freqs: &TileFrequencies,
+    constraints: &[Option<Terrain>; HEX_SIDES],
+) -> (f32, u16) {
+    let (matching_count, matching_unique) = fit_counts_for_constraints(freqs, constraints);
+    (fit_chance_of_count(freqs, matching_count), matching_unique)
 }
 
 /// Compute fit chance at `pos` from current map state.
-fn compute_fit_chance(map: &Map, freqs: &TileFrequencies, pos: HexPos) -> (f32, u16) {
-    fit_chance_for_constraints(freqs, &constraints_at(map, pos))
+fn compute_fit_chance(
+    map: &Map,
+    freqs: &TileFrequencies,
+    cache: &mut ThreadFitCache,
+    pos: HexPos,
+) -> (f32, u16) {
+    cache.get(freqs, &constraints_at(map, pos))
 }
 
 /// Compute how placing the next tile at `pos` with `rotation` changes the fit chance
@@ -574,6 +789,7 @@ fn compute_fit_chance(map: &Map, freqs: &TileFrequencies, pos: HexPos) -> (f32,
 fn compute_neighbor_fit_effects(
     map: &Map,
     freqs: &TileFrequencies,
+    cache: &mut ThreadFitCache,
     pos: HexPos,
     rotation: Rotation,
 ) -> Vec<NeighborFitEffect> {
@@ -593,14 +809,14 @@ fn compute_neighbor_fit_effects(
 
         // Before: current constraints at the neighbor.
         let constraints_before = constraints_at(map, neighbor_pos);
-        let (chance_before, _) = fit_chance_for_constraints(freqs, &constraints_before);
+        let (chance_before, _) = cache.get(freqs, &constraints_before);
 
         // After: same constraints plus the placed tile's edge on the facing side.
         let facing_side = Map::opposite_side(side);
         let mut constraints_after = constraints_before;
         constraints_after[facing_side] = Some(next_profile.at_index(side));
 
-        let (chance_after, _) = fit_chance_for_constraints(freqs, &constraints_after);
+        let (chance_after, _) = cache.get(freqs, &constraints_after);
 
         // Normally adding a constraint can only reduce options.
         // Edge case: neighbor at map boundary may have incomplete constraints.
@@ -615,53 +831,234 @@ fn compute_neighbor_fit_effects(
     effects
 }
 
+/// The next tile at `pos` with `rotation`, scored with everything `PlacementScore` holds.
+#[allow(clippy::too_many_arguments)]
+fn score_at(
+    map: &Map,
+    groups: &GroupAssignments,
+    freqs: &TileFrequencies,
+    large_groups: &[LargeGroup],
+    rules: &ScoreRules,
+    cache: &mut ThreadFitCache,
+    pos: HexPos,
+    rotation: Rotation,
+) -> Option<PlacementScore> {
+    let mut score = BestPlacements::score_of_next_at(map, groups, rules, pos, rotation)?;
+    score.group_effects = large_group_effects(map, groups, large_groups, pos, rotation);
+    (score.fit_chance, score.fit_unique) = compute_fit_chance(map, freqs, cache, pos);
+    score.neighbor_fit_effects = compute_neighbor_fit_effects(map, freqs, cache, pos, rotation);
+    Some(score)
+}
+
+/// Every legal rotation of the next tile at `pos`, best first.
+#[allow(clippy::too_many_arguments)]
+fn score_rotations(
+    map: &Map,
+    groups: &GroupAssignments,
+    freqs: &TileFrequencies,
+    large_groups: &[LargeGroup],
+    scorer: &dyn PlacementScorer,
+    rules: &ScoreRules,
+    cache: &mut ThreadFitCache,
+    pos: HexPos,
+) -> Vec<PlacementScore> {
+    let mut rotations: Vec<PlacementScore> = (0..HEX_SIDES)
+        .filter_map(|rotation| {
+            score_at(
+                map,
+                groups,
+                freqs,
+                large_groups,
+                rules,
+                cache,
+                pos,
+                rotation,
+            )
+        })
+        .collect();
+    // Among ties, the highest rotation comes first.
+    rotations.sort_by(|a, b| {
+        scorer
+            .compare(b, a)
+            .then_with(|| b.rotation.cmp(&a.rotation))
+    });
+    rotations
+}
+
 impl BestPlacements {
+    /// The one of `candidates` that `compute` would rank first with the `DefaultScorer`, without
+    /// scoring the whole frontier. Used to play simulated games the way the ranking would.
+    ///
+    /// The default order looks at the fit chance first, so only the cells that are hardest to
+    /// fill are scored in full. It does not look at the points, so they are valued by the
+    /// default rules.
+    pub(crate) fn default_best_of(
+        map: &Map,
+        groups: &GroupAssignments,
+        freqs: &TileFrequencies,
+        fit_cache: &mut FitChanceCache,
+        candidates: &[(HexPos, Rotation)],
+    ) -> Option<(HexPos, Rotation)> {
+        let shared = FitChanceCache::default();
+        let mut cache = ThreadFitCache {
+            shared: &shared,
+            local: fit_cache,
+        };
+        let legal: Vec<(HexPos, Rotation, u32)> = candidates
+            .iter()
+            .filter(|&&(pos, rotation)| {
+                !would_create_split(map, pos) && count_matching_edges(map, pos, rotation).is_some()
+            })
+            .map(|&(pos, rotation)| {
+                let (chance, _) = compute_fit_chance(map, freqs, &mut cache, pos);
+                (pos, rotation, fit_key(chance))
+            })
+            .collect();
+        let hardest = legal.iter().map(|&(_, _, key)| key).min()?;
+
+        let large_groups = large_groups_of(groups);
+        let rules = ScoreRules::default();
+        legal
+            .iter()
+            .filter(|&&(_, _, key)| key == hardest)
+            .filter_map(|&(pos, rotation, _)| {
+                score_at(
+                    map,
+                    groups,
+                    freqs,
+                    &large_groups,
+                    &rules,
+                    &mut cache,
+                    pos,
+                    rotation,
+                )
+            })
+            // Among ties, the highest rotation wins, as in `score_rotations`.
+            .max_by(|a, b| a.cmp(b).then(a.rotation.cmp(&b.rotation)))
+            .map(|score| (score.pos, score.rotation))
+    }
+}
+
+impl BestPlacements {
+    /// Placements ranked by the default scorer, with points valued by the default rules.
     pub fn compute(map: &Map, groups: &GroupAssignments, freqs: &TileFrequencies) -> Self {
-        // Collect all open groups with more than MIN_GROUP_SIZE tiles,
-        // ranked per terrain by unit count (1 = largest).
-        use std::collections::HashMap;
-        let mut per_terrain: HashMap<Terrain, Vec<(usize, u32)>> = HashMap::new();
-        for (idx, group) in groups.groups.iter().enumerate() {
-            if group.is_closed() {
-                continue;
-            }
-            per_terrain
-                .entry(group.terrain)
-                .or_default()
-                .push((idx, group.unit_count));
-        }
-        let mut large_groups = Vec::new();
-        for (_, mut entries) in per_terrain {
-            entries.sort_by(|a, b| b.1.cmp(&a.1));
-            for (rank_0, &(group_idx, _)) in entries.iter().enumerate() {
-                large_groups.push(LargeGroup {
-                    group_idx,
-                    rank: rank_0 + 1,
-                });
-            }
-        }
+        Self::compute_with(
+            map,
+            groups,
+            freqs,
+            Box::new(DefaultScorer),
+            ScoreRules::default(),
+        )
+    }
 
-        let mut best_placements = BTreeSet::default();
+    /// Like `compute`, ranking placements with `scorer` and valuing points with `rules`.
+    pub fn compute_with(
+        map: &Map,
+        groups: &GroupAssignments,
+        freqs: &TileFrequencies,
+        scorer: Box<dyn PlacementScorer>,
+        rules: ScoreRules,
+    ) -> Self {
+        let mut placements = Self {
+            scorer,
+            rules,
+            ..Self::default()
+        };
+        placements.rescore(map, groups, freqs);
+        placements
+    }
 
-        for pos in &groups.possible_placements {
-            let best_rotation = (0..HEX_SIDES)
-                .filter_map(|rotation| {
-                    let mut score = BestPlacements::score_of_next_at(map, groups, *pos, rotation)?;
-                    score.group_effects =
-                        large_group_effects(map, groups, &large_groups, *pos, rotation);
-                    Some(score)
-                })
-                .max();
-            if let Some(mut score) = best_rotation {
-                let (chance, unique) = compute_fit_chance(map, freqs, *pos);
-                score.fit_chance = chance;
-                score.fit_unique = unique;
-                score.neighbor_fit_effects =
-                    compute_neighbor_fit_effects(map, freqs, *pos, score.rotation);
-                best_placements.insert(score);
+    /// Rank the placements with another scorer. The scorer also picks the rotation at each
+    /// position, so everything is scored again.
+    pub fn set_scorer(
+        &mut self,
+        scorer: Box<dyn PlacementScorer>,
+        map: &Map,
+        groups: &GroupAssignments,
+        freqs: &TileFrequencies,
+    ) {
+        self.scorer = scorer;
+        self.rescore(map, groups, freqs);
+    }
+
+    pub fn rules(&self) -> &ScoreRules {
+        &self.rules
+    }
+
+    /// Value points with other rules from the next `update` on.
+    pub fn set_rules(&mut self, rules: ScoreRules) {
+        self.rules = rules;
+    }
+
+    /// Bring the placements up to date after an incremental map update. `changes` are the
+    /// ones `TileFrequencies::update` returned for `freqs`.
+    ///
+    /// The next tile changes with every placement, so all frontier positions are scored again.
+    /// The expensive part, the fit chances, comes from the cache.
+    pub fn update(
+        &mut self,
+        map: &Map,
+        groups: &GroupAssignments,
+        freqs: &TileFrequencies,
+        changes: &[FrequencyChange],
+    ) {
+        self.fit_cache.apply(changes);
+        self.rescore(map, groups, freqs);
+    }
+
+    fn rescore(&mut self, map: &Map, groups: &GroupAssignments, freqs: &TileFrequencies) {
+        let large_groups = large_groups_of(groups);
+
+        // Positions are scored on all threads. Sorted, so that the result does not depend on
+        // the hash order of the frontier or on how the work is split.
+        let mut frontier: Vec<HexPos> = groups.possible_placements.iter().copied().collect();
+        frontier.sort_by_key(|pos| (pos.x(), pos.y()));
+        let shared = &self.fit_cache;
+        let scorer = self.scorer.as_ref();
+        let rules = &self.rules;
+        let chunks: Vec<(Vec<Vec<PlacementScore>>, FitChanceCache)> = frontier
+            .par_iter()
+            .fold(
+                || (Vec::new(), FitChanceCache::default()),
+                |(mut scored, mut local), &pos| {
+                    let mut cache = ThreadFitCache {
+                        shared,
+                        local: &mut local,
+                    };
+                    let rotations = score_rotations(
+                        map,
+                        groups,
+                        freqs,
+                        &large_groups,
+                        scorer,
+                        rules,
+                        &mut cache,
+                        pos,
+                    );
+                    if !rotations.is_empty() {
+                        scored.push(rotations);
+                    }
+                    (scored, local)
+                },
+            )
+            .collect();
+
+        let mut placements = Vec::new();
+        let mut positions = HashMap::new();
+        let mut best = Vec::new();
+        for (scored, local) in chunks {
+            self.fit_cache.merge(local);
+            for rotations in scored {
+                let pos = rotations[0].pos;
+                best.push(placements.len());
+                positions.insert(pos, placements.len()..placements.len() + rotations.len());
+                placements.extend(rotations);
             }
         }
 
-        Self { best_placements }
+        best.sort_by(|&a, &b| self.scorer.compare(&placements[b], &placements[a]));
+        self.placements = placements;
+        self.positions = positions;
+        self.best = best;
     }
 }
@@ -0,0 +1,665 @@
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use comfy_table::{presets::NOTHING, Cell, CellAlignment, Table};
+use dorfromantische2_rs::{
+    best_placements::BestPlacements,
+    data::{GroupKind, HexPos, HEX_SIDES},
+    demand::Demand,
+    export::Export,
+    group::Group,
+    hex,
+    holes::Holes,
+    lookahead::{Lookahead, LookaheadStep},
+    map::{Quest, QuestType},
+    quest_feasibility,
+    raw_data::SaveGame,
+    replay::Review,
+    rollout::{RankedPolicy, RolloutConfig, Rollouts},
+    score::ScoreRules,
+    scorer::{DefaultScorer, PlacementScorer, WeightedScorer},
+    survival::{GameStats, SurvivalModel, TurnEstimate},
+    tile_frequency::{EdgePattern, TileFrequencies},
+    timeline::{Recorded, Timeline},
+};
+
+use crate::{Analysis, CliError, Command};
+
+pub fn run(analysis: &Analysis, command: &Command, out: &mut impl Write) -> Result<(), CliError> {
+    let result = match *command {
+        Command::Quests => quests(analysis, out),
+        Command::Groups { closed } => groups(analysis, closed, out),
+        Command::Placements { count, ref weights } => {
+            let scorer: Box<dyn PlacementScorer> = match weights {
+                Some(path) => Box::new(WeightedScorer::load(path)?),
+                None => Box::new(DefaultScorer),
+            };
+            placements(analysis, count, scorer, out)
+        }
+        Command::Lookahead { depth, width } => lookahead(analysis, depth, width, out),
+        Command::Rollouts { config } => rollouts(analysis, config, out),
+        Command::Survival { count } => survival(analysis, count, out),
+        Command::Holes => holes(analysis, out),
+        Command::Demand { count } => demand(analysis, count, out),
+        Command::Tile { pos } => tile(analysis, pos, out),
+        Command::Frequencies => frequencies(analysis, out),
+        Command::Stats => stats(analysis, out),
+        Command::Export => export(analysis, out),
+        Command::Review { ref earlier, csv } => review(&timeline_of(analysis, earlier)?, csv, out),
+    };
+    Ok(result?)
+}
+
+fn table(header: &[&str]) -> Table {
+    let mut table = Table::new();
+    table.load_preset(NOTHING).set_header(header.to_vec());
+    // Everything but the first column holds numbers.
+    for column in table.column_iter_mut().skip(1) {
+        column.set_cell_alignment(CellAlignment::Right);
+    }
+    table
+}
+
+/// Placements ranked by `scorer`, with points valued by the rules of the analyzed game.
+fn best_placements(
+    analysis: &Analysis,
+    freqs: &TileFrequencies,
+    scorer: Box<dyn PlacementScorer>,
+) -> BestPlacements {
+    let rules = ScoreRules::from(&analysis.savegame);
+    BestPlacements::compute_with(&analysis.map, &analysis.groups, freqs, scorer, rules)
+}
+
+fn quest_label(quest: &Quest) -> String {
+    format!("{} {}", quest.quest_type.label(), quest.target_value)
+}
+
+fn quests(analysis: &Analysis, out: &mut impl Write) -> io::Result<()> {
+    let Analysis { map, groups, .. } = analysis;
+    let freqs = TileFrequencies::from_map(map);
+    let mut quests = quest_feasibility::analyze(map, groups, &freqs);
+    quests.sort_by_key(|quest| {
+        (
+            groups.groups[quest.group].kind as usize,
+            quest.remaining,
+            quest.quest.quest_id,
+        )
+    });
+
+    let mut table = table(&[
+        "Terrain",
+        "Quest",
+        "Units",
+        "Left",
+        "Open edges",
+        "Gain",
+        "Status",
+    ]);
+    for quest in &quests {
+        let left = if quest.quest.quest_type == QuestType::Flag && quest.remaining <= 0 {
+            "close".to_string()
+        } else {
+            quest.remaining.to_string()
+        };
+        table.add_row(vec![
+            Cell::new(format!("{:?}", groups.groups[quest.group].kind)),
+            Cell::new(quest_label(&quest.quest)),
+            Cell::new(quest.units),
+            Cell::new(left),
+            Cell::new(quest.open_edges),
+            Cell::new(match quest.gain {
+                Some((min, max)) => format!("{min}-{max}"),
+                None => String::new(),
+            }),
+            Cell::new(quest.label()),
+        ]);
+    }
+    writeln!(out, "{table}")?;
+
+    let fulfilled = quests.iter().filter(|quest| quest.remaining <= 0).count();
+    let lost = quests.iter().filter(|quest| !quest.is_feasible()).count();
+    writeln!(
+        out,
+        "\n{} active quests, {fulfilled} fulfilled, {lost} lost",
+        quests.len()
+    )
+}
+
+fn groups(analysis: &Analysis, closed: bool, out: &mut impl Write) -> io::Result<()> {
+    let mut groups: Vec<(usize, &Group)> = analysis
+        .groups
+        .groups
+        .iter()
+        .enumerate()
+        .filter(|(_, group)| closed || !group.is_closed())
+        .collect();
+    groups.sort_by_key(|&(index, group)| (std::cmp::Reverse(group.unit_count), index));
+
+    let mut table = table(&["Kind", "Group", "Units", "Segments", "Open edges", "Quests"]);
+    table
+        .column_mut(5)
+        .expect("Quests column")
+        .set_cell_alignment(CellAlignment::Left);
+    for (index, group) in groups {
+        let quests: Vec<String> = group
+            .quests
+            .iter()
+            .filter(|quest| quest.active)
+            .map(quest_label)
+            .collect();
+        table.add_row(vec![
+            Cell::new(format!("{:?}", group.kind)),
+            Cell::new(index),
+            Cell::new(group.unit_count),
+            Cell::new(group.segment_indices.len()),
+            Cell::new(if group.is_closed() {
+                "closed".to_string()
+            } else {
+                group.open_edges.len().to_string()
+            }),
+            Cell::new(quests.join(", ")),
+        ]);
+    }
+    writeln!(out, "{table}")
+}
+
+fn placements(
+    analysis: &Analysis,
+    count: usize,
+    scorer: Box<dyn PlacementScorer>,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let freqs = TileFrequencies::from_map(&analysis.map);
+    let placements = best_placements(analysis, &freqs, scorer);
+
+    let mut table = table(&[
+        "Position",
+        "Rotation",
+        "Matching",
+        "Fit chance",
+        "Patterns",
+        "Points",
+    ]);
+    for score in placements.iter_best().take(count) {
+        let points = match score.points.tiles {
+            0 => score.points.points.to_string(),
+            tiles => format!("{} +{tiles} tiles", score.points.points),
+        };
+        table.add_row(vec![
+            Cell::new(format!("({}, {})", score.pos.x(), score.pos.y())),
+            Cell::new(score.rotation),
+            Cell::new(format!("{}/{HEX_SIDES}", score.matching_edges)),
+            Cell::new(format!("{:.1}%", score.fit_chance * 100.0)),
+            Cell::new(score.fit_unique),
+            Cell::new(points),
+        ]);
+    }
+    writeln!(out, "{table}")
+}
+
+fn lookahead(
+    analysis: &Analysis,
+    depth: usize,
+    width: usize,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let freqs = TileFrequencies::from_map(&analysis.map);
+    let lookahead = Lookahead::search(&analysis.map, &analysis.groups, &freqs, depth, width);
+
+    let step_rows = |table: &mut Table, label: &str, steps: &[LookaheadStep]| {
+        for (index, step) in steps.iter().enumerate() {
+            table.add_row(vec![
+                Cell::new(if index == 0 { label } else { "" }),
+                Cell::new(index + 1),
+                Cell::new(format!("({}, {})", step.pos.x(), step.pos.y())),
+                Cell::new(step.rotation),
+                Cell::new(format!("{}/{HEX_SIDES}", step.value.matching_edges)),
+                Cell::new(if step.value.perfect { "yes" } else { "" }),
+                Cell::new(step.value.dead_cells),
+                Cell::new(step.value.total()),
+            ]);
+        }
+    };
+    let mut table = table(&[
+        "", "Tile", "Position", "Rotation", "Matching", "Perfect", "Dead", "Value",
+    ]);
+    step_rows(&mut table, "Best", &lookahead.sequence);
+    step_rows(&mut table, "Greedy", &lookahead.greedy);
+    writeln!(out, "{table}")?;
+    writeln!(
+        out,
+        "\n{} of {} known tiles placed, value {} vs {} greedy (+{})",
+        lookahead.sequence.len(),
+        analysis.map.tile_queue.len(),
+        lookahead.total(),
+        lookahead.greedy_total(),
+        lookahead.improvement()
+    )
+}
+
+fn rollouts(analysis: &Analysis, config: RolloutConfig, out: &mut impl Write) -> io::Result<()> {
+    let Analysis { map, groups, .. } = analysis;
+    let freqs = TileFrequencies::from_map(map);
+    let placements = best_placements(analysis, &freqs, Box::new(DefaultScorer));
+    let rollouts = Rollouts::run(map, groups, &freqs, &placements, config, &RankedPolicy);
+
+    let mut table = table(&[
+        "Position",
+        "Rotation",
+        "Rank",
+        "Fit chance",
+        "Value",
+        "Perfect",
+        "Dead",
+        "Stuck",
+    ]);
+    for result in &rollouts.results {
+        table.add_row(vec![
+            Cell::new(format!("({}, {})", result.pos.x(), result.pos.y())),
+            Cell::new(result.rotation),
+            Cell::new(result.rank + 1),
+            Cell::new(format!("{:.1}%", result.fit_chance * 100.0)),
+            Cell::new(format!("{:.2}", result.mean_value)),
+            Cell::new(format!("{:.2}", result.mean_perfect)),
+            Cell::new(format!("{:.2}", result.mean_dead_cells)),
+            Cell::new(format!("{:.0}%", result.stuck_rate * 100.0)),
+        ]);
+    }
+    writeln!(out, "{table}")?;
+    writeln!(
+        out,
+        "\n{} rollouts of {} turns per placement, seed {}",
+        config.rollouts, config.turns, config.seed
+    )
+}
+
+fn holes(analysis: &Analysis, out: &mut impl Write) -> io::Result<()> {
+    let freqs = TileFrequencies::from_map(&analysis.map);
+    let holes = Holes::find(&analysis.map, &freqs);
+
+    let mut table = table(&["Position", "Cells", "Min fit chance", "Patterns", "Status"]);
+    for hole in &holes.holes {
+        let first = hole.cells[0].pos;
+        // Of the hardest cell.
+        let patterns = hole
+            .cells
+            .iter()
+            .map(|cell| cell.fit_unique)
+            .min()
+            .unwrap_or_default();
+        let status = if !hole.is_fillable() {
+            "unfillable"
+        } else if !hole.can_fill_perfectly() {
+            "imperfect"
+        } else {
+            ""
+        };
+        table.add_row(vec![
+            Cell::new(format!("({}, {})", first.x(), first.y())),
+            Cell::new(hole.size()),
+            Cell::new(format!("{:.1}%", hole.min_fit_chance() * 100.0)),
+            Cell::new(patterns),
+            Cell::new(status),
+        ]);
+    }
+    writeln!(out, "{table}")?;
+    writeln!(
+        out,
+        "\n{} holes with {} cells, {} can never be filled perfectly",
+        holes.holes.len(),
+        holes.cell_count(),
+        holes.imperfect().count()
+    )
+}
+
+fn demand(analysis: &Analysis, count: usize, out: &mut impl Write) -> io::Result<()> {
+    let Analysis { map, groups, .. } = analysis;
+    let freqs = TileFrequencies::from_map(map);
+    let demand = Demand::compute(map, groups, &freqs);
+    let served = demand.served_cells();
+
+    let mut patterns = table(&["Edges", "Cells", "Demand", "Supply", "Pressure"]);
+    for need in demand.patterns.iter().take(count) {
+        patterns.add_row(vec![
+            Cell::new(edges_label(&need.pattern)),
+            Cell::new(need.cells),
+            Cell::new(format!(
+                "{:.1}%",
+                need.demand / served.max(1) as f32 * 100.0
+            )),
+            Cell::new(format!("{:.2}%", need.supply * 100.0)),
+            Cell::new(format!("{:.2}", need.pressure(served))),
+        ]);
+    }
+    writeln!(out, "{patterns}")?;
+
+    let mut cells = table(&["Position", "Patterns", "Supply"]);
+    for cell in demand.cells.iter().take(count) {
+        cells.add_row(vec![
+            Cell::new(format!("({}, {})", cell.pos.x(), cell.pos.y())),
+            Cell::new(cell.patterns.len()),
+            Cell::new(format!("{:.2}%", cell.supply * 100.0)),
+        ]);
+    }
+    writeln!(out, "\n{cells}")?;
+    writeln!(
+        out,
+        "\n{} frontier cells, {} no known pattern fits perfectly",
+        demand.cells.len(),
+        demand.cells.len() - served
+    )
+}
+
+fn tile(analysis: &Analysis, center: HexPos, out: &mut impl Write) -> io::Result<()> {
+    let Analysis { map, groups, .. } = analysis;
+    let positions =
+        std::iter::once(center).chain((0..HEX_SIDES).map(|r| hex::neighbor_pos_of(center, r)));
+
+    for pos in positions {
+        let marker = if pos == center { " <<<" } else { "" };
+        if !map.has(pos) {
+            let state = if map.preplaced_at(pos).is_some() {
+                "preplaced"
+            } else if groups.possible_placements.contains(&pos) {
+                "open"
+            } else {
+                "empty"
+            };
+            writeln!(out, "({}, {}) {state}{marker}", pos.x(), pos.y())?;
+            continue;
+        }
+
+        writeln!(out, "({}, {}){marker}", pos.x(), pos.y())?;
+        for segment_index in map.segment_indices_at(pos).into_iter().flatten() {
+            let segment = map.segment(segment_index);
+            let group = match groups.group_of(segment_index) {
+                Some(group_index) => {
+                    let group = &groups.groups[group_index];
+                    format!(
+                        "group {group_index} ({:?}, {} units, {})",
+                        group.kind,
+                        group.unit_count,
+                        if group.is_closed() {
+                            "closed".to_string()
+                        } else {
+                            format!("{} open edges", group.open_edges.len())
+                        }
+                    )
+                }
+                None => "no group".to_string(),
+            };
+            writeln!(
+                out,
+                "  {:?} {:?} rotation={} units={} | {group}",
+                segment.terrain, segment.form, segment.rotation, segment.unit_count
+            )?;
+        }
+        if let Some(quest) = map.quests.get(&pos) {
+            writeln!(
+                out,
+                "  quest: {:?} {} active={}",
+                quest.terrain,
+                quest_label(quest),
+                quest.active
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn edges_label(edges: &EdgePattern) -> String {
+    (0..HEX_SIDES)
+        .map(|side| format!("{:?}", edges.0.at_index(side)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn frequencies(analysis: &Analysis, out: &mut impl Write) -> io::Result<()> {
+    let freqs = TileFrequencies::from_map(&analysis.map);
+
+    let mut table = table(&["Edges", "Count", "Share"]);
+    for entry in &freqs.entries {
+        table.add_row(vec![
+            Cell::new(edges_label(&entry.edges)),
+            Cell::new(entry.count),
+            Cell::new(format!("{:.2}%", entry.fraction * 100.0)),
+        ]);
+    }
+    writeln!(out, "{table}")?;
+    writeln!(
+        out,
+        "\n{} patterns over {} tiles",
+        freqs.entries.len(),
+        freqs.total_tiles
+    )
+}
+
+fn stats(analysis: &Analysis, out: &mut impl Write) -> io::Result<()> {
+    let Analysis {
+        savegame,
+        map,
+        groups,
+    } = analysis;
+    let open_groups = |kind: GroupKind| {
+        groups
+            .groups
+            .iter()
+            .filter(|group| group.kind == kind && !group.is_closed())
+            .count()
+    };
+    let active_quests = map.quests.values().filter(|quest| quest.active).count();
+
+    let mut table = Table::new();
+    table.load_preset(NOTHING);
+    let rows: Vec<(&str, String)> = vec![
+        ("Score", savegame.score.to_string()),
+        ("Level", savegame.level.to_string()),
+        ("Placed tiles", savegame.tiles.len().to_string()),
+        ("Tiles in stack", map.tile_stack_count.to_string()),
+        (
+            "Perfect placements",
+            savegame.perfect_placements.to_string(),
+        ),
+        ("Quests fulfilled", savegame.quests_fulfilled.to_string()),
+        ("Quests failed", savegame.quests_failed.to_string()),
+        ("Active quests", active_quests.to_string()),
+        ("Groups", groups.groups.len().to_string()),
+        (
+            "Open groups",
+            [
+                GroupKind::House,
+                GroupKind::Forest,
+                GroupKind::Wheat,
+                GroupKind::Rail,
+                GroupKind::River,
+            ]
+            .map(|kind| format!("{kind:?} {}", open_groups(kind)))
+            .join(", "),
+        ),
+        (
+            "Possible placements",
+            groups.possible_placements.len().to_string(),
+        ),
+        ("Preplaced tiles", map.preplaced_tiles.len().to_string()),
+        ("Playtime", format!("{:.0} min", savegame.playtime / 60.0)),
+    ];
+    for (name, value) in rows {
+        table.add_row(vec![Cell::new(name), Cell::new(value)]);
+    }
+    writeln!(out, "{table}")
+}
+
+fn turns_label(estimate: &TurnEstimate) -> String {
+    match estimate.turns {
+        Some(turns) => format!("{turns:.1}"),
+        None => "stack grows".to_string(),
+    }
+}
+
+fn survival(analysis: &Analysis, count: usize, out: &mut impl Write) -> io::Result<()> {
+    let Analysis {
+        savegame,
+        map,
+        groups,
+    } = analysis;
+    let model = SurvivalModel::new(&GameStats::from(savegame), ScoreRules::from(savegame));
+    let now = model.estimate(map.tile_stack_count, groups);
+
+    let mut overview = Table::new();
+    overview.load_preset(NOTHING);
+    let rows: Vec<(&str, String)> = vec![
+        ("Tiles in stack", now.tiles_left.to_string()),
+        (
+            "Perfect rate",
+            format!("{:.1}%", model.perfect_rate * 100.0),
+        ),
+        ("Quests per tile", format!("{:.3}", model.quest_rate)),
+        ("Stack change per turn", format!("{:+.2}", now.drift)),
+        ("Pending quest tiles", format!("{:.1}", now.pending_tiles)),
+        ("Expected turns", turns_label(&now)),
+        ("Game-over risk", format!("{:.1}%", now.risk * 100.0)),
+    ];
+    for (name, value) in rows {
+        overview.add_row(vec![Cell::new(name), Cell::new(value)]);
+    }
+    writeln!(out, "{overview}\n")?;
+
+    let pending = model.pending_quests(groups);
+    if !pending.is_empty() {
+        let mut table = table(&["Pending quest", "Remaining", "Chance", "Tiles"]);
+        for quest in &pending {
+            table.add_row(vec![
+                Cell::new(format!(
+                    "{:?} {}",
+                    quest.quest.terrain,
+                    quest_label(&quest.quest)
+                )),
+                Cell::new(quest.remaining),
+                Cell::new(format!("{:.0}%", quest.chance * 100.0)),
+                Cell::new(quest.tiles),
+            ]);
+        }
+        writeln!(out, "{table}\n")?;
+    }
+
+    let freqs = TileFrequencies::from_map(map);
+    let placements = best_placements(analysis, &freqs, Box::new(DefaultScorer));
+    let mut table = table(&["Position", "Rotation", "Tiles", "Turns", "Risk"]);
+    for score in placements.iter_best().take(count) {
+        let after = model.estimate_after(map.tile_stack_count, groups, score);
+        table.add_row(vec![
+            Cell::new(format!("({}, {})", score.pos.x(), score.pos.y())),
+            Cell::new(score.rotation),
+            Cell::new(format!("{:+}", after.tiles_left - now.tiles_left)),
+            Cell::new(match after.turns_delta(&now) {
+                Some(delta) => format!("{delta:+.1}"),
+                None => turns_label(&after),
+            }),
+            Cell::new(format!("{:.1}%", after.risk * 100.0)),
+        ]);
+    }
+    writeln!(out, "{table}")
+}
+
+fn export(analysis: &Analysis, out: &mut impl Write) -> io::Result<()> {
+    let Analysis {
+        savegame,
+        map,
+        groups,
+    } = analysis;
+    let freqs = TileFrequencies::from_map(map);
+    let placements = best_placements(analysis, &freqs, Box::new(DefaultScorer));
+    let export = Export::new(savegame, map, groups, &freqs, &placements);
+    writeln!(out, "{}", export.to_json())
+}
+
+/// The earlier saves followed by the analyzed one, which must all belong to the same game.
+fn timeline_of(analysis: &Analysis, earlier: &[PathBuf]) -> Result<Timeline, CliError> {
+    let mut timeline = Timeline::default();
+    for (index, path) in earlier.iter().enumerate() {
+        if timeline.record(&SaveGame::load(path)?) == Recorded::NewGame && index > 0 {
+            return Err(CliError::Usage(format!(
+                "{} does not continue the game of {}",
+                path.display(),
+                earlier[index - 1].display()
+            )));
+        }
+    }
+    if timeline.record(&analysis.savegame) == Recorded::NewGame {
+        return Err(CliError::Usage(format!(
+            "The savegame does not continue the game of {}",
+            earlier.last().unwrap().display()
+        )));
+    }
+    Ok(timeline)
+}
+
+fn review(timeline: &Timeline, csv: bool, out: &mut impl Write) -> io::Result<()> {
+    let review = Review::of(timeline, Box::new(DefaultScorer));
+    if csv {
+        write!(out, "{}", review.to_csv())
+    } else {
+        writeln!(out, "{}", review.to_json())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_command_prints() {
+        let path = std::path::Path::new("tests/fixtures/dorfromantik.dump");
+        if !path.exists() {
+            eprintln!("Skipping test: fixture {} not found", path.display());
+            return;
+        }
+        let analysis = Analysis::load(path).unwrap();
+        for (command, expected) in [
+            (Command::Quests, "active quests"),
+            (Command::Groups { closed: true }, "closed"),
+            (
+                Command::Placements {
+                    count: 3,
+                    weights: None,
+                },
+                "Fit chance",
+            ),
+            (Command::Lookahead { depth: 2, width: 4 }, "greedy (+"),
+            (
+                Command::Rollouts {
+                    config: RolloutConfig {
+                        candidates: 2,
+                        rollouts: 4,
+                        ..RolloutConfig::default()
+                    },
+                },
+                "rollouts of 6 turns",
+            ),
+            (Command::Survival { count: 3 }, "Game-over risk"),
+            (
+                Command::Tile {
+                    pos: HexPos::new(0, 1),
+                },
+                "(0, 1) <<<",
+            ),
+            (Command::Frequencies, "patterns over"),
+            (Command::Stats, "Tiles in stack"),
+            (Command::Export, "\"version\": "),
+            (
+                Command::Review {
+                    earlier: vec![path.to_path_buf()],
+                    csv: true,
+                },
+                "step,x,y,rotation",
+            ),
+        ] {
+            let mut out = Vec::new();
+            run(&analysis, &command, &mut out).unwrap();
+            let out = String::from_utf8(out).unwrap();
+            assert!(out.contains(expected), "{command:?}:\n{out}");
+        }
+    }
+}
@@ -0,0 +1,435 @@
+//! Headless analysis of a savegame, for scripting without the GUI.
+//! Run with: cargo run --bin dorf -- <savegame> <command> [args]
+
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use dorfromantische2_rs::{
+    data::HexPos,
+    group_assignments::GroupAssignments,
+    lookahead::{DEFAULT_BEAM_WIDTH, DEFAULT_DEPTH},
+    map::Map,
+    raw_data::{LoadError, SaveGame},
+    rollout::RolloutConfig,
+    scorer::ScorerError,
+};
+
+mod commands;
+
+const USAGE: &str = "\
+Usage: dorf <savegame> <command> [args]
+
+Commands:
+  quests              Active quests and the progress of their groups
+  groups [--closed]   Open groups by size, including closed ones with --closed
+  placements [count] [--weights <file>]
+                      Best placements for the next tile (default 10), ranked by the
+                      weighted sum in the TOML file if given
+  lookahead [depth] [width]
+                      Best placements for the known tiles together, compared with
+                      greedy play (default 3 tiles, 8 sequences kept per tile)
+  rollouts [candidates] [turns] [rollouts] [seed]
+                      Rate the best placements by playing random tiles after them
+                      (default 5 placements, 6 turns, 32 rollouts each, seed 2)
+  survival [count]    Expected remaining turns and game-over risk, and how the best
+                      placements change them (default 10)
+  holes               Empty regions enclosed by placed tiles and how well they can be
+                      filled
+  demand [count]      Patterns the open edges need most compared with how often they
+                      come up, and the cells hardest to serve (default 10)
+  tile <x> <y>        Segments, groups and quests at and around a hex position
+  frequencies         How often each edge pattern has been placed
+  stats               Overview of the game
+  export              Versioned JSON of tiles, groups, placements, frequencies and holes
+  review <earlier>... [--csv]
+                      Compare the tiles placed since the earlier saves of the game (oldest
+                      first) with the recommended placements, as JSON or CSV";
+
+const DEFAULT_PLACEMENTS: usize = 10;
+
+/// Why `dorf` could not do what it was asked.
+#[derive(Debug)]
+pub enum CliError {
+    /// The command line is malformed.
+    Usage(String),
+    Load(LoadError),
+    Scorer(ScorerError),
+    Output(std::io::Error),
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::Usage(message) => write!(f, "{message}"),
+            CliError::Load(e) => write!(f, "{e}"),
+            CliError::Scorer(e) => write!(f, "{e}"),
+            CliError::Output(e) => write!(f, "Failed to write output: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl From<LoadError> for CliError {
+    fn from(error: LoadError) -> Self {
+        CliError::Load(error)
+    }
+}
+
+impl From<ScorerError> for CliError {
+    fn from(error: ScorerError) -> Self {
+        CliError::Scorer(error)
+    }
+}
+
+impl From<std::io::Error> for CliError {
+    fn from(error: std::io::Error) -> Self {
+        CliError::Output(error)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Quests,
+    Groups {
+        closed: bool,
+    },
+    Placements {
+        count: usize,
+        /// Rank by the weighted sum from this file instead of the default order.
+        weights: Option<PathBuf>,
+    },
+    Lookahead {
+        depth: usize,
+        width: usize,
+    },
+    Rollouts {
+        config: RolloutConfig,
+    },
+    Survival {
+        count: usize,
+    },
+    Holes,
+    Demand {
+        count: usize,
+    },
+    Tile {
+        pos: HexPos,
+    },
+    Frequencies,
+    Stats,
+    Export,
+    Review {
+        /// Earlier saves of the same game, oldest first.
+        earlier: Vec<PathBuf>,
+        csv: bool,
+    },
+}
+
+/// The savegame and the analysis every command is based on.
+pub struct Analysis {
+    pub savegame: SaveGame,
+    pub map: Map,
+    pub groups: GroupAssignments,
+}
+
+impl Analysis {
+    pub fn load(path: &Path) -> Result<Self, LoadError> {
+        let savegame = SaveGame::load(path)?;
+        let map = Map::from(&savegame);
+        let groups = GroupAssignments::from(&map);
+        Ok(Self {
+            savegame,
+            map,
+            groups,
+        })
+    }
+}
+
+fn required<'a>(name: &str, value: Option<&'a String>) -> Result<&'a str, CliError> {
+    value
+        .map(String::as_str)
+        .ok_or_else(|| CliError::Usage(format!("Missing <{name}>")))
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, CliError> {
+    value
+        .parse()
+        .map_err(|_| CliError::Usage(format!("Invalid <{name}>: {value}")))
+}
+
+fn parse_args(args: &[String]) -> Result<(PathBuf, Command), CliError> {
+    let (path, command, rest) = match args {
+        [path, command, rest @ ..] => (PathBuf::from(path), command.as_str(), rest),
+        [_] => return Err(CliError::Usage("Missing <command>".to_string())),
+        [] => return Err(CliError::Usage("Missing <savegame>".to_string())),
+    };
+
+    let command = match command {
+        "quests" => Command::Quests,
+        "groups" => match rest {
+            [] => Command::Groups { closed: false },
+            [flag] if flag == "--closed" => Command::Groups { closed: true },
+            _ => return Err(CliError::Usage(format!("Invalid arguments: {rest:?}"))),
+        },
+        "placements" => {
+            let (weights, count) = match rest {
+                [flag, path, count @ ..] | [count @ .., flag, path] if flag == "--weights" => {
+                    (Some(PathBuf::from(path)), count)
+                }
+                _ => (None, rest),
+            };
+            Command::Placements {
+                count: match count {
+                    [] => DEFAULT_PLACEMENTS,
+                    [count] => parse_number("count", count)?,
+                    _ => return Err(CliError::Usage(format!("Invalid arguments: {rest:?}"))),
+                },
+                weights,
+            }
+        }
+        "lookahead" => Command::Lookahead {
+            depth: rest
+                .first()
+                .map(|depth| parse_number("depth", depth))
+                .transpose()?
+                .unwrap_or(DEFAULT_DEPTH),
+            width: rest
+                .get(1)
+                .map(|width| parse_number("width", width))
+                .transpose()?
+                .unwrap_or(DEFAULT_BEAM_WIDTH),
+        },
+        "rollouts" => {
+            let defaults = RolloutConfig::default();
+            let arg = |index: usize, name: &str, default: usize| -> Result<usize, CliError> {
+                rest.get(index)
+                    .map(|value| parse_number(name, value))
+                    .transpose()
+                    .map(|value| value.unwrap_or(default))
+            };
+            Command::Rollouts {
+                config: RolloutConfig {
+                    candidates: arg(0, "candidates", defaults.candidates)?,
+                    turns: arg(1, "turns", defaults.turns)?,
+                    rollouts: arg(2, "rollouts", defaults.rollouts)?,
+                    seed: rest
+                        .get(3)
+                        .map(|seed| parse_number("seed", seed))
+                        .transpose()?
+                        .unwrap_or(defaults.seed),
+                },
+            }
+        }
+        "survival" => Command::Survival {
+            count: rest
+                .first()
+                .map(|count| parse_number("count", count))
+                .transpose()?
+                .unwrap_or(DEFAULT_PLACEMENTS),
+        },
+        "holes" => Command::Holes,
+        "demand" => Command::Demand {
+            count: rest
+                .first()
+                .map(|count| parse_number("count", count))
+                .transpose()?
+                .unwrap_or(DEFAULT_PLACEMENTS),
+        },
+        "tile" => Command::Tile {
+            pos: HexPos::new(
+                parse_number("x", required("x", rest.first())?)?,
+                parse_number("y", required("y", rest.get(1))?)?,
+            ),
+        },
+        "frequencies" => Command::Frequencies,
+        "stats" => Command::Stats,
+        "export" => Command::Export,
+        "review" => {
+            let csv = rest.iter().any(|arg| arg == "--csv");
+            let earlier: Vec<PathBuf> = rest
+                .iter()
+                .filter(|arg| *arg != "--csv")
+                .map(PathBuf::from)
+                .collect();
+            if earlier.is_empty() {
+                return Err(CliError::Usage("Missing <earlier>".to_string()));
+            }
+            Command::Review { earlier, csv }
+        }
+        _ => return Err(CliError::Usage(format!("Unknown command: {command}"))),
+    };
+
+    let expected_args = match command {
+        Command::Groups { closed } => usize::from(closed),
+        Command::Placements { .. } | Command::Review { .. } => rest.len(),
+        Command::Lookahead { .. } => rest.len().min(2),
+        Command::Rollouts { .. } => rest.len().min(4),
+        Command::Survival { .. } | Command::Demand { .. } => rest.len().min(1),
+        Command::Tile { .. } => 2,
+        _ => 0,
+    };
+    if rest.len() > expected_args {
+        return Err(CliError::Usage(format!(
+            "Unexpected arguments: {:?}",
+            &rest[expected_args..]
+        )));
+    }
+
+    Ok((path, command))
+}
+
+fn run(args: &[String]) -> Result<(), CliError> {
+    let (path, command) = parse_args(args)?;
+    let analysis = Analysis::load(&path)?;
+    match commands::run(&analysis, &command, &mut std::io::stdout().lock()) {
+        // Stop quietly when piped into e.g. `head`.
+        Err(CliError::Output(error)) if error.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    }
+}
+
+fn main() -> ExitCode {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error @ CliError::Usage(_)) => {
+            eprintln!("error: {error}\n\n{USAGE}");
+            ExitCode::from(2)
+        }
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<(PathBuf, Command), CliError> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        parse_args(&args)
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        let (path, command) = parse(&["save.sav", "quests"]).unwrap();
+        assert_eq!(path, PathBuf::from("save.sav"));
+        assert_eq!(command, Command::Quests);
+
+        let (_, command) = parse(&["save.sav", "groups", "--closed"]).unwrap();
+        assert_eq!(command, Command::Groups { closed: true });
+        let (_, command) = parse(&["save.sav", "placements"]).unwrap();
+        assert_eq!(
+            command,
+            Command::Placements {
+                count: 10,
+                weights: None
+            }
+        );
+        let (_, command) = parse(&["save.sav", "placements", "3"]).unwrap();
+        assert_eq!(
+            command,
+            Command::Placements {
+                count: 3,
+                weights: None
+            }
+        );
+        for args in [
+            &["save.sav", "placements", "--weights", "w.toml", "3"],
+            &["save.sav", "placements", "3", "--weights", "w.toml"],
+        ] {
+            let (_, command) = parse(args).unwrap();
+            assert_eq!(
+                command,
+                Command::Placements {
+                    count: 3,
+                    weights: Some(PathBuf::from("w.toml"))
+                }
+            );
+        }
+        let (_, command) = parse(&["save.sav", "lookahead"]).unwrap();
+        assert_eq!(command, Command::Lookahead { depth: 3, width: 8 });
+        let (_, command) = parse(&["save.sav", "lookahead", "2", "16"]).unwrap();
+        assert_eq!(
+            command,
+            Command::Lookahead {
+                depth: 2,
+                width: 16
+            }
+        );
+        let (_, command) = parse(&["save.sav", "rollouts", "3", "4"]).unwrap();
+        assert_eq!(
+            command,
+            Command::Rollouts {
+                config: RolloutConfig {
+                    candidates: 3,
+                    turns: 4,
+                    ..RolloutConfig::default()
+                }
+            }
+        );
+        let (_, command) = parse(&["save.sav", "survival", "5"]).unwrap();
+        assert_eq!(command, Command::Survival { count: 5 });
+        let (_, command) = parse(&["save.sav", "holes"]).unwrap();
+        assert_eq!(command, Command::Holes);
+        let (_, command) = parse(&["save.sav", "demand", "4"]).unwrap();
+        assert_eq!(command, Command::Demand { count: 4 });
+        let (_, command) = parse(&["save.sav", "export"]).unwrap();
+        assert_eq!(command, Command::Export);
+        let (_, command) = parse(&["save.sav", "review", "a.sav", "--csv", "b.sav"]).unwrap();
+        assert_eq!(
+            command,
+            Command::Review {
+                earlier: vec![PathBuf::from("a.sav"), PathBuf::from("b.sav")],
+                csv: true
+            }
+        );
+        let (_, command) = parse(&["save.sav", "tile", "-4", "7"]).unwrap();
+        assert_eq!(
+            command,
+            Command::Tile {
+                pos: HexPos::new(-4, 7)
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_commands() {
+        for args in [
+            &[][..],
+            &["save.sav"],
+            &["save.sav", "unknown"],
+            &["save.sav", "tile", "1"],
+            &["save.sav", "tile", "1", "y"],
+            &["save.sav", "placements", "-1"],
+            &["save.sav", "placements", "3", "4"],
+            &["save.sav", "placements", "--weights"],
+            &["save.sav", "lookahead", "1", "2", "3"],
+            &["save.sav", "survival", "1", "2"],
+            &["save.sav", "rollouts", "1", "2", "3", "-4"],
+            &["save.sav", "stats", "extra"],
+            &["save.sav", "holes", "3"],
+            &["save.sav", "demand", "1", "2"],
+            &["save.sav", "groups", "--open"],
+            &["save.sav", "review"],
+            &["save.sav", "review", "--csv"],
+        ] {
+            assert!(
+                matches!(parse(args), Err(CliError::Usage(_))),
+                "{args:?} should be rejected"
+            );
+        }
+    }
+}
@@ -2,7 +2,7 @@ use crate::raw_data;
 
 use super::Terrain;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize)]
 pub enum Form {
     Size1 = 0,
     Size2 = 1,
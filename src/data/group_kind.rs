@@ -1,7 +1,7 @@
 /// The kind of group a segment can belong to. Unlike `Terrain`, this has no
 /// Lake/Station/Empty/Missing variants — those participate in groups via
 /// `EdgeTerrain::group_memberships()`.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize)]
 pub enum GroupKind {
     House,
     Forest,
@@ -12,7 +12,10 @@ pub use group_kind::GroupKind;
 pub use segment::Segment;
 pub use side::Side;
 pub use terrain::{EdgeMatch, Terrain};
-pub use tile_table::{quest_terrain, segments_from_quest_tile, segments_from_special_tile_id};
+pub use tile_table::{
+    quest_terrain, segments_from_quest_tile, segments_from_quest_tile_id,
+    segments_from_special_tile_id,
+};
 
 pub use crate::coords::HexPos;
 
@@ -13,7 +13,7 @@ pub enum EdgeMatch {
     Missing,
 }
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash, PartialOrd, Ord)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash, PartialOrd, Ord, serde::Serialize)]
 pub enum Terrain {
     Missing = 0,
     Empty = 1,
@@ -379,7 +379,11 @@ pub fn quest_terrain(quest_tile_id: QuestTileId) -> Option<Terrain> {
 }
 
 pub fn segments_from_quest_tile(pos: HexPos, quest_tile: &QuestTile) -> Vec<Segment> {
-    let id = quest_tile.quest_tile_id;
+    segments_from_quest_tile_id(pos, quest_tile.quest_tile_id)
+}
+
+/// Segments of the quest tile `id` (also used for preplaced tiles), unrotated.
+pub fn segments_from_quest_tile_id(pos: HexPos, id: QuestTileId) -> Vec<Segment> {
     let segments = match raw_segments_for_quest_tile(id) {
         Some(s) => s,
         None => {
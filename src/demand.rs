@@ -0,0 +1,183 @@
+//! Which tile patterns the board needs. Every frontier cell is served by the known patterns
+//! that match all of its placed neighbors, and its need is split evenly among them. Summed over
+//! the frontier this is the demand for each pattern, which is compared with how often the
+//! pattern has come up so far.
+
+use std::collections::HashMap;
+
+use crate::{
+    best_placements::{constraints_at, pattern_fits_perfectly},
+    data::{HexPos, Terrain, HEX_SIDES},
+    group_assignments::GroupAssignments,
+    map::Map,
+    tile_frequency::{EdgePattern, TileFrequencies},
+};
+
+type Constraints = [Option<Terrain>; HEX_SIDES];
+
+/// The patterns one frontier cell needs.
+#[derive(Clone, Debug)]
+pub struct CellDemand {
+    pub pos: HexPos,
+    pub constraints: Constraints,
+    /// Patterns that match every placed neighbor, most frequent first.
+    pub patterns: Vec<EdgePattern>,
+    /// Chance that a random tile is one of them.
+    pub supply: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct PatternDemand {
+    pub pattern: EdgePattern,
+    /// Frontier cells the pattern fits perfectly.
+    pub cells: usize,
+    /// The pattern's share of the need of each of those cells, summed.
+    pub demand: f32,
+    /// Share of the known tiles with this pattern.
+    pub supply: f32,
+}
+
+impl PatternDemand {
+    /// Share of the demand over share of the supply. Above 1 the board asks for the pattern
+    /// more often than it comes up.
+    pub fn pressure(&self, served_cells: usize) -> f32 {
+        if served_cells == 0 || self.supply == 0.0 {
+            return 0.0;
+        }
+        self.demand / served_cells as f32 / self.supply
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Demand {
+    /// Hardest to serve first.
+    pub cells: Vec<CellDemand>,
+    /// Most needed first.
+    pub patterns: Vec<PatternDemand>,
+}
+
+fn edges(pattern: &EdgePattern) -> [Terrain; HEX_SIDES] {
+    std::array::from_fn(|side| pattern.0.at_index(side))
+}
+
+impl Demand {
+    pub fn compute(map: &Map, groups: &GroupAssignments, freqs: &TileFrequencies) -> Self {
+        // Many frontier cells share their constraints, e.g. a single forest edge.
+        let mut fitting: HashMap<Constraints, Vec<usize>> = HashMap::new();
+        let mut cells: Vec<(CellDemand, Vec<usize>)> = groups
+            .possible_placements
+            .iter()
+            .map(|&pos| {
+                let constraints = constraints_at(map, pos);
+                let patterns = fitting
+                    .entry(constraints)
+                    .or_insert_with(|| {
+                        (0..freqs.entries.len())
+                            .filter(|&index| {
+                                pattern_fits_perfectly(&freqs.entries[index].edges.0, &constraints)
+                            })
+                            .collect()
+                    })
+                    .clone();
+                let supply = patterns.iter().fold(0.0, |supply, &index| {
+                    supply + freqs.entries[index].fraction as f32
+                });
+                let cell = CellDemand {
+                    pos,
+                    constraints,
+                    patterns: patterns
+                        .iter()
+                        .map(|&index| freqs.entries[index].edges.clone())
+                        .collect(),
+                    supply,
+                };
+                (cell, patterns)
+            })
+            .collect();
+        cells.sort_by(|(a, _), (b, _)| {
+            a.supply
+                .total_cmp(&b.supply)
+                .then_with(|| (a.pos.x(), a.pos.y()).cmp(&(b.pos.x(), b.pos.y())))
+        });
+
+        let mut per_pattern: Vec<(usize, f32)> = vec![(0, 0.0); freqs.entries.len()];
+        for (_, patterns) in &cells {
+            let share = 1.0 / patterns.len() as f32;
+            for &index in patterns {
+                per_pattern[index].0 += 1;
+                per_pattern[index].1 += share;
+            }
+        }
+        let mut patterns: Vec<PatternDemand> = per_pattern
+            .into_iter()
+            .enumerate()
+            .filter(|(_, (cells, _))| *cells > 0)
+            .map(|(index, (cells, demand))| PatternDemand {
+                pattern: freqs.entries[index].edges.clone(),
+                cells,
+                demand,
+                supply: freqs.entries[index].fraction as f32,
+            })
+            .collect();
+        patterns.sort_by(|a, b| {
+            b.demand
+                .total_cmp(&a.demand)
+                .then_with(|| edges(&a.pattern).cmp(&edges(&b.pattern)))
+        });
+
+        Self {
+            cells: cells.into_iter().map(|(cell, _)| cell).collect(),
+            patterns,
+        }
+    }
+
+    /// Frontier cells no known pattern fits perfectly.
+    pub fn unserved(&self) -> impl Iterator<Item = &CellDemand> {
+        self.cells.iter().filter(|cell| cell.patterns.is_empty())
+    }
+
+    /// Frontier cells at least one known pattern fits perfectly.
+    pub fn served_cells(&self) -> usize {
+        self.cells.len() - self.unserved().count()
+    }
+
+    pub fn of_pattern(&self, pattern: &EdgePattern) -> Option<&PatternDemand> {
+        self.patterns.iter().find(|entry| &entry.pattern == pattern)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::EdgeProfile;
+
+    fn demand(demand: f32, supply: f32) -> PatternDemand {
+        PatternDemand {
+            pattern: EdgePattern(EdgeProfile::from_segments(&[])),
+            cells: 4,
+            demand,
+            supply,
+        }
+    }
+
+    #[test]
+    fn test_pressure_compares_shares() {
+        // Half of 10 cells need it, a quarter of the tiles have it.
+        assert_eq!(demand(5.0, 0.25).pressure(10), 2.0);
+        assert_eq!(demand(1.0, 0.1).pressure(10), 1.0);
+        assert_eq!(demand(1.0, 0.0).pressure(10), 0.0);
+        assert_eq!(demand(1.0, 0.5).pressure(0), 0.0);
+    }
+
+    #[test]
+    fn test_empty_board_has_no_demand() {
+        let demand = Demand::compute(
+            &Map::default(),
+            &GroupAssignments::default(),
+            &TileFrequencies::default(),
+        );
+        assert!(demand.cells.is_empty());
+        assert!(demand.patterns.is_empty());
+        assert_eq!(demand.served_cells(), 0);
+    }
+}
@@ -0,0 +1,106 @@
+/// Union-find over the elements `0..len`, with path halving and union by rank.
+#[derive(Clone, Debug, Default)]
+pub struct DisjointSet {
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+}
+
+impl DisjointSet {
+    /// Create `len` singleton sets.
+    pub fn new(len: usize) -> Self {
+        Self {
+            parent: (0..len).collect(),
+            rank: vec![0; len],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    /// Add singleton sets until there are `len` elements.
+    pub fn grow(&mut self, len: usize) {
+        let old_len = self.parent.len();
+        if len > old_len {
+            self.parent.extend(old_len..len);
+            self.rank.resize(len, 0);
+        }
+    }
+
+    /// Representative of the set containing `element`.
+    pub fn find(&mut self, mut element: usize) -> usize {
+        while self.parent[element] != element {
+            let grandparent = self.parent[self.parent[element]];
+            self.parent[element] = grandparent;
+            element = grandparent;
+        }
+        element
+    }
+
+    /// Merge the sets containing `a` and `b`, returning the representative of the result.
+    pub fn union(&mut self, a: usize, b: usize) -> usize {
+        let a = self.find(a);
+        let b = self.find(b);
+        if a == b {
+            return a;
+        }
+        let (root, child) = if self.rank[a] < self.rank[b] {
+            (b, a)
+        } else {
+            (a, b)
+        };
+        self.parent[child] = root;
+        if self.rank[root] == self.rank[child] {
+            self.rank[root] += 1;
+        }
+        root
+    }
+
+    pub fn same(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_sets_are_singletons() {
+        let mut sets = DisjointSet::new(4);
+        for element in 0..4 {
+            assert_eq!(sets.find(element), element);
+        }
+        assert!(!sets.same(0, 1));
+    }
+
+    #[test]
+    fn test_union_is_transitive() {
+        let mut sets = DisjointSet::new(6);
+        sets.union(0, 1);
+        sets.union(2, 3);
+        assert!(!sets.same(1, 2));
+        let root = sets.union(1, 3);
+        for element in 0..4 {
+            assert_eq!(sets.find(element), root);
+        }
+        assert!(!sets.same(0, 4));
+        assert_eq!(sets.union(0, 3), root);
+    }
+
+    #[test]
+    fn test_grow_keeps_existing_sets() {
+        let mut sets = DisjointSet::new(2);
+        sets.union(0, 1);
+        sets.grow(4);
+        assert_eq!(sets.len(), 4);
+        assert!(sets.same(0, 1));
+        assert_eq!(sets.find(3), 3);
+        sets.grow(1);
+        assert_eq!(sets.len(), 4);
+    }
+}
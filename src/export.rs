@@ -0,0 +1,452 @@
+//! Versioned JSON document of a loaded save and everything derived from it, to diff runs and
+//! feed notebooks. Positions are axial `[x, y]` pairs. Lists are sorted, so the same save
+//! always gives the same document.
+
+use glam::Vec2;
+use serde::Serialize;
+
+use crate::{
+    best_placements::{
+        BestPlacements, GroupEdgeAlteration, GroupEffect, NeighborFitEffect, PlacementScore,
+        QuestEffect,
+    },
+    coords::WorldPos,
+    data::{Form, GroupKind, HexPos, Rotation, Segment, Terrain, HEX_SIDES},
+    group::Group,
+    group_assignments::GroupAssignments,
+    hex,
+    holes::{Hole, HoleCell, Holes},
+    map::{Map, Quest, QuestType, SegmentIndex},
+    raw_data::SaveGame,
+    score::ScoreRules,
+    survival::{GameStats, SurvivalModel},
+    tile_frequency::{TileFrequencies, TileFrequency},
+};
+
+/// Bumped when a field is removed or changes its meaning. New fields keep the version.
+pub const EXPORT_VERSION: u32 = 1;
+
+type Pos = [i32; 2];
+
+/// A segment of a group, identified by its tile and rotation.
+type Member = (i32, i32, Rotation);
+
+fn pos(pos: HexPos) -> Pos {
+    [pos.x(), pos.y()]
+}
+
+fn sorted_positions<'a>(positions: impl IntoIterator<Item = &'a HexPos>) -> Vec<Pos> {
+    let mut positions: Vec<Pos> = positions.into_iter().map(|&p| pos(p)).collect();
+    positions.sort();
+    positions.dedup();
+    positions
+}
+
+#[derive(Serialize)]
+pub struct Export {
+    pub version: u32,
+    pub game: GameExport,
+    pub tiles: Vec<TileExport>,
+    pub next_tile: Vec<SegmentExport>,
+    pub groups: Vec<GroupExport>,
+    /// Best first.
+    pub placements: Vec<PlacementExport>,
+    /// Most frequent first.
+    pub frequencies: Vec<FrequencyExport>,
+    /// Ordered by their first cell.
+    pub holes: Vec<HoleExport>,
+}
+
+#[derive(Serialize)]
+pub struct GameExport {
+    pub score: i32,
+    pub level: i32,
+    pub placed_tiles: usize,
+    pub tile_stack_count: i32,
+    pub perfect_placements: i32,
+    pub quests_fulfilled: i32,
+    pub quests_failed: i32,
+    /// In seconds.
+    pub playtime: f32,
+    /// `None` if the stack grows on average.
+    pub expected_turns: Option<f32>,
+    pub game_over_risk: f32,
+}
+
+#[derive(Serialize)]
+pub struct TileExport {
+    pub pos: Pos,
+    pub segments: Vec<SegmentExport>,
+    pub quest: Option<QuestExport>,
+}
+
+#[derive(Serialize)]
+pub struct SegmentExport {
+    pub terrain: Terrain,
+    pub form: Form,
+    pub rotation: Rotation,
+    pub unit_count: u32,
+    /// Index into `groups` of the group the segment is shown as part of.
+    pub group: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct QuestExport {
+    pub terrain: Terrain,
+    pub quest_type: QuestType,
+    pub target_value: i32,
+    pub active: bool,
+    pub quest_id: i32,
+    pub quest_level: i32,
+}
+
+#[derive(Serialize)]
+pub struct GroupExport {
+    pub kind: GroupKind,
+    pub tiles: Vec<Pos>,
+    pub segment_count: usize,
+    pub unit_count: u32,
+    pub closed: bool,
+    pub open_edges: Vec<Pos>,
+    pub preplaced_edges: Vec<Pos>,
+    pub quests: Vec<QuestExport>,
+    /// In world coordinates.
+    pub centroid: [f32; 2],
+    pub radius: f32,
+}
+
+#[derive(Serialize)]
+pub struct PlacementExport {
+    pub pos: Pos,
+    pub rotation: Rotation,
+    pub matching_edges: u8,
+    pub neighbor_bonus: u8,
+    pub connection_difficulty: u8,
+    pub crowding: u8,
+    pub preplaced_neighbors: u8,
+    pub fit_chance: f32,
+    pub fit_unique: u16,
+    pub points: i32,
+    /// Added to the tile stack.
+    pub tiles_gained: i32,
+    pub expected_points: f32,
+    pub group_effects: Vec<GroupEffectExport>,
+    pub group_edge_alterations: Vec<GroupEdgeAlterationExport>,
+    pub neighbor_fit_effects: Vec<NeighborFitEffectExport>,
+}
+
+#[derive(Serialize)]
+pub struct GroupEffectExport {
+    pub terrain: Terrain,
+    pub rank: usize,
+    pub open_edges_before: usize,
+    pub open_edge_delta: i8,
+    pub quest: Option<QuestEffectExport>,
+}
+
+#[derive(Serialize)]
+pub struct QuestEffectExport {
+    pub quest_type: QuestType,
+    pub target: i32,
+    pub current_segments: usize,
+    pub segments_after: usize,
+    pub would_close: bool,
+}
+
+#[derive(Serialize)]
+pub struct GroupEdgeAlterationExport {
+    pub group_size: usize,
+    pub diff: i8,
+}
+
+#[derive(Serialize)]
+pub struct NeighborFitEffectExport {
+    pub side: usize,
+    pub chance_before: f32,
+    pub chance_after: f32,
+}
+
+#[derive(Serialize)]
+pub struct FrequencyExport {
+    pub edges: [Terrain; HEX_SIDES],
+    pub count: usize,
+    pub fraction: f64,
+}
+
+#[derive(Serialize)]
+pub struct HoleExport {
+    pub cells: Vec<HoleCellExport>,
+    pub fillable: bool,
+    pub perfectly_fillable: bool,
+}
+
+#[derive(Serialize)]
+pub struct HoleCellExport {
+    pub pos: Pos,
+    pub constraints: [Option<Terrain>; HEX_SIDES],
+    pub fit_chance: f32,
+    pub fit_unique: u16,
+    pub perfect_chance: f32,
+}
+
+impl From<&Quest> for QuestExport {
+    fn from(quest: &Quest) -> Self {
+        Self {
+            terrain: quest.terrain,
+            quest_type: quest.quest_type,
+            target_value: quest.target_value,
+            active: quest.active,
+            quest_id: quest.quest_id,
+            quest_level: quest.quest_level,
+        }
+    }
+}
+
+impl From<&QuestEffect> for QuestEffectExport {
+    fn from(effect: &QuestEffect) -> Self {
+        Self {
+            quest_type: effect.quest_type,
+            target: effect.target,
+            current_segments: effect.current_segments,
+            segments_after: effect.segments_after,
+            would_close: effect.would_close,
+        }
+    }
+}
+
+impl From<&GroupEffect> for GroupEffectExport {
+    fn from(effect: &GroupEffect) -> Self {
+        Self {
+            terrain: effect.terrain,
+            rank: effect.rank,
+            open_edges_before: effect.open_edges_before,
+            open_edge_delta: effect.open_edge_delta,
+            quest: effect.quest.as_ref().map(QuestEffectExport::from),
+        }
+    }
+}
+
+impl From<&GroupEdgeAlteration> for GroupEdgeAlterationExport {
+    fn from(alteration: &GroupEdgeAlteration) -> Self {
+        Self {
+            group_size: alteration.group_size,
+            diff: alteration.diff,
+        }
+    }
+}
+
+impl From<&NeighborFitEffect> for NeighborFitEffectExport {
+    fn from(effect: &NeighborFitEffect) -> Self {
+        Self {
+            side: effect.side,
+            chance_before: effect.chance_before,
+            chance_after: effect.chance_after,
+        }
+    }
+}
+
+impl From<&PlacementScore> for PlacementExport {
+    fn from(score: &PlacementScore) -> Self {
+        // Effects are collected from hash maps, so sort them.
+        let mut group_effects: Vec<GroupEffectExport> =
+            score.group_effects.iter().map(Into::into).collect();
+        group_effects.sort_by_key(|effect| {
+            (
+                effect.terrain,
+                effect.rank,
+                effect.open_edges_before,
+                effect.open_edge_delta,
+            )
+        });
+        let mut group_edge_alterations: Vec<GroupEdgeAlterationExport> = score
+            .group_edge_alterations
+            .iter()
+            .map(Into::into)
+            .collect();
+        group_edge_alterations.sort_by_key(|alteration| (alteration.group_size, alteration.diff));
+        Self {
+            pos: pos(score.pos),
+            rotation: score.rotation,
+            matching_edges: score.matching_edges,
+            neighbor_bonus: score.neighbor_bonus,
+            connection_difficulty: score.connection_difficulty,
+            crowding: score.crowding,
+            preplaced_neighbors: score.preplaced_neighbors,
+            fit_chance: score.fit_chance,
+            fit_unique: score.fit_unique,
+            points: score.points.points,
+            tiles_gained: score.points.tiles,
+            expected_points: score.expected_points,
+            group_effects,
+            group_edge_alterations,
+            neighbor_fit_effects: score.neighbor_fit_effects.iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<&TileFrequency> for FrequencyExport {
+    fn from(frequency: &TileFrequency) -> Self {
+        Self {
+            edges: std::array::from_fn(|side| frequency.edges.0.at_index(side)),
+            count: frequency.count,
+            fraction: frequency.fraction,
+        }
+    }
+}
+
+impl From<&HoleCell> for HoleCellExport {
+    fn from(cell: &HoleCell) -> Self {
+        Self {
+            pos: pos(cell.pos),
+            constraints: cell.constraints,
+            fit_chance: cell.fit_chance,
+            fit_unique: cell.fit_unique,
+            perfect_chance: cell.perfect_chance,
+        }
+    }
+}
+
+impl From<&Hole> for HoleExport {
+    fn from(hole: &Hole) -> Self {
+        Self {
+            cells: hole.cells.iter().map(Into::into).collect(),
+            fillable: hole.is_fillable(),
+            perfectly_fillable: hole.can_fill_perfectly(),
+        }
+    }
+}
+
+fn export_group(map: &Map, group: &Group) -> GroupExport {
+    let mut quests: Vec<&Quest> = group.quests.iter().collect();
+    quests.sort_by_key(|quest| (quest.quest_id, quest.target_value));
+    let tiles = sorted_positions(group.segment_indices.iter().map(|&i| &map.segment(i).pos));
+    // Sum in tile order, as the centroid of the group depends on the order of its hash set.
+    let centroid = WorldPos(
+        tiles
+            .iter()
+            .map(|&[x, y]| hex::hex_to_world(HexPos::new(x, y)).0)
+            .sum::<Vec2>()
+            / tiles.len() as f32,
+    );
+    GroupExport {
+        kind: group.kind,
+        tiles,
+        segment_count: group.segment_indices.len(),
+        unit_count: group.unit_count,
+        closed: group.is_closed(),
+        open_edges: sorted_positions(&group.open_edges),
+        preplaced_edges: sorted_positions(&group.preplaced_edges),
+        quests: quests.into_iter().map(Into::into).collect(),
+        centroid: centroid.0.to_array(),
+        radius: Group::compute_radius(centroid, &group.segment_indices, &map.segments),
+    }
+}
+
+fn export_segment(segment: &Segment, group: Option<usize>) -> SegmentExport {
+    SegmentExport {
+        terrain: segment.terrain,
+        form: segment.form,
+        rotation: segment.rotation,
+        unit_count: segment.unit_count,
+        group,
+    }
+}
+
+impl Export {
+    pub fn new(
+        savegame: &SaveGame,
+        map: &Map,
+        groups: &GroupAssignments,
+        freqs: &TileFrequencies,
+        placements: &BestPlacements,
+    ) -> Self {
+        // Groups are ordered by kind and members, which doesn't depend on how they were found.
+        let members_of = |group: &Group| {
+            let mut members: Vec<Member> = group
+                .segment_indices
+                .iter()
+                .map(|&index| {
+                    let segment = map.segment(index);
+                    (segment.pos.x(), segment.pos.y(), segment.rotation)
+                })
+                .collect();
+            members.sort();
+            members
+        };
+        let mut group_order: Vec<(usize, Vec<Member>, usize)> = groups
+            .groups
+            .iter()
+            .enumerate()
+            .map(|(index, group)| (group.kind as usize, members_of(group), index))
+            .collect();
+        group_order.sort();
+        let mut exported_index = vec![0; groups.groups.len()];
+        for (exported, &(_, _, index)) in group_order.iter().enumerate() {
+            exported_index[index] = exported;
+        }
+        let group_of = |segment_index: SegmentIndex| {
+            groups
+                .group_of(segment_index)
+                .map(|group_index| exported_index[group_index])
+        };
+
+        let mut positions: Vec<HexPos> = map.iter_tile_positions().collect();
+        positions.sort_by_key(|&p| pos(p));
+        let tiles = positions
+            .into_iter()
+            .map(|tile_pos| TileExport {
+                pos: pos(tile_pos),
+                segments: map
+                    .segment_indices_at(tile_pos)
+                    .into_iter()
+                    .flatten()
+                    .map(|index| export_segment(map.segment(index), group_of(index)))
+                    .collect(),
+                quest: map.quests.get(&tile_pos).map(Into::into),
+            })
+            .collect();
+
+        let survival = SurvivalModel::new(&GameStats::from(savegame), ScoreRules::from(savegame))
+            .estimate(map.tile_stack_count, groups);
+
+        let mut frequencies: Vec<FrequencyExport> = freqs.entries.iter().map(Into::into).collect();
+        frequencies.sort_by_key(|entry| (std::cmp::Reverse(entry.count), entry.edges));
+
+        Self {
+            version: EXPORT_VERSION,
+            game: GameExport {
+                score: savegame.score,
+                level: savegame.level,
+                placed_tiles: savegame.tiles.len(),
+                tile_stack_count: map.tile_stack_count,
+                perfect_placements: savegame.perfect_placements,
+                quests_fulfilled: savegame.quests_fulfilled,
+                quests_failed: savegame.quests_failed,
+                playtime: savegame.playtime,
+                expected_turns: survival.turns,
+                game_over_risk: survival.risk,
+            },
+            tiles,
+            next_tile: map
+                .next_tile
+                .iter()
+                .map(|segment| export_segment(segment, None))
+                .collect(),
+            groups: group_order
+                .iter()
+                .map(|&(_, _, index)| export_group(map, &groups.groups[index]))
+                .collect(),
+            placements: placements.iter_best().map(Into::into).collect(),
+            frequencies,
+            holes: Holes::find(map, freqs)
+                .holes
+                .iter()
+                .map(Into::into)
+                .collect(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("The export only holds plain data")
+    }
+}
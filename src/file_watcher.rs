@@ -1,14 +1,23 @@
 use std::{
     path::{Path, PathBuf},
     thread::JoinHandle,
-    time::{Duration, SystemTime},
+    time::{Duration, Instant, SystemTime},
 };
 
 use crate::{
-    best_placements::BestPlacements, group_assignments::GroupAssignments, map::Map, raw_data,
+    best_placements::BestPlacements,
+    group_assignments::GroupAssignments,
+    map::Map,
+    raw_data,
+    replay::Review,
+    save_slots::{self, SaveSlot},
+    score::ScoreRules,
+    scorer::PlacementScorer,
+    timeline::Timeline,
 };
 
-use std::fs::File;
+/// How often the saves directory is checked for a slot the game wrote.
+const SLOT_CHECK_INTERVAL: Duration = Duration::from_secs(1);
 
 #[derive(Default)]
 pub struct FileChooseDialog {
@@ -22,10 +31,22 @@ impl FileChooseDialog {
             .is_some_and(|handle| !handle.is_finished())
     }
 
-    pub fn open(&mut self) {
+    pub fn open(&mut self, directory: &Path) {
         if !self.is_open() {
-            self.handle = Some(std::thread::spawn(|| {
-                rfd::FileDialog::new().set_directory(".").pick_file()
+            let directory = directory.to_owned();
+            self.handle = Some(std::thread::spawn(move || {
+                rfd::FileDialog::new().set_directory(directory).pick_file()
+            }));
+        }
+    }
+
+    pub fn open_folder(&mut self, directory: &Path) {
+        if !self.is_open() {
+            let directory = directory.to_owned();
+            self.handle = Some(std::thread::spawn(move || {
+                rfd::FileDialog::new()
+                    .set_directory(directory)
+                    .pick_folder()
             }));
         }
     }
@@ -43,9 +64,22 @@ impl FileChooseDialog {
     }
 }
 
+/// What a finished load produced.
+pub enum Loaded {
+    /// The parsed savegame. Apply it incrementally if possible, otherwise hand it to
+    /// `MapLoader::rebuild`.
+    SaveGame(Box<raw_data::SaveGame>),
+    /// A map analyzed from scratch.
+    Rebuilt(Box<(Map, GroupAssignments, BestPlacements)>),
+}
+
+type LoadResult = Result<Loaded, String>;
+
 #[derive(Default)]
 pub struct MapLoader {
-    handle: Option<JoinHandle<Option<(Map, GroupAssignments, BestPlacements)>>>,
+    handle: Option<JoinHandle<LoadResult>>,
+    /// Why the last load failed, cleared by the next successful one.
+    pub last_error: Option<String>,
 }
 
 impl MapLoader {
@@ -62,59 +96,52 @@ impl MapLoader {
                 log::info!("Loading savegame: {}", path.display());
                 let start = std::time::Instant::now();
 
-                let mut stream = match File::open(&path) {
-                    Ok(f) => f,
-                    Err(e) => {
-                        log::error!("Failed to open file {}: {e}", path.display());
-                        return None;
-                    }
-                };
-
-                // parse_nrbf can panic on malformed data; catch and report.
-                let parsed = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                    nrbf_rs::parse_nrbf(&mut stream)
-                })) {
-                    Ok(value) => value,
-                    Err(_) => {
-                        log::error!("NRBF parsing panicked for {}", path.display());
-                        return None;
-                    }
-                };
-
-                let tree_loaded = start.elapsed();
-                log::info!("NRBF Tree loaded in: {tree_loaded:?}");
-                let start = std::time::Instant::now();
-
-                let savegame = match raw_data::SaveGame::try_from(&parsed) {
-                    Ok(sg) => sg,
-                    Err(e) => {
-                        log::error!("Failed to parse savegame: {e}");
-                        return None;
-                    }
-                };
+                let savegame = raw_data::SaveGame::load(&path)?;
 
                 let save_loaded = start.elapsed();
                 log::info!("Savegame loaded in: {save_loaded:?}");
+
+                Ok(Loaded::SaveGame(Box::new(savegame)))
+            }));
+        }
+    }
+
+    /// Analyze `savegame` from scratch in the background, ranking placements with `scorer`.
+    pub fn rebuild(&mut self, savegame: raw_data::SaveGame, scorer: Box<dyn PlacementScorer>) {
+        if !self.in_progress() {
+            self.handle = Some(std::thread::spawn(move || {
                 let start = std::time::Instant::now();
 
                 let map = Map::from(&savegame);
                 let groups = GroupAssignments::from(&map);
                 let freqs = crate::tile_frequency::TileFrequencies::from_map(&map);
-                let best_placements = BestPlacements::compute(&map, &groups, &freqs);
+                let rules = ScoreRules::from(&savegame);
+                let best_placements =
+                    BestPlacements::compute_with(&map, &groups, &freqs, scorer, rules);
                 let map_loaded = start.elapsed();
                 log::info!("Map loaded in: {map_loaded:?}");
 
-                Some((map, groups, best_placements))
+                Ok(Loaded::Rebuilt(Box::new((map, groups, best_placements))))
             }));
         }
     }
 
-    pub fn take_result(&mut self) -> Option<(Map, GroupAssignments, BestPlacements)> {
+    pub fn take_result(&mut self) -> Option<Loaded> {
         if self.handle.as_ref().is_some_and(JoinHandle::is_finished) {
-            match self.handle.take().unwrap().join() {
-                Ok(result) => result,
-                Err(_) => {
-                    log::error!("Map loader thread panicked");
+            let result = self
+                .handle
+                .take()
+                .unwrap()
+                .join()
+                .unwrap_or_else(|_| Err("Map loader thread panicked".to_string()));
+            match result {
+                Ok(loaded) => {
+                    self.last_error = None;
+                    Some(loaded)
+                }
+                Err(error) => {
+                    log::error!("{error}");
+                    self.last_error = Some(error);
                     None
                 }
             }
@@ -124,6 +151,130 @@ impl MapLoader {
     }
 }
 
+/// Write the review of `timeline` as JSON to `path` and as CSV next to it.
+fn write_review(
+    timeline: &Timeline,
+    scorer: Box<dyn PlacementScorer>,
+    path: &Path,
+) -> Result<(), String> {
+    let review = Review::of(timeline, scorer);
+    let write = |path: &Path, contents: String| {
+        std::fs::write(path, contents)
+            .map_err(|error| format!("Failed to write {}: {error}", path.display()))
+    };
+    write(path, review.to_json())?;
+    write(&path.with_extension("csv"), review.to_csv())
+}
+
+/// Asks where to save a review of the timeline and writes it in the background.
+#[derive(Default)]
+pub struct ReviewExport {
+    handle: Option<JoinHandle<Result<Option<PathBuf>, String>>>,
+    /// Where the last review was written, or why that failed.
+    pub last_result: Option<Result<PathBuf, String>>,
+}
+
+impl ReviewExport {
+    pub fn in_progress(&self) -> bool {
+        self.handle
+            .as_ref()
+            .is_some_and(|handle| !handle.is_finished())
+    }
+
+    pub fn start(
+        &mut self,
+        directory: &Path,
+        timeline: Timeline,
+        scorer: Box<dyn PlacementScorer>,
+    ) {
+        if !self.in_progress() {
+            let directory = directory.to_owned();
+            self.handle = Some(std::thread::spawn(move || {
+                let Some(path) = rfd::FileDialog::new()
+                    .set_directory(directory)
+                    .set_file_name("review.json")
+                    .save_file()
+                else {
+                    return Ok(None);
+                };
+                write_review(&timeline, scorer, &path)?;
+                Ok(Some(path))
+            }));
+        }
+    }
+
+    /// Pick up the result of a finished export.
+    pub fn poll(&mut self) {
+        if self.handle.as_ref().is_some_and(JoinHandle::is_finished) {
+            let result = self
+                .handle
+                .take()
+                .unwrap()
+                .join()
+                .unwrap_or_else(|_| Err("Review export thread panicked".to_string()));
+            match result {
+                Ok(Some(path)) => {
+                    log::info!("Review written to {}", path.display());
+                    self.last_result = Some(Ok(path));
+                }
+                Ok(None) => {}
+                Err(error) => {
+                    log::error!("{error}");
+                    self.last_result = Some(Err(error));
+                }
+            }
+        }
+    }
+}
+
+/// Loads every slot of the saves directory in the background, for the slot picker.
+#[derive(Default)]
+pub struct SlotScanner {
+    handle: Option<JoinHandle<std::io::Result<Vec<SaveSlot>>>>,
+    /// Slots of the last scan, most recently written first.
+    pub slots: Vec<SaveSlot>,
+    /// Why the last scan failed, cleared by the next successful one.
+    pub last_error: Option<String>,
+}
+
+impl SlotScanner {
+    pub fn in_progress(&self) -> bool {
+        self.handle
+            .as_ref()
+            .is_some_and(|handle| !handle.is_finished())
+    }
+
+    pub fn scan(&mut self, dir: &Path) {
+        if !self.in_progress() {
+            let dir = dir.to_owned();
+            self.handle = Some(std::thread::spawn(move || save_slots::scan(&dir)));
+        }
+    }
+
+    /// Pick up the result of a finished scan.
+    pub fn poll(&mut self) {
+        if self.handle.as_ref().is_some_and(JoinHandle::is_finished) {
+            let result = self
+                .handle
+                .take()
+                .unwrap()
+                .join()
+                .unwrap_or_else(|_| Err(std::io::Error::other("Slot scanner thread panicked")));
+            match result {
+                Ok(slots) => {
+                    self.slots = slots;
+                    self.last_error = None;
+                }
+                Err(error) => {
+                    let error = format!("Failed to list save slots: {error}");
+                    log::error!("{error}");
+                    self.last_error = Some(error);
+                }
+            }
+        }
+    }
+}
+
 fn previous_file_path_cache_path() -> PathBuf {
     let mut previous_file_path =
         dirs::cache_dir().expect("There is no cache directory on this system");
@@ -135,9 +286,17 @@ fn previous_file_path_cache_path() -> PathBuf {
 pub struct FileWatcher {
     pub file_choose_dialog: FileChooseDialog,
     pub file: Option<PathBuf>,
+    /// The `Saves` directory whose most recently written slot is followed, if any.
+    pub saves_dir: Option<PathBuf>,
+    /// When the newest slot was written as of the last switch. A slot written after that is
+    /// the game being played.
+    newest_slot: SystemTime,
+    next_slot_check: Instant,
     pub mtime: SystemTime,
     pub change_detected: bool,
     pub map_loader: MapLoader,
+    pub review_export: ReviewExport,
+    pub slot_scanner: SlotScanner,
 }
 
 impl Default for FileWatcher {
@@ -145,37 +304,126 @@ impl Default for FileWatcher {
         Self {
             file_choose_dialog: FileChooseDialog::default(),
             file: None,
+            saves_dir: None,
+            newest_slot: SystemTime::UNIX_EPOCH,
+            next_slot_check: Instant::now(),
             mtime: SystemTime::now(),
             change_detected: false,
             map_loader: MapLoader::default(),
+            review_export: ReviewExport::default(),
+            slot_scanner: SlotScanner::default(),
         }
     }
 }
 
 impl FileWatcher {
-    pub fn set_file_path(&mut self, file: &Path) {
+    fn watch(&mut self, file: &Path) {
         self.file = Some(file.to_path_buf());
         self.mtime = SystemTime::UNIX_EPOCH;
+    }
 
+    fn remember(path: &Path) {
         let cache_path = previous_file_path_cache_path();
-        std::fs::write(cache_path, file.to_str().unwrap())
+        std::fs::write(cache_path, path.to_str().unwrap())
             .expect("Failed to write file path to cache");
     }
 
+    pub fn set_file_path(&mut self, file: &Path) {
+        self.saves_dir = None;
+        self.watch(file);
+        Self::remember(file);
+    }
+
+    /// Follow the most recently written slot of the saves directory `dir`.
+    pub fn set_saves_dir(&mut self, dir: &Path) {
+        self.saves_dir = Some(dir.to_path_buf());
+        self.newest_slot = SystemTime::UNIX_EPOCH;
+        Self::remember(dir);
+        self.follow_latest_slot();
+        self.slot_scanner.scan(dir);
+    }
+
+    /// Watch a file, or follow the slots of a directory.
+    pub fn set_path(&mut self, path: &Path) {
+        if path.is_dir() {
+            self.set_saves_dir(path);
+        } else {
+            self.set_file_path(path);
+        }
+    }
+
+    /// Watch `slot` of the saves directory until the game writes another slot.
+    pub fn choose_slot(&mut self, slot: &Path) {
+        if let Some((_, modified)) = self.saves_dir.as_deref().and_then(save_slots::latest_slot) {
+            self.newest_slot = modified;
+        }
+        self.watch(slot);
+    }
+
+    /// Switch to the slot the game wrote last, if it was written since the last switch.
+    fn follow_latest_slot(&mut self) {
+        let Some(dir) = self.saves_dir.clone() else {
+            return;
+        };
+        let Some((slot, modified)) = save_slots::latest_slot(&dir) else {
+            return;
+        };
+        if modified <= self.newest_slot {
+            return;
+        }
+        self.newest_slot = modified;
+        if self.file.as_ref() != Some(&slot) {
+            log::info!("Following save slot {}", slot.display());
+            self.watch(&slot);
+            self.slot_scanner.scan(&dir);
+        }
+    }
+
+    /// Where the file dialogs start: the saves directory, the watched file, or the saves
+    /// directory of the game if it can be found.
+    fn dialog_directory(&self) -> PathBuf {
+        self.saves_dir
+            .clone()
+            .or_else(|| Some(self.file.as_deref()?.parent()?.to_path_buf()))
+            .or_else(save_slots::default_saves_dir)
+            .unwrap_or_else(|| PathBuf::from("."))
+    }
+
+    pub fn open_file_dialog(&mut self) {
+        let directory = self.dialog_directory();
+        self.file_choose_dialog.open(&directory);
+    }
+
+    pub fn open_folder_dialog(&mut self) {
+        let directory = self.dialog_directory();
+        self.file_choose_dialog.open_folder(&directory);
+    }
+
+    /// Ask where to save a review of `timeline`, starting in the same directory as the other
+    /// dialogs.
+    pub fn export_review(&mut self, timeline: Timeline, scorer: Box<dyn PlacementScorer>) {
+        let directory = self.dialog_directory();
+        self.review_export.start(&directory, timeline, scorer);
+    }
+
     pub fn use_previous_file_path(&mut self) {
         let cache_path = previous_file_path_cache_path();
         if let Ok(file_path) = std::fs::read_to_string(cache_path) {
-            self.set_file_path(&PathBuf::from(file_path));
+            self.set_path(&PathBuf::from(file_path));
         }
     }
 
     pub fn handle_file_dialog(&mut self) {
-        if let Some(file) = self.file_choose_dialog.take_result() {
-            self.set_file_path(&file);
+        if let Some(path) = self.file_choose_dialog.take_result() {
+            self.set_path(&path);
         }
     }
 
     pub fn reload_file_if_changed(&mut self) {
+        if self.saves_dir.is_some() && Instant::now() >= self.next_slot_check {
+            self.next_slot_check = Instant::now() + SLOT_CHECK_INTERVAL;
+            self.follow_latest_slot();
+        }
         if let Some(file) = self.file.as_ref() {
             let actual_mtime = file.metadata().ok().and_then(|md| md.modified().ok());
             if let Some(actual_mtime) = actual_mtime {
@@ -205,6 +453,40 @@ mod tests {
         assert!(!fw.change_detected);
         assert!(!fw.file_choose_dialog.is_open());
         assert!(!fw.map_loader.in_progress());
+        assert!(!fw.review_export.in_progress());
+        assert!(fw.saves_dir.is_none());
+    }
+
+    #[test]
+    fn test_saves_dir_follows_the_latest_slot() {
+        let dir = std::env::temp_dir().join("dorfromantische2_rs_test_saves_dir");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let now = SystemTime::now();
+        let write = |name: &str, age: u64| {
+            let file = std::fs::File::create(dir.join(name)).unwrap();
+            file.set_modified(now - Duration::from_secs(age)).unwrap();
+        };
+        write("slot0.sav", 20);
+        write("slot1.sav", 10);
+
+        // Not `set_path`, which would remember the directory for the next start.
+        let mut fw = FileWatcher {
+            saves_dir: Some(dir.clone()),
+            ..FileWatcher::default()
+        };
+        fw.follow_latest_slot();
+        assert_eq!(fw.file, Some(dir.join("slot1.sav")));
+
+        // A slot picked by hand stays until the game writes a slot.
+        fw.choose_slot(&dir.join("slot0.sav"));
+        fw.follow_latest_slot();
+        assert_eq!(fw.file, Some(dir.join("slot0.sav")));
+
+        write("slot1.sav", 0);
+        fw.follow_latest_slot();
+        assert_eq!(fw.file, Some(dir.join("slot1.sav")));
+        assert_eq!(fw.dialog_directory(), dir);
     }
 
     #[test]
@@ -216,4 +498,64 @@ mod tests {
         assert_eq!(fw.file.as_ref().unwrap(), &path);
         assert_eq!(fw.mtime, SystemTime::UNIX_EPOCH);
     }
+
+    #[test]
+    fn test_map_loader_reports_error() {
+        let mut loader = MapLoader::default();
+        loader.load(Path::new("/nonexistent/dorfromantische2_rs_test.sav"));
+        while loader.in_progress() {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(loader.take_result().is_none());
+        let error = loader.last_error.expect("No error recorded");
+        assert!(error.starts_with("Failed to open file"), "{error}");
+    }
+
+    #[test]
+    fn test_map_loader_parses_then_rebuilds() {
+        let path = Path::new("tests/fixtures/dorfromantik.dump");
+        if !path.exists() {
+            return;
+        }
+        let wait = |loader: &MapLoader| {
+            while loader.in_progress() {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        };
+
+        let mut loader = MapLoader::default();
+        loader.load(path);
+        wait(&loader);
+        let Some(Loaded::SaveGame(savegame)) = loader.take_result() else {
+            panic!("Expected the parsed savegame");
+        };
+
+        loader.rebuild(*savegame, Box::new(crate::scorer::DefaultScorer));
+        wait(&loader);
+        assert!(matches!(loader.take_result(), Some(Loaded::Rebuilt(..))));
+        assert!(loader.last_error.is_none());
+    }
+
+    #[test]
+    fn test_write_review_writes_json_and_csv() {
+        let path = Path::new("tests/fixtures/dorfromantik.dump");
+        if !path.exists() {
+            return;
+        }
+        let full = raw_data::SaveGame::load(path).unwrap();
+        let mut earlier = full.clone();
+        let removed = earlier.tiles.split_off(earlier.tiles.len() - 2);
+        earlier.tile_stack.insert(0, removed[0].clone());
+        let mut timeline = Timeline::default();
+        timeline.record(&earlier);
+        timeline.record(&full);
+
+        let json = std::env::temp_dir().join("dorfromantische2_rs_test_review.json");
+        write_review(&timeline, Box::new(crate::scorer::DefaultScorer), &json).unwrap();
+        let csv = std::fs::read_to_string(json.with_extension("csv")).unwrap();
+        assert_eq!(csv.lines().count(), 3);
+        assert!(std::fs::read_to_string(&json)
+            .unwrap()
+            .contains("\"summary\""));
+    }
 }
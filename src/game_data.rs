@@ -3,9 +3,19 @@ use std::collections::HashSet;
 use crate::{
     best_placements::BestPlacements,
     data::{EdgeMatch, HexPos, Terrain},
+    demand::Demand,
     group_assignments::GroupAssignments,
+    holes::Holes,
+    lookahead::{Lookahead, DEFAULT_BEAM_WIDTH, DEFAULT_DEPTH},
     map::Map,
+    quest_feasibility::{self, QuestFeasibility},
+    raw_data,
+    rollout::{RankedPolicy, RolloutConfig, Rollouts},
+    score::ScoreRules,
+    scorer::PlacementScorer,
+    survival::{GameStats, SurvivalModel, TurnEstimate},
     tile_frequency::TileFrequencies,
+    timeline::Timeline,
 };
 
 #[derive(Default)]
@@ -14,8 +24,22 @@ pub struct GameData {
     pub group_assignments: GroupAssignments,
     pub best_placements: BestPlacements,
     pub tile_frequencies: TileFrequencies,
+    /// Counters of the last loaded save.
+    pub stats: GameStats,
+    /// Every loaded save of the current game. Survives rebuilds of the map.
+    pub timeline: Timeline,
     /// Tiles with at least one non-matching edge. Computed lazily.
     imperfect_tiles: Option<HashSet<HexPos>>,
+    /// Empty regions enclosed by placed tiles. Computed lazily.
+    holes: Option<Holes>,
+    /// Search over the known tiles. Computed lazily.
+    lookahead: Option<Lookahead>,
+    /// Best placements rated by rollouts. Computed lazily.
+    rollouts: Option<Rollouts>,
+    /// Whether the active quests can still be completed. Computed lazily.
+    quest_feasibility: Option<Vec<QuestFeasibility>>,
+    /// Which patterns the frontier needs. Computed lazily.
+    demand: Option<Demand>,
 }
 
 impl GameData {
@@ -27,9 +51,132 @@ impl GameData {
         self.imperfect_tiles.as_ref().unwrap()
     }
 
+    /// Get or compute the empty regions that placed tiles enclose.
+    pub fn holes(&mut self) -> &Holes {
+        if self.holes.is_none() {
+            self.holes = Some(Holes::find(&self.map, &self.tile_frequencies));
+        }
+        self.holes.as_ref().unwrap()
+    }
+
+    /// Get or compute the best placements for the known tiles together.
+    pub fn lookahead(&mut self) -> &Lookahead {
+        if self.lookahead.is_none() {
+            let start = std::time::Instant::now();
+            self.lookahead = Some(Lookahead::search(
+                &self.map,
+                &self.group_assignments,
+                &self.tile_frequencies,
+                DEFAULT_DEPTH,
+                DEFAULT_BEAM_WIDTH,
+            ));
+            log::info!("Lookahead computed in: {:?}", start.elapsed());
+        }
+        self.lookahead.as_ref().unwrap()
+    }
+
+    pub fn survival_model(&self) -> SurvivalModel {
+        SurvivalModel::new(&self.stats, self.best_placements.rules().clone())
+    }
+
+    /// Expected remaining turns and game-over risk before the next placement.
+    pub fn survival(&self) -> TurnEstimate {
+        self.survival_model()
+            .estimate(self.map.tile_stack_count, &self.group_assignments)
+    }
+
+    /// Get or compute the feasibility of the active quests, ordered by group.
+    pub fn quest_feasibility(&mut self) -> &[QuestFeasibility] {
+        if self.quest_feasibility.is_none() {
+            let start = std::time::Instant::now();
+            self.quest_feasibility = Some(quest_feasibility::analyze(
+                &self.map,
+                &self.group_assignments,
+                &self.tile_frequencies,
+            ));
+            log::info!("Quest feasibility computed in: {:?}", start.elapsed());
+        }
+        self.quest_feasibility.as_ref().unwrap()
+    }
+
+    /// Get or compute which patterns the frontier needs.
+    pub fn demand(&mut self) -> &Demand {
+        if self.demand.is_none() {
+            let start = std::time::Instant::now();
+            self.demand = Some(Demand::compute(
+                &self.map,
+                &self.group_assignments,
+                &self.tile_frequencies,
+            ));
+            log::info!("Demand computed in: {:?}", start.elapsed());
+        }
+        self.demand.as_ref().unwrap()
+    }
+
+    /// Get or compute the rollouts of the best placements.
+    pub fn rollouts(&mut self) -> &Rollouts {
+        if self.rollouts.is_none() {
+            let start = std::time::Instant::now();
+            self.rollouts = Some(Rollouts::run(
+                &self.map,
+                &self.group_assignments,
+                &self.tile_frequencies,
+                &self.best_placements,
+                RolloutConfig::default(),
+                &RankedPolicy,
+            ));
+            log::info!("Rollouts computed in: {:?}", start.elapsed());
+        }
+        self.rollouts.as_ref().unwrap()
+    }
+
+    /// Rank the placements with `scorer` from now on.
+    pub fn set_scorer(&mut self, scorer: Box<dyn PlacementScorer>) {
+        self.best_placements.set_scorer(
+            scorer,
+            &self.map,
+            &self.group_assignments,
+            &self.tile_frequencies,
+        );
+        // The rollouts rate the top ranked placements.
+        self.rollouts = None;
+    }
+
+    /// Apply a newer save of the same game incrementally. Returns false, leaving everything
+    /// untouched, if the map has to be rebuilt from scratch instead.
+    pub fn update_from(&mut self, savegame: &raw_data::SaveGame) -> bool {
+        let start = std::time::Instant::now();
+        let Some(update) = self.map.extend_from(savegame) else {
+            return false;
+        };
+        self.stats = GameStats::from(savegame);
+        self.best_placements.set_rules(ScoreRules::from(savegame));
+        let changed: Vec<HexPos> = update.changed_positions().collect();
+        self.group_assignments.update(&self.map, &changed);
+        let changes = self.tile_frequencies.update(&self.map, &update);
+        self.best_placements.update(
+            &self.map,
+            &self.group_assignments,
+            &self.tile_frequencies,
+            &changes,
+        );
+        self.invalidate_cache();
+        log::info!(
+            "Map updated with {} new tiles in: {:?}",
+            update.added.len(),
+            start.elapsed()
+        );
+        true
+    }
+
     /// Invalidate cached computations (call after map reload).
     pub fn invalidate_cache(&mut self) {
         self.imperfect_tiles = None;
+        self.holes = None;
+        self.lookahead = None;
+        self.rollouts = None;
+        self.quest_feasibility = None;
+        self.demand = None;
     }
 }
 
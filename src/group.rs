@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use glam::Vec2;
 
@@ -11,12 +11,15 @@ use crate::{
 /// Index into the groups array, identifying a connected terrain group.
 pub type GroupIndex = usize;
 
+#[derive(Clone)]
 pub struct Group {
     pub kind: GroupKind,
     /// Kept for backward compatibility with shader/render code.
     pub terrain: crate::data::Terrain,
     pub segment_indices: HashSet<SegmentIndex>,
     pub open_edges: HashSet<HexPos>,
+    /// Open edges whose cell is taken by a preplaced tile that has not been reached yet.
+    pub preplaced_edges: HashSet<HexPos>,
     /// Quests that target this group (placed on tiles belonging to this group).
     pub quests: Vec<Quest>,
     /// Total unit count (houses, trees, fields, etc.) across all segments in this group.
@@ -74,6 +77,24 @@ impl Group {
             .sum()
     }
 
+    /// Collect the quests on the group's tiles that target its kind, once per tile.
+    pub fn compute_quests(
+        kind: GroupKind,
+        segment_indices: &HashSet<SegmentIndex>,
+        segments: &[Segment],
+        quests: &HashMap<HexPos, Quest>,
+    ) -> Vec<Quest> {
+        let mut positions = HashSet::new();
+        segment_indices
+            .iter()
+            .map(|&i| segments[i].pos)
+            .filter(|&pos| positions.insert(pos))
+            .filter_map(|pos| quests.get(&pos))
+            .filter(|quest| kind.accepts(quest.terrain))
+            .cloned()
+            .collect()
+    }
+
     /// How many units remain to fulfill each quest. Negative means already exceeded.
     pub fn remaining_per_quest(&self) -> Vec<(&Quest, i32)> {
         self.quests
@@ -2,6 +2,7 @@ use std::collections::{HashSet, VecDeque};
 
 use crate::{
     data::{GroupKind, HexPos, Terrain, HEX_SIDES},
+    disjoint_set::DisjointSet,
     group::{Group, GroupIndex},
     map::{Map, SegmentIndex},
 };
@@ -20,7 +21,6 @@ pub struct GroupAnalyzer<'a> {
 
     pub possible_placements: HashSet<HexPos>,
     pub groups: Vec<Group>,
-    pub assigned_groups: Vec<GroupIndex>,
 }
 
 impl<'a> GroupAnalyzer<'a> {
@@ -36,6 +36,9 @@ impl<'a> GroupAnalyzer<'a> {
             self.pos_queue.push_back(pos);
             self.discovered_pos.insert(pos);
             true
+        } else if self.map.preplaced_at(pos).is_some() {
+            // A preplaced tile will show up here, so nothing can be placed.
+            false
         } else {
             // If it doesn't, then it's a possible placement.
             self.possible_placements.insert(pos);
@@ -59,6 +62,7 @@ impl<'a> GroupAnalyzer<'a> {
         // Collect all members belonging to the same group as `segment`.
         let mut segment_indices = HashSet::new();
         let mut open_edges = HashSet::new();
+        let mut preplaced_edges = HashSet::new();
         let mut quests = Vec::new();
         // Track which tile positions we've already checked for quests.
         let mut checked_quest_positions = HashSet::new();
@@ -92,6 +96,9 @@ impl<'a> GroupAnalyzer<'a> {
 
                 if !neighbor_exists {
                     open_edges.insert(neighbor_pos);
+                    if self.map.preplaced_at(neighbor_pos).is_some() {
+                        preplaced_edges.insert(neighbor_pos);
+                    }
                     continue;
                 }
 
@@ -115,22 +122,15 @@ impl<'a> GroupAnalyzer<'a> {
                     });
             }
         }
-        // Derive a representative Terrain for backward compat (shader/render).
-        let group_terrain = match kind {
-            GroupKind::House => Terrain::House,
-            GroupKind::Forest => Terrain::Forest,
-            GroupKind::Wheat => Terrain::Wheat,
-            GroupKind::Rail => Terrain::Rail,
-            GroupKind::River => Terrain::River,
-        };
         let unit_count = Group::compute_unit_count(&segment_indices, &self.map.segments);
         let centroid = Group::compute_centroid(&segment_indices, &self.map.segments);
         let radius = Group::compute_radius(centroid, &segment_indices, &self.map.segments);
         self.groups.push(Group {
             kind,
-            terrain: group_terrain,
+            terrain: representative_terrain(kind),
             segment_indices,
             open_edges,
+            preplaced_edges,
             quests,
             unit_count,
             centroid,
@@ -164,23 +164,29 @@ impl<'a> GroupAnalyzer<'a> {
                     .for_each(|index| self.discover_groups_of_segment(index));
             }
         }
+    }
+}
 
-        self.assigned_groups
-            .resize(self.discovered_segments.len(), usize::MAX);
-        // For segments in multiple groups (stations), prefer the open group
-        // so they don't get hidden when only one group is closed.
-        for (group_index, group) in self.groups.iter().enumerate() {
-            for segment_index in &group.segment_indices {
-                let prev = self.assigned_groups[*segment_index];
-                if prev == usize::MAX || !group.is_closed() {
-                    self.assigned_groups[*segment_index] = group_index;
-                }
-            }
-        }
+/// Derive a representative Terrain for backward compat (shader/render).
+fn representative_terrain(kind: GroupKind) -> Terrain {
+    match kind {
+        GroupKind::House => Terrain::House,
+        GroupKind::Forest => Terrain::Forest,
+        GroupKind::Wheat => Terrain::Wheat,
+        GroupKind::Rail => Terrain::Rail,
+        GroupKind::River => Terrain::River,
     }
 }
 
-#[derive(Default)]
+/// Number of group kinds, so that every (segment, kind) pair gets its own disjoint-set element.
+const KIND_COUNT: usize = 5;
+
+/// The disjoint-set element of the membership of a segment in a group of `kind`.
+fn node_of(segment_index: SegmentIndex, kind: GroupKind) -> usize {
+    segment_index * KIND_COUNT + kind as usize
+}
+
+#[derive(Clone, Default)]
 pub struct GroupAssignments {
     /// Positions where tiles can be placed.
     pub possible_placements: HashSet<HexPos>,
@@ -188,32 +194,346 @@ pub struct GroupAssignments {
     pub groups: Vec<Group>,
     /// Mapping of segment index to group index.
     pub assigned_groups: Vec<GroupIndex>,
+
+    /// Tiles whose segments have been assigned.
+    tiles: HashSet<HexPos>,
+    /// Connected group memberships, with elements given by `node_of`.
+    sets: DisjointSet,
+    /// Group index of each set, only valid at the set's representative.
+    group_at_root: Vec<GroupIndex>,
 }
 
-impl From<&Map> for GroupAssignments {
-    fn from(map: &Map) -> Self {
-        let mut analyzer = GroupAnalyzer {
+impl<'a> GroupAnalyzer<'a> {
+    fn new(map: &'a Map) -> Self {
+        let origin = HexPos::new(0, 0);
+        Self {
             map,
-            pos_queue: VecDeque::from([HexPos::new(0, 0)]),
-            discovered_pos: HashSet::from([HexPos::new(0, 0)]),
+            pos_queue: VecDeque::from([origin]),
+            discovered_pos: HashSet::from([origin]),
 
             segment_queue: Vec::default(),
             discovered_segments: HashSet::default(),
 
             possible_placements: HashSet::default(),
             groups: Vec::default(),
-            assigned_groups: Vec::default(),
-        };
+        }
+    }
+}
+
+impl From<&Map> for GroupAssignments {
+    fn from(map: &Map) -> Self {
+        let mut analyzer = GroupAnalyzer::new(map);
         analyzer.run();
-        Self {
+        let mut assignments = Self {
             possible_placements: analyzer.possible_placements,
             groups: analyzer.groups,
-            assigned_groups: analyzer.assigned_groups,
-        }
+            tiles: analyzer.discovered_pos,
+            ..Self::default()
+        };
+        assignments.index_groups(map);
+        assignments
     }
 }
 
 impl GroupAssignments {
+    /// Build the disjoint sets and the segment assignments of flood-filled groups.
+    fn index_groups(&mut self, map: &Map) {
+        self.grow(map.segments.len());
+        for (group_index, group) in self.groups.iter().enumerate() {
+            let mut nodes = group
+                .segment_indices
+                .iter()
+                .map(|&segment_index| node_of(segment_index, group.kind));
+            let first = nodes.next().expect("Groups are never empty");
+            let root = nodes.fold(first, |root, node| self.sets.union(root, node));
+            self.group_at_root[root] = group_index;
+        }
+        for segment_index in 0..map.segments.len() {
+            self.reassign(map, segment_index);
+        }
+    }
+
+    fn grow(&mut self, segment_count: usize) {
+        self.sets.grow(segment_count * KIND_COUNT);
+        self.group_at_root
+            .resize(segment_count * KIND_COUNT, usize::MAX);
+        self.assigned_groups.resize(segment_count, usize::MAX);
+    }
+
+    /// The group the segment belongs to as a member of `kind`, if it has been assigned.
+    fn group_index_of(
+        &mut self,
+        segment_index: SegmentIndex,
+        kind: GroupKind,
+    ) -> Option<GroupIndex> {
+        let root = self.sets.find(node_of(segment_index, kind));
+        let group_index = self.group_at_root[root];
+        (group_index != usize::MAX).then_some(group_index)
+    }
+
+    /// Map the segment to one of its groups. For segments in multiple groups (stations),
+    /// prefer the open group so they don't get hidden when only one group is closed.
+    fn reassign(&mut self, map: &Map, segment_index: SegmentIndex) {
+        let terrain = map.segment(segment_index).terrain;
+        let mut assigned = usize::MAX;
+        for &kind in GroupKind::memberships_of(terrain) {
+            let Some(group_index) = self.group_index_of(segment_index, kind) else {
+                continue;
+            };
+            if assigned == usize::MAX {
+                assigned = group_index;
+            }
+            if !self.groups[group_index].is_closed() {
+                assigned = group_index;
+                break;
+            }
+        }
+        self.assigned_groups[segment_index] = assigned;
+    }
+
+    /// Add the tile at `pos`, which must already be on `map`, connecting its segments to the
+    /// groups of previously added neighbors.
+    ///
+    /// Groups joined by the new tile are merged in place, so adding the tiles of a map in any
+    /// order gives the same groups as the flood fill of `GroupAssignments::from`.
+    pub fn add_tile(&mut self, map: &Map, pos: HexPos) {
+        if !self.tiles.insert(pos) {
+            return;
+        }
+        self.grow(map.segments.len());
+
+        self.possible_placements.remove(&pos);
+        for rotation in 0..HEX_SIDES {
+            let neighbor_pos = Map::neighbor_pos_of(pos, rotation);
+            if !map.has(neighbor_pos) && map.preplaced_at(neighbor_pos).is_none() {
+                self.possible_placements.insert(neighbor_pos);
+            }
+        }
+
+        // Memberships of the neighbors facing this tile. Their groups may have been closed.
+        let mut facing = Vec::new();
+        // Memberships of the new segments. Their groups have grown.
+        let mut grown = Vec::new();
+        // Segments that may need another group assigned.
+        let mut reassign = Vec::new();
+
+        for rotation in 0..HEX_SIDES {
+            let neighbor_pos = Map::neighbor_pos_of(pos, rotation);
+            if !self.tiles.contains(&neighbor_pos) {
+                continue;
+            }
+            let back_rotation = Map::opposite_side(rotation);
+            let Some((neighbor_index, neighbor)) = map.segment_at(neighbor_pos, back_rotation)
+            else {
+                continue;
+            };
+            for &kind in GroupKind::memberships_of(neighbor.terrain) {
+                if let Some(group_index) = self.group_index_of(neighbor_index, kind) {
+                    let group = &mut self.groups[group_index];
+                    group.open_edges.remove(&pos);
+                    group.preplaced_edges.remove(&pos);
+                    facing.push(node_of(neighbor_index, kind));
+                }
+            }
+        }
+
+        for segment_index in map.segment_indices_at(pos).into_iter().flatten() {
+            reassign.push(segment_index);
+            let segment = map.segment(segment_index);
+            for &kind in GroupKind::memberships_of(segment.terrain) {
+                let mut open_edges = HashSet::new();
+                let mut preplaced_edges = HashSet::new();
+                let mut connections = Vec::new();
+                for rotation in
+                    (0..HEX_SIDES).filter(|&rotation| segment.contains_rotation(rotation))
+                {
+                    let neighbor_pos = Map::neighbor_pos_of(pos, rotation);
+                    if !self.tiles.contains(&neighbor_pos) {
+                        open_edges.insert(neighbor_pos);
+                        if map.preplaced_at(neighbor_pos).is_some() {
+                            preplaced_edges.insert(neighbor_pos);
+                        }
+                        continue;
+                    }
+                    let back_rotation = Map::opposite_side(rotation);
+                    if let Some((neighbor_index, neighbor)) =
+                        map.segment_at(neighbor_pos, back_rotation)
+                    {
+                        if kind.accepts(neighbor.terrain) {
+                            connections.push(node_of(neighbor_index, kind));
+                        }
+                    }
+                }
+
+                let node = node_of(segment_index, kind);
+                self.group_at_root[node] = self.groups.len();
+                self.groups.push(Group {
+                    kind,
+                    terrain: representative_terrain(kind),
+                    segment_indices: HashSet::from([segment_index]),
+                    open_edges,
+                    preplaced_edges,
+                    quests: Vec::new(),
+                    unit_count: segment.unit_count,
+                    centroid: crate::hex::hex_to_world(pos),
+                    radius: 0.0,
+                });
+                for connection in connections {
+                    self.merge(node, connection, &mut reassign);
+                }
+                grown.push(node);
+            }
+        }
+
+        // Refresh the values derived from the members of grown groups.
+        let mut refreshed = HashSet::new();
+        for &node in &grown {
+            let group_index = self.group_at_root[self.sets.find(node)];
+            if !refreshed.insert(group_index) {
+                continue;
+            }
+            let group = &mut self.groups[group_index];
+            group.centroid = Group::compute_centroid(&group.segment_indices, &map.segments);
+            group.radius =
+                Group::compute_radius(group.centroid, &group.segment_indices, &map.segments);
+            group.quests = Group::compute_quests(
+                group.kind,
+                &group.segment_indices,
+                &map.segments,
+                &map.quests,
+            );
+        }
+
+        // Segments in multiple groups move away from groups that just got closed.
+        let mut checked = HashSet::new();
+        for node in facing.into_iter().chain(grown) {
+            let group_index = self.group_at_root[self.sets.find(node)];
+            let group = &self.groups[group_index];
+            if checked.insert(group_index) && group.is_closed() {
+                reassign.extend(group.segment_indices.iter().copied().filter(|&index| {
+                    GroupKind::memberships_of(map.segment(index).terrain).len() > 1
+                }));
+            }
+        }
+
+        for segment_index in reassign {
+            self.reassign(map, segment_index);
+        }
+    }
+
+    /// Merge the groups of the memberships `a` and `b` into the larger of the two. The
+    /// members of the smaller group are added to `reassign`.
+    fn merge(&mut self, a: usize, b: usize, reassign: &mut Vec<SegmentIndex>) {
+        let (root_a, root_b) = (self.sets.find(a), self.sets.find(b));
+        if root_a == root_b {
+            return;
+        }
+        let (mut keep, gone) = {
+            let (group_a, group_b) = (self.group_at_root[root_a], self.group_at_root[root_b]);
+            if self.groups[group_a].segment_indices.len()
+                < self.groups[group_b].segment_indices.len()
+            {
+                (group_b, group_a)
+            } else {
+                (group_a, group_b)
+            }
+        };
+
+        // Removing the merged group moves the last group into its slot.
+        let last = self.groups.len() - 1;
+        let removed = self.groups.swap_remove(gone);
+        if gone != last {
+            if keep == last {
+                keep = gone;
+            }
+            let moved = &self.groups[gone];
+            let moved_node = node_of(*moved.segment_indices.iter().next().unwrap(), moved.kind);
+            let moved_root = self.sets.find(moved_node);
+            self.group_at_root[moved_root] = gone;
+            for &segment_index in &self.groups[gone].segment_indices {
+                if self.assigned_groups[segment_index] == last {
+                    self.assigned_groups[segment_index] = gone;
+                }
+            }
+        }
+
+        let root = self.sets.union(root_a, root_b);
+        self.group_at_root[root] = keep;
+
+        reassign.extend(removed.segment_indices.iter().copied());
+        let group = &mut self.groups[keep];
+        group.segment_indices.extend(removed.segment_indices);
+        group.open_edges.extend(removed.open_edges);
+        group.preplaced_edges.extend(removed.preplaced_edges);
+        group.unit_count += removed.unit_count;
+    }
+
+    /// A preplaced tile appeared or disappeared at the empty cell `pos`.
+    fn update_preplaced(&mut self, map: &Map, pos: HexPos) {
+        let preplaced = map.preplaced_at(pos).is_some();
+        let mut next_to_tile = false;
+        for rotation in 0..HEX_SIDES {
+            let neighbor_pos = Map::neighbor_pos_of(pos, rotation);
+            if !self.tiles.contains(&neighbor_pos) {
+                continue;
+            }
+            next_to_tile = true;
+            let back_rotation = Map::opposite_side(rotation);
+            let Some((neighbor_index, neighbor)) = map.segment_at(neighbor_pos, back_rotation)
+            else {
+                continue;
+            };
+            for &kind in GroupKind::memberships_of(neighbor.terrain) {
+                if let Some(group_index) = self.group_index_of(neighbor_index, kind) {
+                    let group = &mut self.groups[group_index];
+                    if preplaced && group.open_edges.contains(&pos) {
+                        group.preplaced_edges.insert(pos);
+                    } else {
+                        group.preplaced_edges.remove(&pos);
+                    }
+                }
+            }
+        }
+
+        if next_to_tile && !preplaced {
+            self.possible_placements.insert(pos);
+        } else {
+            self.possible_placements.remove(&pos);
+        }
+    }
+
+    /// Bring the assignments up to date after the cells at `changed` got a tile, the quest of
+    /// their tile changed, or a preplaced tile appeared or disappeared there (see
+    /// `Map::extend_from`).
+    pub fn update(&mut self, map: &Map, changed: &[HexPos]) {
+        for &pos in changed {
+            if self.tiles.contains(&pos) {
+                self.refresh_quests(map, pos);
+            } else if map.has(pos) {
+                self.add_tile(map, pos);
+            } else {
+                self.update_preplaced(map, pos);
+            }
+        }
+    }
+
+    /// Read the quests of the groups through the tile at `pos` from `map` again.
+    fn refresh_quests(&mut self, map: &Map, pos: HexPos) {
+        for segment_index in map.segment_indices_at(pos).into_iter().flatten() {
+            for &kind in GroupKind::memberships_of(map.segment(segment_index).terrain) {
+                if let Some(group_index) = self.group_index_of(segment_index, kind) {
+                    let group = &mut self.groups[group_index];
+                    group.quests = Group::compute_quests(
+                        group.kind,
+                        &group.segment_indices,
+                        &map.segments,
+                        &map.quests,
+                    );
+                }
+            }
+        }
+    }
+
     pub fn group_of(&self, segment_index: SegmentIndex) -> Option<GroupIndex> {
         let group_index = self.assigned_groups[segment_index];
         if group_index == usize::MAX {
@@ -222,4 +542,20 @@ impl GroupAssignments {
             Some(group_index)
         }
     }
+
+    /// The group the segment belongs to as a member of `kind`.
+    pub fn group_of_kind(
+        &self,
+        segment_index: SegmentIndex,
+        kind: GroupKind,
+    ) -> Option<GroupIndex> {
+        let assigned = self.group_of(segment_index)?;
+        if self.groups[assigned].kind == kind {
+            return Some(assigned);
+        }
+        // Only stations are in two groups, so this is rare.
+        self.groups
+            .iter()
+            .position(|group| group.kind == kind && group.segment_indices.contains(&segment_index))
+    }
 }
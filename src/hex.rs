@@ -23,6 +23,36 @@ pub fn neighbor_pos_of(pos: HexPos, rotation: Rotation) -> HexPos {
     }
 }
 
+/// Side of a preplaced-tile section, in world units. Section (`x`, `y`) is the square of this
+/// size centred on (`x`, `y`) times the size.
+///
+/// The game does not store the section layout. This size is fitted to the test fixture, where
+/// the reached preplaced tiles of 38 of the 45 listed sections lie in the square of their
+/// section, spread over all of it.
+pub const SECTION_SIZE: f32 = 28.0;
+
+/// Convert the savegame's offset coordinates (`s`, `t`) to axial coordinates.
+///
+/// Hex grid tutorial:
+/// https://www.redblobgames.com/grids/hexagons/#line-drawing
+pub fn offset_to_hex(s: i32, t: i32) -> HexPos {
+    HexPos::new(s, t - ((s + 1) & -2i32) / 2)
+}
+
+/// Centre tile of the preplaced-tile section at (`x`, `y`) of the section grid.
+///
+/// The cell of a preplaced tile within its section is derived from the preplaced-tile seed and
+/// is not stored, so the section centre stands in for it until the tile is reached.
+pub fn section_to_hex(x: i32, y: i32) -> HexPos {
+    world_to_hex(WorldPos(Vec2::new(x as f32, y as f32) * SECTION_SIZE))
+}
+
+/// Section grid position of the section `pos` lies in.
+pub fn hex_to_section(pos: HexPos) -> (i32, i32) {
+    let section = hex_to_world(pos).0 / SECTION_SIZE;
+    (section.x.round() as i32, section.y.round() as i32)
+}
+
 pub fn hex_to_world(pos: HexPos) -> WorldPos {
     WorldPos(Vec2::new(
         pos.x() as f32 * 1.5,
@@ -84,6 +114,28 @@ mod tests {
         neighbor_pos_of(HexPos::ZERO, 6);
     }
 
+    #[test]
+    fn test_offset_to_hex_known() {
+        assert_eq!(offset_to_hex(0, 0), HexPos::ZERO);
+        assert_eq!(offset_to_hex(1, 0), HexPos::new(1, -1));
+        assert_eq!(offset_to_hex(2, 3), HexPos::new(2, 2));
+        assert_eq!(offset_to_hex(-1, 0), HexPos::new(-1, 0));
+        assert_eq!(offset_to_hex(-2, 0), HexPos::new(-2, 1));
+    }
+
+    #[test]
+    fn test_section_to_hex_centres_are_distinct() {
+        assert_eq!(section_to_hex(0, 0), HexPos::ZERO);
+        let mut seen = std::collections::HashSet::new();
+        for x in -4..=4 {
+            for y in -4..=4 {
+                let centre = section_to_hex(x, y);
+                assert!(seen.insert(centre), "Duplicate at ({x},{y})");
+                assert_eq!(hex_to_section(centre), (x, y));
+            }
+        }
+    }
+
     #[test]
     fn test_hex_to_world_origin() {
         assert_eq!(hex_to_world(HexPos::ZERO), WorldPos::ZERO);
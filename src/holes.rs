@@ -0,0 +1,212 @@
+//! Empty regions that placed tiles enclose completely. `would_create_split` keeps placements
+//! from cutting up the empty cells around one position; this looks at the whole map. A hole
+//! only ever shrinks, and a cell whose placed neighbors no known pattern matches on every side
+//! can never be filled perfectly.
+
+use std::collections::{HashSet, VecDeque};
+
+use crate::{
+    best_placements::{
+        constraints_at, fit_chance_for_constraints, is_occupied, pattern_fits_perfectly,
+    },
+    data::{HexPos, Terrain, HEX_SIDES},
+    map::Map,
+    tile_frequency::TileFrequencies,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HoleCell {
+    pub pos: HexPos,
+    /// Edges of the placed neighbors, `None` towards other cells of the hole.
+    pub constraints: [Option<Terrain>; HEX_SIDES],
+    /// Chance that a random tile fits the cell.
+    pub fit_chance: f32,
+    pub fit_unique: u16,
+    /// Chance that a random tile matches every placed neighbor.
+    pub perfect_chance: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hole {
+    /// Ordered by position.
+    pub cells: Vec<HoleCell>,
+}
+
+impl Hole {
+    pub fn size(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Whether every cell can take a tile at all.
+    pub fn is_fillable(&self) -> bool {
+        self.cells.iter().all(|cell| cell.fit_chance > 0.0)
+    }
+
+    /// Whether every cell has a known pattern that matches all of its placed neighbors.
+    pub fn can_fill_perfectly(&self) -> bool {
+        self.cells.iter().all(|cell| cell.perfect_chance > 0.0)
+    }
+
+    /// The hardest cell to fill.
+    pub fn min_fit_chance(&self) -> f32 {
+        self.cells
+            .iter()
+            .map(|cell| cell.fit_chance)
+            .fold(1.0, f32::min)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Holes {
+    /// Ordered by their first cell.
+    pub holes: Vec<Hole>,
+}
+
+/// Chance that a random tile matches every constrained side of a cell in some rotation.
+pub fn perfect_chance_for_constraints(
+    freqs: &TileFrequencies,
+    constraints: &[Option<Terrain>; HEX_SIDES],
+) -> f32 {
+    if freqs.total_tiles == 0 {
+        return 0.0;
+    }
+    let matching: usize = freqs
+        .entries
+        .iter()
+        .filter(|entry| pattern_fits_perfectly(&entry.edges.0, constraints))
+        .map(|entry| entry.count)
+        .sum();
+    matching as f32 / freqs.total_tiles as f32
+}
+
+/// Connected regions of `empty` cells among `cells` that do not reach a position outside
+/// `in_bounds`. Every cell outside the bounds counts as empty, so regions that reach them are
+/// open to the rest of the plane.
+pub(crate) fn enclosed_regions(
+    cells: impl IntoIterator<Item = HexPos>,
+    is_empty: impl Fn(HexPos) -> bool,
+    in_bounds: impl Fn(HexPos) -> bool,
+) -> Vec<Vec<HexPos>> {
+    let mut visited: HashSet<HexPos> = HashSet::new();
+    let mut regions = Vec::new();
+    for start in cells {
+        if !is_empty(start) || !visited.insert(start) {
+            continue;
+        }
+        let mut region = Vec::new();
+        let mut open = false;
+        let mut queue = VecDeque::from([start]);
+        while let Some(pos) = queue.pop_front() {
+            region.push(pos);
+            for side in 0..HEX_SIDES {
+                let neighbor = Map::neighbor_pos_of(pos, side);
+                if !in_bounds(neighbor) {
+                    open = true;
+                } else if is_empty(neighbor) && visited.insert(neighbor) {
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        if !open {
+            region.sort_by_key(|pos| (pos.x(), pos.y()));
+            regions.push(region);
+        }
+    }
+    regions.sort_by_key(|region| (region[0].x(), region[0].y()));
+    regions
+}
+
+impl Holes {
+    pub fn find(map: &Map, freqs: &TileFrequencies) -> Self {
+        let regions = enclosed_regions(
+            map.iter_indexed_positions(),
+            |pos| !is_occupied(map, pos),
+            |pos| map.tile_key(pos).is_some(),
+        );
+
+        let holes = regions
+            .into_iter()
+            .map(|region| Hole {
+                cells: region
+                    .into_iter()
+                    .map(|pos| {
+                        let constraints = constraints_at(map, pos);
+                        let (fit_chance, fit_unique) =
+                            fit_chance_for_constraints(freqs, &constraints);
+                        HoleCell {
+                            pos,
+                            constraints,
+                            fit_chance,
+                            fit_unique,
+                            perfect_chance: perfect_chance_for_constraints(freqs, &constraints),
+                        }
+                    })
+                    .collect(),
+            })
+            .collect();
+        Self { holes }
+    }
+
+    /// Holes with a cell that can never be filled perfectly.
+    pub fn imperfect(&self) -> impl Iterator<Item = &Hole> {
+        self.holes.iter().filter(|hole| !hole.can_fill_perfectly())
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.holes.iter().map(Hole::size).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ring(center: HexPos) -> HashSet<HexPos> {
+        (0..HEX_SIDES)
+            .map(|side| Map::neighbor_pos_of(center, side))
+            .collect()
+    }
+
+    fn square(size: i32) -> Vec<HexPos> {
+        (0..size)
+            .flat_map(|y| (0..size).map(move |x| HexPos::new(x, y)))
+            .collect()
+    }
+
+    #[test]
+    fn test_enclosed_regions_skip_the_outside() {
+        let center = HexPos::new(3, 3);
+        let occupied = ring(center);
+        let in_bounds = |pos: HexPos| (0..7).contains(&pos.x()) && (0..7).contains(&pos.y());
+        let regions = enclosed_regions(square(7), |pos| !occupied.contains(&pos), in_bounds);
+        assert_eq!(regions, vec![vec![center]]);
+
+        // Opening the ring joins the cell with the outside.
+        let open: HashSet<HexPos> = occupied
+            .iter()
+            .copied()
+            .filter(|&pos| pos != Map::neighbor_pos_of(center, 0))
+            .collect();
+        let regions = enclosed_regions(square(7), |pos| !open.contains(&pos), in_bounds);
+        assert!(regions.is_empty());
+    }
+
+    #[test]
+    fn test_enclosed_regions_join_neighbors() {
+        let (a, b) = (
+            HexPos::new(3, 3),
+            Map::neighbor_pos_of(HexPos::new(3, 3), 1),
+        );
+        let occupied: HashSet<HexPos> = ring(a)
+            .union(&ring(b))
+            .copied()
+            .filter(|&pos| pos != a && pos != b)
+            .collect();
+        let in_bounds = |pos: HexPos| (0..8).contains(&pos.x()) && (0..8).contains(&pos.y());
+        let regions = enclosed_regions(square(8), |pos| !occupied.contains(&pos), in_bounds);
+        assert_eq!(regions.len(), 1);
+        let mut expected = vec![a, b];
+        expected.sort_by_key(|pos| (pos.x(), pos.y()));
+        assert_eq!(regions[0], expected);
+    }
+}
@@ -1,10 +1,25 @@
 pub mod best_placements;
 pub mod coords;
 pub mod data;
+pub mod demand;
+pub mod disjoint_set;
+pub mod export;
 pub mod game;
 pub mod group;
 pub mod group_assignments;
 pub mod hex;
+pub mod holes;
+pub mod lookahead;
 pub mod map;
+pub mod nrbf_tree;
+pub mod quest_feasibility;
 pub mod raw_data;
+pub mod replay;
+pub mod rollout;
+pub mod save_slots;
+pub mod savegame_writer;
+pub mod score;
+pub mod scorer;
+pub mod survival;
 pub mod tile_frequency;
+pub mod timeline;
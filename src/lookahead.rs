@@ -0,0 +1,490 @@
+//! Search over the known part of the tile stack. The queued tiles are placed one after another
+//! on a simulated board, and the sequence that scores best as a whole is compared with placing
+//! each tile where the default ranking of `BestPlacements` puts it first.
+//!
+//! A placement is valued by what it does to the board, not by the game's score:
+//! matching edges, a bonus for surrounding the tile completely and a penalty for every empty
+//! cell that no known tile pattern fits any more.
+
+use std::{cmp::Reverse, collections::HashSet};
+
+use crate::{
+    best_placements::{
+        constraints_at, count_matches, splits_empty_neighbors, BestPlacements, FitChanceCache,
+    },
+    data::{EdgeProfile, HexPos, Rotation, Segment, Terrain, HEX_SIDES},
+    group_assignments::GroupAssignments,
+    map::Map,
+    tile_frequency::TileFrequencies,
+};
+
+pub const DEFAULT_DEPTH: usize = 3;
+pub const DEFAULT_BEAM_WIDTH: usize = 8;
+
+/// Extra value of a placement that matches on all six sides.
+const PERFECT_BONUS: i32 = 3;
+/// Value lost per empty cell that the placement makes unfillable.
+const DEAD_CELL_PENALTY: i32 = 4;
+
+/// What a single simulated placement does to the board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StepValue {
+    pub matching_edges: u8,
+    /// All six neighbors are tiles and match.
+    pub perfect: bool,
+    /// Empty neighbors that some known pattern fitted before and none fits after.
+    pub dead_cells: u8,
+}
+
+impl StepValue {
+    pub fn total(&self) -> i32 {
+        i32::from(self.matching_edges) + i32::from(self.perfect) * PERFECT_BONUS
+            - i32::from(self.dead_cells) * DEAD_CELL_PENALTY
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LookaheadStep {
+    pub pos: HexPos,
+    pub rotation: Rotation,
+    pub value: StepValue,
+}
+
+/// The best sequence found for the known tiles, next to greedy play.
+#[derive(Clone, Debug, Default)]
+pub struct Lookahead {
+    /// One step per known tile, starting with the next tile.
+    pub sequence: Vec<LookaheadStep>,
+    /// Every tile placed where the default ranking of `BestPlacements` puts it first, as when
+    /// following the top recommendation each turn.
+    pub greedy: Vec<LookaheadStep>,
+}
+
+fn total_of(steps: &[LookaheadStep]) -> i32 {
+    steps.iter().map(|step| step.value.total()).sum()
+}
+
+impl Lookahead {
+    /// Search placements for the first `depth` tiles of `map.tile_queue`, keeping the
+    /// `beam_width` best partial sequences after each tile.
+    pub fn search(
+        map: &Map,
+        groups: &GroupAssignments,
+        freqs: &TileFrequencies,
+        depth: usize,
+        beam_width: usize,
+    ) -> Self {
+        let mut search = Search {
+            map,
+            freqs,
+            frontier: sorted(groups.possible_placements.iter().copied()),
+            fit_cache: FitChanceCache::default(),
+        };
+        let greedy = search.ranked(groups, depth);
+        let sequence = search.beam(depth, beam_width.max(1));
+        // The beam values placements differently than the ranking, so it can miss the greedy
+        // line.
+        let sequence = if total_of(&sequence) >= total_of(&greedy) {
+            sequence
+        } else {
+            greedy.clone()
+        };
+        Self { sequence, greedy }
+    }
+
+    pub fn total(&self) -> i32 {
+        total_of(&self.sequence)
+    }
+
+    pub fn greedy_total(&self) -> i32 {
+        total_of(&self.greedy)
+    }
+
+    /// How much better the best sequence is than greedy play. Never negative.
+    pub fn improvement(&self) -> i32 {
+        self.total() - self.greedy_total()
+    }
+
+    /// Whether the best sequence places the next tile somewhere else than greedy play.
+    pub fn differs_from_greedy(&self) -> bool {
+        self.sequence.first().map(|step| (step.pos, step.rotation))
+            != self.greedy.first().map(|step| (step.pos, step.rotation))
+    }
+}
+
+/// Simulated placements on top of the real map. Only a handful of tiles are ever placed, so
+/// copying it per search node is cheap.
+#[derive(Clone)]
+pub(crate) struct Board<'a> {
+    pub(crate) map: &'a Map,
+    pub(crate) placed: Vec<(HexPos, EdgeProfile)>,
+}
+
+impl<'a> Board<'a> {
+    pub(crate) fn new(map: &'a Map) -> Self {
+        Self {
+            map,
+            placed: Vec::new(),
+        }
+    }
+
+    fn placed_at(&self, pos: HexPos) -> Option<&EdgeProfile> {
+        self.placed
+            .iter()
+            .find(|(placed_pos, _)| *placed_pos == pos)
+            .map(|(_, profile)| profile)
+    }
+
+    pub(crate) fn is_free(&self, pos: HexPos) -> bool {
+        !self.map.has(pos) && self.map.preplaced_at(pos).is_none() && self.placed_at(pos).is_none()
+    }
+
+    pub(crate) fn constraints_at(&self, pos: HexPos) -> [Option<Terrain>; HEX_SIDES] {
+        let mut constraints = constraints_at(self.map, pos);
+        for (side, constraint) in constraints.iter_mut().enumerate() {
+            if let Some(profile) = self.placed_at(Map::neighbor_pos_of(pos, side)) {
+                *constraint = Some(profile.at_index(Map::opposite_side(side)));
+            }
+        }
+        constraints
+    }
+
+    /// Free cells the next tile can go to, in a fixed order.
+    pub(crate) fn candidates(&self, frontier: &[HexPos]) -> Vec<HexPos> {
+        let around_placed = self
+            .placed
+            .iter()
+            .flat_map(|&(pos, _)| (0..HEX_SIDES).map(move |side| Map::neighbor_pos_of(pos, side)));
+        sorted(
+            frontier
+                .iter()
+                .copied()
+                .chain(around_placed)
+                .filter(|&pos| self.is_free(pos)),
+        )
+    }
+}
+
+/// The real map and groups with simulated placements on top, for ranking placements the way
+/// `BestPlacements` does. Both are only copied once something looks at them.
+pub(crate) struct Game<'a> {
+    map: &'a Map,
+    groups: &'a GroupAssignments,
+    /// Placements not applied to `copy` yet.
+    pending: Vec<(HexPos, Vec<Segment>, Rotation)>,
+    copy: Option<(Map, GroupAssignments)>,
+}
+
+impl<'a> Game<'a> {
+    pub(crate) fn new(map: &'a Map, groups: &'a GroupAssignments) -> Self {
+        Self {
+            map,
+            groups,
+            pending: Vec::new(),
+            copy: None,
+        }
+    }
+
+    pub(crate) fn place(&mut self, pos: HexPos, tile: &[Segment], rotation: Rotation) {
+        self.pending.push((pos, tile.to_vec(), rotation));
+    }
+
+    /// The map and groups after the placements so far, with `tile` as the next tile.
+    pub(crate) fn with_next_tile(&mut self, tile: &[Segment]) -> (&Map, &GroupAssignments) {
+        let (base_map, base_groups) = (self.map, self.groups);
+        let (map, groups) = self
+            .copy
+            .get_or_insert_with(|| (base_map.clone(), base_groups.clone()));
+        for (pos, segments, rotation) in self.pending.drain(..) {
+            map.place(pos, &segments, rotation);
+            groups.update(map, &[pos]);
+        }
+        map.next_tile = tile.to_vec();
+        (map, groups)
+    }
+}
+
+/// Symmetric tiles look the same in several rotations. Only the first of each is kept.
+pub(crate) fn distinct_rotations(profile: &EdgeProfile) -> Vec<(Rotation, EdgeProfile)> {
+    let mut seen = HashSet::new();
+    (0..HEX_SIDES)
+        .map(|rotation| (rotation, profile.rotated(rotation)))
+        .filter(|(_, rotated)| seen.insert(rotated.clone()))
+        .collect()
+}
+
+pub(crate) fn sorted(positions: impl Iterator<Item = HexPos>) -> Vec<HexPos> {
+    let mut positions: Vec<HexPos> = positions.collect();
+    positions.sort_by_key(|pos| (pos.x(), pos.y()));
+    positions.dedup();
+    positions
+}
+
+/// Value of placing `profile` at `pos` on `board`, or `None` if it does not fit there or
+/// would split an empty region.
+pub(crate) fn step_value(
+    board: &Board,
+    pos: HexPos,
+    profile: &EdgeProfile,
+    freqs: &TileFrequencies,
+    fit_cache: &mut FitChanceCache,
+) -> Option<StepValue> {
+    let occupied = std::array::from_fn(|side| !board.is_free(Map::neighbor_pos_of(pos, side)));
+    if splits_empty_neighbors(occupied) {
+        return None;
+    }
+    let constraints = board.constraints_at(pos);
+    let (matching_edges, legal) = count_matches(profile, &constraints);
+    if !legal {
+        return None;
+    }
+
+    Some(StepValue {
+        matching_edges,
+        perfect: constraints.iter().all(Option::is_some) && matching_edges == HEX_SIDES as u8,
+        dead_cells: dead_cells(board, pos, profile, freqs, fit_cache),
+    })
+}
+
+/// Empty neighbors of `pos` that some known pattern fits now and none fits once `profile` is
+/// placed at `pos`.
+pub(crate) fn dead_cells(
+    board: &Board,
+    pos: HexPos,
+    profile: &EdgeProfile,
+    freqs: &TileFrequencies,
+    fit_cache: &mut FitChanceCache,
+) -> u8 {
+    let mut dead_cells = 0;
+    for side in 0..HEX_SIDES {
+        let neighbor_pos = Map::neighbor_pos_of(pos, side);
+        if !board.is_free(neighbor_pos) {
+            continue;
+        }
+        let before = board.constraints_at(neighbor_pos);
+        let mut after = before;
+        after[Map::opposite_side(side)] = Some(profile.at_index(side));
+        let (chance_before, _) = fit_cache.get(freqs, &before);
+        let (chance_after, _) = fit_cache.get(freqs, &after);
+        if chance_before > 0.0 && chance_after == 0.0 {
+            dead_cells += 1;
+        }
+    }
+    dead_cells
+}
+
+struct Search<'a> {
+    map: &'a Map,
+    freqs: &'a TileFrequencies,
+    frontier: Vec<HexPos>,
+    fit_cache: FitChanceCache,
+}
+
+struct Node<'a> {
+    board: Board<'a>,
+    steps: Vec<LookaheadStep>,
+    total: i32,
+}
+
+impl<'a> Search<'a> {
+    fn evaluate(&mut self, board: &Board, pos: HexPos, profile: &EdgeProfile) -> Option<StepValue> {
+        step_value(board, pos, profile, self.freqs, &mut self.fit_cache)
+    }
+
+    /// Place each of the first `depth` known tiles where the default ranking puts it first.
+    /// Stops early if a tile fits nowhere.
+    fn ranked(&mut self, groups: &GroupAssignments, depth: usize) -> Vec<LookaheadStep> {
+        let map = self.map;
+        let mut board = Board::new(map);
+        let mut game = Game::new(map, groups);
+        let mut steps = Vec::new();
+
+        for tile in map.tile_queue.iter().take(depth) {
+            let profile = EdgeProfile::from_segments(tile);
+            let mut moves = Vec::new();
+            for pos in board.candidates(&self.frontier) {
+                for (rotation, rotated) in distinct_rotations(&profile) {
+                    if let Some(value) = self.evaluate(&board, pos, &rotated) {
+                        moves.push(LookaheadStep {
+                            pos,
+                            rotation,
+                            value,
+                        });
+                    }
+                }
+            }
+            let candidates: Vec<(HexPos, Rotation)> =
+                moves.iter().map(|step| (step.pos, step.rotation)).collect();
+            let (ranked_map, ranked_groups) = game.with_next_tile(tile);
+            let best = BestPlacements::default_best_of(
+                ranked_map,
+                ranked_groups,
+                self.freqs,
+                &mut self.fit_cache,
+                &candidates,
+            );
+            let Some(step) = best.and_then(|best| {
+                moves
+                    .iter()
+                    .find(|step| (step.pos, step.rotation) == best)
+                    .copied()
+            }) else {
+                break;
+            };
+            board
+                .placed
+                .push((step.pos, profile.rotated(step.rotation)));
+            game.place(step.pos, tile, step.rotation);
+            steps.push(step);
+        }
+        steps
+    }
+
+    /// Keep the `width` best partial sequences after each of the first `depth` known tiles.
+    /// Stops early if a tile fits nowhere.
+    fn beam(&mut self, depth: usize, width: usize) -> Vec<LookaheadStep> {
+        let map = self.map;
+        let mut beam = vec![Node {
+            board: Board::new(map),
+            steps: Vec::new(),
+            total: 0,
+        }];
+
+        for tile in map.tile_queue.iter().take(depth) {
+            let profile = EdgeProfile::from_segments(tile);
+            let rotations = distinct_rotations(&profile);
+
+            // (total, parent, step), expanded into nodes only once the beam is cut.
+            let mut children = Vec::new();
+            for (parent, node) in beam.iter().enumerate() {
+                for pos in node.board.candidates(&self.frontier) {
+                    for (rotation, rotated) in &rotations {
+                        if let Some(value) = self.evaluate(&node.board, pos, rotated) {
+                            let step = LookaheadStep {
+                                pos,
+                                rotation: *rotation,
+                                value,
+                            };
+                            children.push((node.total + value.total(), parent, step));
+                        }
+                    }
+                }
+            }
+            if children.is_empty() {
+                break;
+            }
+            children.sort_by_key(|&(total, parent, step)| {
+                (
+                    Reverse(total),
+                    parent,
+                    step.pos.x(),
+                    step.pos.y(),
+                    step.rotation,
+                )
+            });
+
+            // Different orders of the same placements end up on the same board.
+            let mut boards = HashSet::new();
+            let mut next = Vec::with_capacity(width);
+            for (total, parent, step) in children {
+                let parent = &beam[parent];
+                let mut board = parent.board.clone();
+                board
+                    .placed
+                    .push((step.pos, profile.rotated(step.rotation)));
+                let mut key: Vec<(HexPos, EdgeProfile)> = board.placed.clone();
+                key.sort_by_key(|(pos, _)| (pos.x(), pos.y()));
+                if !boards.insert(key) {
+                    continue;
+                }
+                let mut steps = parent.steps.clone();
+                steps.push(step);
+                next.push(Node {
+                    board,
+                    steps,
+                    total,
+                });
+                if next.len() == width {
+                    break;
+                }
+            }
+            beam = next;
+        }
+
+        beam.into_iter()
+            .next()
+            .map(|node| node.steps)
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{Form, Segment};
+
+    fn segment(pos: HexPos, terrain: Terrain) -> Segment {
+        Segment {
+            pos,
+            form: Form::Size6,
+            terrain,
+            rotation: 0,
+            unit_count: 0,
+        }
+    }
+
+    #[test]
+    fn test_step_value_total() {
+        let plain = StepValue {
+            matching_edges: 2,
+            perfect: false,
+            dead_cells: 0,
+        };
+        assert_eq!(plain.total(), 2);
+        let perfect = StepValue {
+            matching_edges: 6,
+            perfect: true,
+            dead_cells: 0,
+        };
+        assert_eq!(perfect.total(), 6 + PERFECT_BONUS);
+        let blocking = StepValue {
+            matching_edges: 3,
+            perfect: false,
+            dead_cells: 1,
+        };
+        assert_eq!(blocking.total(), 3 - DEAD_CELL_PENALTY);
+    }
+
+    #[test]
+    fn test_board_sees_simulated_tiles() {
+        let map = Map::default();
+        let pos = HexPos::new(3, -2);
+        let board = Board {
+            map: &map,
+            placed: vec![(
+                pos,
+                EdgeProfile::from_segments(&[segment(pos, Terrain::Forest)]),
+            )],
+        };
+        assert!(!board.is_free(pos));
+        let neighbor = Map::neighbor_pos_of(pos, 1);
+        assert!(board.is_free(neighbor));
+        let constraints = board.constraints_at(neighbor);
+        assert_eq!(constraints[Map::opposite_side(1)], Some(Terrain::Forest));
+        assert_eq!(constraints.iter().flatten().count(), 1);
+        assert!(board
+            .candidates(&[])
+            .contains(&Map::neighbor_pos_of(pos, 4)));
+    }
+
+    #[test]
+    fn test_search_without_queue_is_empty() {
+        let map = Map::default();
+        let groups = GroupAssignments::default();
+        let freqs = TileFrequencies::default();
+        let lookahead = Lookahead::search(&map, &groups, &freqs, DEFAULT_DEPTH, 4);
+        assert!(lookahead.sequence.is_empty());
+        assert_eq!(lookahead.improvement(), 0);
+        assert!(!lookahead.differs_from_greedy());
+    }
+}
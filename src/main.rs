@@ -22,8 +22,9 @@ mod ui;
 // can refer to them via `crate::` paths without re-declaring (and re-analyzing)
 // them, which would produce spurious dead-code warnings.
 pub use dorfromantische2_rs::{
-    best_placements, coords, data, game, group, group_assignments, hex, map, raw_data,
-    tile_frequency,
+    best_placements, coords, data, demand, game, group, group_assignments, hex, holes, lookahead,
+    map, quest_feasibility, raw_data, replay, rollout, save_slots, score, scorer, survival,
+    tile_frequency, timeline,
 };
 
 fn run(
@@ -176,11 +177,11 @@ fn main() {
     let pipeline = Pipeline::new(&gpu, &window, &app.bind_groups.layouts);
     let ui = EguiIntegration::new(&window);
 
-    // Load the specified or previous file.
+    // Load the specified or previous file, or follow the slots of a saves directory.
     let arguments = env::args().collect::<Vec<_>>();
     if arguments.len() > 1 {
-        let file = PathBuf::from(&arguments[1]);
-        app.file_watcher.set_file_path(&file);
+        let path = PathBuf::from(&arguments[1]);
+        app.file_watcher.set_path(&path);
     } else {
         app.file_watcher.use_previous_file_path();
     }
@@ -1,12 +1,13 @@
 use std::ops::Range;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::{
     data::{
-        quest_terrain, segments_from_quest_tile, segments_from_special_tile_id, HexPos, Rotation,
-        Segment, Terrain, HEX_SIDES,
+        quest_terrain, segments_from_quest_tile, segments_from_quest_tile_id,
+        segments_from_special_tile_id, HexPos, Rotation, Segment, Terrain, HEX_SIDES,
     },
+    hex::{hex_to_section, offset_to_hex, section_to_hex},
     raw_data,
 };
 use glam::IVec2;
@@ -14,13 +15,18 @@ use glam::IVec2;
 /// Index into the spatial tile index (derived from tile position).
 pub type TileKey = usize;
 
+/// Side length of the square chunks the tile index is made of.
+pub const CHUNK_SIZE: i32 = 32;
+/// Number of keys per chunk. The keys of a chunk are contiguous, row by row.
+pub const CHUNK_AREA: usize = (CHUNK_SIZE * CHUNK_SIZE) as usize;
+
 /// Index into the flat segments array.
 pub type SegmentIndex = usize;
 /// Number of segments belonging to a single tile.
 pub type SegmentCount = usize;
 
 /// Whether the quest requires exactly the target count or at least the target count.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
 pub enum QuestType {
     /// Group must have at least target_value units.
     MoreThan,
@@ -69,7 +75,7 @@ impl QuestType {
 
 /// A quest placed on a tile, targeting a specific terrain group size.
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Quest {
     pub terrain: Terrain,
     pub target_value: i32,
@@ -81,6 +87,14 @@ pub struct Quest {
     pub unlocked_challenge_id: i32,
 }
 
+/// A tile the game has preplaced in a part of the world that has not been reached yet.
+#[derive(Debug, Clone)]
+pub struct PreplacedTile {
+    pub quest_tile_id: i32,
+    /// Segments from the tile table. The rotation is not stored, so they are unrotated.
+    pub segments: Vec<Segment>,
+}
+
 type TileData = (
     Vec<(HexPos, usize, usize)>,
     Vec<Segment>,
@@ -89,19 +103,53 @@ type TileData = (
     IVec2,
     IVec2,
 );
-type IndexData = (
-    Vec<Option<(SegmentIndex, SegmentCount)>>,
-    Vec<Option<[Option<SegmentIndex>; HEX_SIDES]>>,
+type UpcomingData = (
+    Vec<Segment>,
+    [Terrain; HEX_SIDES],
+    Option<Quest>,
+    Vec<Vec<Segment>>,
+    Vec<Option<Quest>>,
 );
 
+/// What changed when a map was extended from a newer savegame of the same game.
+#[derive(Debug, Default)]
+pub struct MapUpdate {
+    /// Positions of the tiles that were added.
+    pub added: Vec<HexPos>,
+    /// Cells where a preplaced tile appeared or disappeared.
+    pub preplaced_changed: Vec<HexPos>,
+    /// Tiles that were on the map before, whose quest changed, e.g. got completed.
+    pub quests_changed: Vec<HexPos>,
+    /// The next tile before the update.
+    pub previous_next_tile: Vec<Segment>,
+}
+
+impl MapUpdate {
+    /// All cells whose content changed.
+    pub fn changed_positions(&self) -> impl Iterator<Item = HexPos> + '_ {
+        self.added
+            .iter()
+            .chain(&self.preplaced_changed)
+            .chain(&self.quests_changed)
+            .copied()
+    }
+}
+
+#[derive(Clone)]
 pub struct Map {
-    /// Defines the smallest possible coordinate in the index.
+    /// Smallest coordinate of the placed tiles, minus one cell of margin.
     pub index_offset: IVec2,
-    /// Defines the extents of the index structure.
+    /// Extents of the placed tiles, with one cell of margin on each side.
     pub index_size: IVec2,
 
     pub world_y_extents: IVec2,
 
+    /// Index slot of each allocated chunk, keyed by chunk coordinate. Only chunks that hold a
+    /// tile or a neighbor of one are allocated.
+    pub chunk_slots: HashMap<IVec2, usize>,
+    /// Chunk coordinate of each index slot.
+    pub chunks: Vec<IVec2>,
+
     /// Maps a tile position key to a set of segment indices.
     pub tile_index: Vec<Option<(SegmentIndex, SegmentCount)>>,
     pub rendered_tiles: Vec<Option<[Option<SegmentIndex>; HEX_SIDES]>>,
@@ -117,6 +165,16 @@ pub struct Map {
     pub rendered_next_tile: [Terrain; HEX_SIDES],
     /// Quest attached to the next tile (if any).
     pub next_tile_quest: Option<Quest>,
+
+    /// The known part of the tile stack, starting with the next tile.
+    pub tile_queue: Vec<Vec<Segment>>,
+    /// Quest attached to each entry of `tile_queue` (if any).
+    pub tile_queue_quests: Vec<Option<Quest>>,
+    /// Number of tiles left in the stack, including those not yet revealed.
+    pub tile_stack_count: i32,
+
+    /// Preplaced tiles that have not been reached yet, keyed by their section centre.
+    pub preplaced_tiles: HashMap<HexPos, PreplacedTile>,
 }
 
 impl Default for Map {
@@ -125,6 +183,8 @@ impl Default for Map {
             index_offset: IVec2::default(),
             index_size: IVec2::default(),
             world_y_extents: IVec2::default(),
+            chunk_slots: HashMap::default(),
+            chunks: Vec::default(),
             tile_index: Vec::default(),
             rendered_tiles: Vec::default(),
             segments: Vec::default(),
@@ -132,25 +192,31 @@ impl Default for Map {
             next_tile: Vec::default(),
             rendered_next_tile: [Terrain::Missing; HEX_SIDES],
             next_tile_quest: None,
+            tile_queue: Vec::default(),
+            tile_queue_quests: Vec::default(),
+            tile_stack_count: 0,
+            preplaced_tiles: HashMap::default(),
         }
     }
 }
 
 /// Functions for initialization of map.
 impl Map {
-    /// Compute the position of tile at `pos` in the index structure.
-    fn tile_key_function(offset: IVec2, size: IVec2, pos: HexPos) -> Option<TileKey> {
-        let upper = offset + size;
-        let valid_s = pos.x() >= offset.x && pos.x() < upper.x;
-        let valid_t = pos.y() >= offset.y && pos.y() < upper.y;
-        (valid_s && valid_t)
-            .then(|| usize::try_from((pos.y() - offset.y) * size.x + (pos.x() - offset.x)).unwrap())
+    /// The chunk containing `pos` and the offset of `pos` within the chunk's keys.
+    fn chunk_of(pos: HexPos) -> (IVec2, usize) {
+        let chunk = IVec2::new(
+            pos.x().div_euclid(CHUNK_SIZE),
+            pos.y().div_euclid(CHUNK_SIZE),
+        );
+        let local = pos.0 - chunk * CHUNK_SIZE;
+        (
+            chunk,
+            usize::try_from(local.y * CHUNK_SIZE + local.x).unwrap(),
+        )
     }
 
     fn load_tile(raw_tile: &raw_data::Tile) -> (HexPos, Vec<Segment>) {
-        // Hex grid tutorial:
-        // https://www.redblobgames.com/grids/hexagons/#line-drawing
-        let pos = HexPos::new(raw_tile.s, raw_tile.t - ((raw_tile.s + 1) & -2i32) / 2);
+        let pos = offset_to_hex(raw_tile.s, raw_tile.t);
 
         // We store the rotation on the segments, not on the tiles.
         let tile_rotation = raw_tile.rotation.try_into().unwrap();
@@ -185,7 +251,7 @@ impl Map {
         let mut index_max = IVec2::ZERO;
         let mut world_y_extents = IVec2::new(i32::MAX, i32::MIN);
 
-        // Pos                          -- tile_key_function()  --> IndexKey
+        // Pos                          -- tile_key()           --> IndexKey
         // IndexKey                     -- tile_index[]         --> Option<(SegmentIndex, SegmentCount)>
         // (SegmentIndex, SegmentCount) -- segments[]           --> &[Segment]
 
@@ -227,36 +293,60 @@ impl Map {
         )
     }
 
-    /// Build the spatial index and per-rotation rendered tile lookups.
-    fn build_index(
-        pos_map: &[(HexPos, usize, usize)],
-        segments: &[Segment],
-        index_offset: IVec2,
-        index_size: IVec2,
-    ) -> IndexData {
-        let index_length = usize::try_from(index_size.x * index_size.y).unwrap();
-        let mut tile_index = vec![None; index_length];
-        let mut rendered_tiles = vec![None; index_length];
-
-        for &(pos, segment_base_index, segment_count) in pos_map {
-            let position_key = Map::tile_key_function(index_offset, index_size, pos).unwrap();
-            tile_index[position_key] = Some((segment_base_index, segment_count));
-
-            let mut rendered = [None; HEX_SIDES];
-            for (segment_index, segment) in segments
-                .iter()
-                .enumerate()
-                .skip(segment_base_index)
-                .take(segment_count)
-            {
-                for rotation in segment.rotations() {
-                    rendered[rotation] = Some(segment_index);
-                }
+    /// Allocate the chunks of `pos` and its neighbors, so that the cells around a tile can be
+    /// looked up as well.
+    fn allocate_chunks_around(&mut self, pos: HexPos) {
+        let cells = std::iter::once(pos)
+            .chain((0..HEX_SIDES).map(|side| crate::hex::neighbor_pos_of(pos, side)));
+        for cell in cells {
+            let (chunk, _) = Map::chunk_of(cell);
+            if self.chunk_slots.contains_key(&chunk) {
+                continue;
             }
-            rendered_tiles[position_key] = Some(rendered);
+            self.chunk_slots.insert(chunk, self.chunks.len());
+            self.chunks.push(chunk);
+            let index_length = self.chunks.len() * CHUNK_AREA;
+            self.tile_index.resize(index_length, None);
+            self.rendered_tiles.resize(index_length, None);
         }
+    }
 
-        (tile_index, rendered_tiles)
+    /// Enter the tile at `pos` into the spatial index and the per-rotation rendered tile lookup.
+    /// Its segments have to be in `segments` already.
+    fn index_tile(
+        &mut self,
+        pos: HexPos,
+        segment_base_index: SegmentIndex,
+        segment_count: SegmentCount,
+    ) {
+        self.allocate_chunks_around(pos);
+        let key = self.tile_key(pos).unwrap();
+        self.tile_index[key] = Some((segment_base_index, segment_count));
+        self.rendered_tiles[key] = Some(Map::rendered_of(
+            &self.segments,
+            segment_base_index,
+            segment_count,
+        ));
+    }
+
+    /// Which of the tile's segments covers each rotation.
+    fn rendered_of(
+        segments: &[Segment],
+        segment_base_index: SegmentIndex,
+        segment_count: SegmentCount,
+    ) -> [Option<SegmentIndex>; HEX_SIDES] {
+        let mut rendered = [None; HEX_SIDES];
+        for (segment_index, segment) in segments
+            .iter()
+            .enumerate()
+            .skip(segment_base_index)
+            .take(segment_count)
+        {
+            for rotation in segment.rotations() {
+                rendered[rotation] = Some(segment_index);
+            }
+        }
+        rendered
     }
 
     /// Extract quest info from a raw tile (if present).
@@ -275,6 +365,74 @@ impl Map {
         })
     }
 
+    /// Place the preplaced tiles that have not been reached yet at their section centres,
+    /// skipping cells that already hold a tile. A preplaced tile has been reached once a tile
+    /// with its quest tile id lies in its section. See `section_to_hex`.
+    fn load_preplaced_tiles(
+        savegame: &raw_data::SaveGame,
+        occupied: &HashSet<HexPos>,
+    ) -> HashMap<HexPos, PreplacedTile> {
+        let reached: HashSet<((i32, i32), i32)> = savegame
+            .tiles
+            .iter()
+            .filter_map(|raw_tile| {
+                let quest_tile = raw_tile.quest_tile.as_ref()?;
+                let section = hex_to_section(offset_to_hex(raw_tile.s, raw_tile.t));
+                Some((section, quest_tile.quest_tile_id.0))
+            })
+            .collect();
+        savegame
+            .preplaced_tiles
+            .iter()
+            .filter_map(|raw_tile| {
+                let (x, y) = (raw_tile.section_grid_pos_x, raw_tile.section_grid_pos_y);
+                let id = raw_tile.preplaced_tile_id;
+                let pos = section_to_hex(x, y);
+                (!reached.contains(&((x, y), id.0)) && !occupied.contains(&pos)).then(|| {
+                    let segments = segments_from_quest_tile_id(pos, id);
+                    let tile = PreplacedTile {
+                        quest_tile_id: id.0,
+                        segments,
+                    };
+                    (pos, tile)
+                })
+            })
+            .collect()
+    }
+
+    /// Load the known part of the tile stack together with the quest of each entry.
+    fn load_tile_queue(savegame: &raw_data::SaveGame) -> (Vec<Vec<Segment>>, Vec<Option<Quest>>) {
+        savegame
+            .tile_stack
+            .iter()
+            .map(|raw_tile| (Map::load_tile(raw_tile).1, Map::extract_quest(raw_tile)))
+            .unzip()
+    }
+
+    /// Load the next tile (rendered, with its quest) and the rest of the known tile stack.
+    fn load_upcoming(savegame: &raw_data::SaveGame) -> UpcomingData {
+        let (tile_queue, tile_queue_quests) = Map::load_tile_queue(savegame);
+        let (next_tile, rendered_next_tile, next_tile_quest) =
+            if let Some(next_tile) = tile_queue.first() {
+                let rendered_next_tile = Map::render_next_tile(next_tile);
+                (
+                    next_tile.clone(),
+                    rendered_next_tile,
+                    tile_queue_quests[0].clone(),
+                )
+            } else {
+                log::warn!("Savegame has empty tile_stack, no next tile available");
+                (Vec::new(), [Terrain::Missing; HEX_SIDES], None)
+            };
+        (
+            next_tile,
+            rendered_next_tile,
+            next_tile_quest,
+            tile_queue,
+            tile_queue_quests,
+        )
+    }
+
     /// Render the next tile from the tile stack into a per-rotation terrain array.
     fn render_next_tile(next_tile: &[Segment]) -> [Terrain; 6] {
         let mut rendered = [Terrain::Empty; HEX_SIDES];
@@ -295,46 +453,224 @@ impl From<&raw_data::SaveGame> for Map {
         let index_offset = index_min - IVec2::ONE;
         let index_size = index_max + IVec2::ONE - index_offset + IVec2::ONE;
 
-        let (tile_index, rendered_tiles) =
-            Map::build_index(&pos_map, &segments, index_offset, index_size);
-
-        let (next_tile, rendered_next_tile, next_tile_quest) =
-            if let Some(next_raw_tile) = savegame.tile_stack.first() {
-                let (_, next_tile) = Map::load_tile(next_raw_tile);
-                let rendered_next_tile = Map::render_next_tile(&next_tile);
-                let next_tile_quest = Map::extract_quest(next_raw_tile);
-                (next_tile, rendered_next_tile, next_tile_quest)
-            } else {
-                log::warn!("Savegame has empty tile_stack, no next tile available");
-                (Vec::new(), [Terrain::Missing; HEX_SIDES], None)
-            };
+        let (next_tile, rendered_next_tile, next_tile_quest, tile_queue, tile_queue_quests) =
+            Map::load_upcoming(savegame);
+        let occupied = pos_map.iter().map(|&(pos, _, _)| pos).collect();
+        let preplaced_tiles = Map::load_preplaced_tiles(savegame, &occupied);
 
-        Self {
+        let mut map = Self {
             world_y_extents,
             index_offset,
             index_size,
-            tile_index,
-            rendered_tiles,
+            chunk_slots: HashMap::new(),
+            chunks: Vec::new(),
+            tile_index: Vec::new(),
+            rendered_tiles: Vec::new(),
             segments,
             quests,
             next_tile,
             rendered_next_tile,
             next_tile_quest,
+            tile_queue,
+            tile_queue_quests,
+            tile_stack_count: savegame.tile_stack_count,
+            preplaced_tiles,
+        };
+        for &(pos, segment_base_index, segment_count) in &pos_map {
+            map.index_tile(pos, segment_base_index, segment_count);
+        }
+        map
+    }
+}
+
+/// Functions for incremental updates of the map.
+impl Map {
+    /// Add the tiles of a newer save of the same game that are not on the map yet.
+    ///
+    /// Segment indices of existing tiles stay valid, new segments are appended. The quests of
+    /// all tiles are read again, as the game updates them on tiles placed earlier. Returns `None`
+    /// and leaves the map untouched if the map is empty or the savegame lacks some of its
+    /// tiles, i.e. it belongs to a different game and the map has to be rebuilt.
+    pub fn extend_from(&mut self, savegame: &raw_data::SaveGame) -> Option<MapUpdate> {
+        if self.tile_index.is_empty() {
+            return None;
+        }
+        let positions: Vec<HexPos> = savegame
+            .tiles
+            .iter()
+            .map(|raw_tile| offset_to_hex(raw_tile.s, raw_tile.t))
+            .collect();
+        let mut occupied: HashSet<HexPos> = positions.iter().copied().collect();
+        occupied.insert(HexPos::ZERO);
+        if self
+            .iter_tile_positions()
+            .any(|pos| !occupied.contains(&pos))
+        {
+            return None;
+        }
+
+        let quests: HashMap<HexPos, Quest> = positions
+            .iter()
+            .zip(&savegame.tiles)
+            .filter_map(|(&pos, raw_tile)| Some((pos, Map::extract_quest(raw_tile)?)))
+            .collect();
+        let quests_changed = self
+            .iter_tile_positions()
+            .filter(|pos| self.quests.get(pos) != quests.get(pos))
+            .collect();
+        self.quests = quests;
+
+        let new_tiles: Vec<(HexPos, &raw_data::Tile)> = positions
+            .into_iter()
+            .zip(&savegame.tiles)
+            .filter(|&(pos, _)| !self.has(pos))
+            .collect();
+
+        let mut added = Vec::with_capacity(new_tiles.len());
+        for (pos, raw_tile) in new_tiles {
+            let (_, tile_segments) = Map::load_tile(raw_tile);
+            self.extend_bounds(pos);
+
+            let segment_base_index = self.segments.len();
+            let segment_count = tile_segments.len();
+            self.segments.extend(tile_segments);
+            self.index_tile(pos, segment_base_index, segment_count);
+            added.push(pos);
+        }
+
+        let previous_next_tile = std::mem::take(&mut self.next_tile);
+        (
+            self.next_tile,
+            self.rendered_next_tile,
+            self.next_tile_quest,
+            self.tile_queue,
+            self.tile_queue_quests,
+        ) = Map::load_upcoming(savegame);
+        self.tile_stack_count = savegame.tile_stack_count;
+
+        let preplaced_tiles = Map::load_preplaced_tiles(savegame, &occupied);
+        let preplaced_changed = preplaced_tiles
+            .keys()
+            .filter(|pos| !self.preplaced_tiles.contains_key(pos))
+            .chain(
+                self.preplaced_tiles
+                    .keys()
+                    .filter(|pos| !preplaced_tiles.contains_key(pos)),
+            )
+            .copied()
+            .collect();
+        self.preplaced_tiles = preplaced_tiles;
+
+        Some(MapUpdate {
+            added,
+            preplaced_changed,
+            quests_changed,
+            previous_next_tile,
+        })
+    }
+
+    /// Grow the extents to include a new tile at `pos`, keeping one cell of margin like `From`
+    /// does.
+    fn extend_bounds(&mut self, pos: HexPos) {
+        let world_y = pos.y() + (pos.x() + 1) / 2;
+        if self.index_size == IVec2::ZERO {
+            self.index_offset = pos.0 - IVec2::ONE;
+            self.index_size = IVec2::splat(3);
+            self.world_y_extents = IVec2::splat(world_y);
+            return;
+        }
+        let lower = self.index_offset.min(pos.0 - IVec2::ONE);
+        let upper = (self.index_offset + self.index_size).max(pos.0 + IVec2::splat(2));
+        self.index_offset = lower;
+        self.index_size = upper - lower;
+        self.world_y_extents.x = self.world_y_extents.x.min(world_y);
+        self.world_y_extents.y = self.world_y_extents.y.max(world_y);
+    }
+}
+
+/// Functions for editing the map tile by tile.
+impl Map {
+    /// Place a tile at `pos`, growing the index and the extents if needed. `segments` are the
+    /// unrotated segments of the tile, as in `next_tile`. Returns `false` and leaves the map
+    /// untouched if `pos` already holds a tile.
+    ///
+    /// Group assignments computed before the edit are stale afterwards.
+    pub fn place(&mut self, pos: HexPos, segments: &[Segment], rotation: Rotation) -> bool {
+        if self.has(pos) {
+            return false;
+        }
+
+        self.extend_bounds(pos);
+        let segment_base_index = self.segments.len();
+        let segment_count = segments.len();
+        self.segments.extend(segments.iter().map(|segment| Segment {
+            pos,
+            rotation: (segment.rotation + rotation) % HEX_SIDES,
+            ..*segment
+        }));
+        self.index_tile(pos, segment_base_index, segment_count);
+        self.preplaced_tiles.remove(&pos);
+        true
+    }
+
+    /// Remove the tile at `pos` together with its quest and return its segments, rotated as
+    /// they were on the map. The index keeps its chunks and the extents stay as they are.
+    ///
+    /// Segment indices of the tiles placed after it shift down, so group assignments computed
+    /// before the edit are stale afterwards.
+    pub fn remove(&mut self, pos: HexPos) -> Option<Vec<Segment>> {
+        let key = self.tile_key(pos)?;
+        let (segment_base_index, segment_count) = self.tile_index[key].take()?;
+        self.rendered_tiles[key] = None;
+        self.quests.remove(&pos);
+        let removed = self
+            .segments
+            .drain(segment_base_index..segment_base_index + segment_count)
+            .collect();
+
+        for (index, entry) in self.tile_index.iter_mut().enumerate() {
+            let Some((base, count)) = entry else {
+                continue;
+            };
+            if *base > segment_base_index {
+                *base -= segment_count;
+                self.rendered_tiles[index] = Some(Map::rendered_of(&self.segments, *base, *count));
+            }
         }
+        Some(removed)
+    }
+
+    /// A copy of the map with one more tile, see `place`. `None` if `pos` already holds a tile.
+    pub fn clone_with(
+        &self,
+        pos: HexPos,
+        segments: &[Segment],
+        rotation: Rotation,
+    ) -> Option<Self> {
+        let mut map = self.clone();
+        map.place(pos, segments, rotation).then_some(map)
     }
 }
 
 impl Map {
-    /// Compute the position of tile at `pos` in the index structure.
+    /// Compute the position of tile at `pos` in the index structure. `None` if its chunk is
+    /// not allocated, i.e. neither `pos` nor any cell near it holds a tile.
     pub fn tile_key(&self, pos: HexPos) -> Option<TileKey> {
-        Map::tile_key_function(self.index_offset, self.index_size, pos)
+        let (chunk, local) = Map::chunk_of(pos);
+        self.chunk_slots
+            .get(&chunk)
+            .map(|slot| slot * CHUNK_AREA + local)
     }
 
     fn tile_position(&self, index_key: TileKey) -> HexPos {
-        let i32_key = i32::try_from(index_key).unwrap();
-        let x = i32_key % self.index_size.x;
-        let y = (i32_key - x) / self.index_size.x;
-        HexPos::new(x + self.index_offset.x, y + self.index_offset.y)
+        let chunk = self.chunks[index_key / CHUNK_AREA];
+        let local = i32::try_from(index_key % CHUNK_AREA).unwrap();
+        HexPos(chunk * CHUNK_SIZE + IVec2::new(local % CHUNK_SIZE, local / CHUNK_SIZE))
+    }
+
+    /// Every position the index has a key for, chunk by chunk.
+    pub fn iter_indexed_positions(&self) -> impl Iterator<Item = HexPos> + '_ {
+        (0..self.tile_index.len()).map(|key| self.tile_position(key))
     }
 
     pub fn iter_tile_positions(&self) -> impl Iterator<Item = HexPos> + '_ {
@@ -349,6 +685,10 @@ impl Map {
             .is_some_and(|key| self.tile_index[key].is_some())
     }
 
+    pub fn preplaced_at(&self, pos: HexPos) -> Option<&PreplacedTile> {
+        self.preplaced_tiles.get(&pos)
+    }
+
     pub fn segment_indices_at(&self, pos: HexPos) -> Option<Range<SegmentIndex>> {
         self.tile_index[self.tile_key(pos)?].map(|(index, count)| index..index + count)
     }
@@ -365,6 +705,14 @@ impl Map {
         })
     }
 
+    /// Iterate over the known upcoming tiles (next tile first) with their quests.
+    pub fn upcoming_tiles(&self) -> impl Iterator<Item = (&[Segment], Option<&Quest>)> {
+        self.tile_queue
+            .iter()
+            .zip(&self.tile_queue_quests)
+            .map(|(segments, quest)| (segments.as_slice(), quest.as_ref()))
+    }
+
     pub fn segment(&self, segment_index: SegmentIndex) -> &Segment {
         &self.segments[segment_index]
     }
@@ -387,27 +735,22 @@ mod tests {
     use glam::IVec2;
     use std::collections::HashSet;
 
+    fn segment(form: Form, terrain: Terrain, rotation: Rotation, unit_count: u32) -> Segment {
+        Segment {
+            pos: HexPos::ZERO,
+            form,
+            terrain,
+            rotation,
+            unit_count,
+        }
+    }
+
     /// Build a minimal Map with a hex flower: center tile at origin plus 6 neighbors.
     /// Each tile gets a single Size6 segment (covers all 6 rotations) with the given terrain.
     fn make_hex_flower() -> Map {
         let center = HexPos::ZERO;
-        let positions: Vec<HexPos> = std::iter::once(center)
-            .chain((0..HEX_SIDES).map(|r| neighbor_pos_of(center, r)))
-            .collect();
-
-        // Determine index bounds.
-        let min_x = positions.iter().map(|p| p.x()).min().unwrap();
-        let min_y = positions.iter().map(|p| p.y()).min().unwrap();
-        let max_x = positions.iter().map(|p| p.x()).max().unwrap();
-        let max_y = positions.iter().map(|p| p.y()).max().unwrap();
-
-        let index_offset = IVec2::new(min_x - 1, min_y - 1);
-        let index_size = IVec2::new(
-            max_x + 1 - index_offset.x + 1,
-            max_y + 1 - index_offset.y + 1,
-        );
-        let index_length = (index_size.x * index_size.y) as usize;
-
+        let positions =
+            std::iter::once(center).chain((0..HEX_SIDES).map(|r| neighbor_pos_of(center, r)));
         let terrains = [
             Terrain::House,
             Terrain::Forest,
@@ -418,41 +761,12 @@ mod tests {
             Terrain::Wheat,
         ];
 
-        let mut segments = Vec::new();
-        let mut tile_index = vec![None; index_length];
-        let mut rendered_tiles = vec![None; index_length];
-
-        for (i, &pos) in positions.iter().enumerate() {
-            let seg_base = segments.len();
-            let terrain = terrains[i];
-            segments.push(Segment {
-                pos,
-                form: Form::Size6,
-                terrain,
-                rotation: 0,
-                unit_count: Form::Size6.default_unit_count(terrain),
-            });
-
-            let key = Map::tile_key_function(index_offset, index_size, pos).unwrap();
-            tile_index[key] = Some((seg_base, 1));
-
-            // Size6 covers all 6 rotations from base rotation 0.
-            let rendered = [Some(seg_base); HEX_SIDES];
-            rendered_tiles[key] = Some(rendered);
-        }
-
-        Map {
-            index_offset,
-            index_size,
-            world_y_extents: IVec2::new(-1, 1),
-            tile_index,
-            rendered_tiles,
-            segments,
-            quests: HashMap::new(),
-            next_tile: Vec::new(),
-            rendered_next_tile: [Terrain::Missing; HEX_SIDES],
-            next_tile_quest: None,
+        let mut map = Map::default();
+        for (pos, terrain) in positions.zip(terrains) {
+            let unit_count = Form::Size6.default_unit_count(terrain);
+            assert!(map.place(pos, &[segment(Form::Size6, terrain, 0, unit_count)], 0));
         }
+        map
     }
 
     /// Build a map with two adjacent tiles that have multi-segment layouts,
@@ -461,68 +775,17 @@ mod tests {
         let pos_a = HexPos::ZERO;
         let pos_b = neighbor_pos_of(pos_a, 1); // neighbor at rotation 1
 
-        let index_offset = IVec2::new(-1, -1);
-        let index_size = IVec2::new(4, 4);
-        let index_length = (index_size.x * index_size.y) as usize;
-
-        let mut segments = Vec::new();
-        let mut tile_index = vec![None; index_length];
-        let mut rendered_tiles = vec![None; index_length];
-
+        let mut map = Map::default();
         // Tile A: Size3 Forest at rotation 0 (covers rotations 0,1,2)
         //       + Size3 Wheat at rotation 3 (covers rotations 3,4,5)
-        let seg_a0 = segments.len();
-        segments.push(Segment {
-            pos: pos_a,
-            form: Form::Size3,
-            terrain: Terrain::Forest,
-            rotation: 0,
-            unit_count: 17,
-        });
-        segments.push(Segment {
-            pos: pos_a,
-            form: Form::Size3,
-            terrain: Terrain::Wheat,
-            rotation: 3,
-            unit_count: 1,
-        });
-
-        let key_a = Map::tile_key_function(index_offset, index_size, pos_a).unwrap();
-        tile_index[key_a] = Some((seg_a0, 2));
-        let mut rendered_a = [None; HEX_SIDES];
-        // Size3 at rotation 0: covers 0,1,2
-        rendered_a[..3].fill(Some(seg_a0));
-        // Size3 at rotation 3: covers 3,4,5
-        rendered_a[3..].fill(Some(seg_a0 + 1));
-        rendered_tiles[key_a] = Some(rendered_a);
-
+        let tile_a = [
+            segment(Form::Size3, Terrain::Forest, 0, 17),
+            segment(Form::Size3, Terrain::Wheat, 3, 1),
+        ];
+        assert!(map.place(pos_a, &tile_a, 0));
         // Tile B: Size6 River at rotation 0 (covers all)
-        let seg_b0 = segments.len();
-        segments.push(Segment {
-            pos: pos_b,
-            form: Form::Size6,
-            terrain: Terrain::River,
-            rotation: 0,
-            unit_count: 1,
-        });
-
-        let key_b = Map::tile_key_function(index_offset, index_size, pos_b).unwrap();
-        tile_index[key_b] = Some((seg_b0, 1));
-        let rendered_b = [Some(seg_b0); HEX_SIDES];
-        rendered_tiles[key_b] = Some(rendered_b);
-
-        Map {
-            index_offset,
-            index_size,
-            world_y_extents: IVec2::new(-1, 1),
-            tile_index,
-            rendered_tiles,
-            segments,
-            quests: HashMap::new(),
-            next_tile: Vec::new(),
-            rendered_next_tile: [Terrain::Missing; HEX_SIDES],
-            next_tile_quest: None,
-        }
+        assert!(map.place(pos_b, &[segment(Form::Size6, Terrain::River, 0, 1)], 0));
+        map
     }
 
     #[test]
@@ -680,4 +943,134 @@ mod tests {
         assert_eq!(map.iter_tile_positions().count(), 0);
         assert_eq!(map.segments.len(), 0);
     }
+
+    #[test]
+    fn test_upcoming_tiles_pairs_segments_with_quests() {
+        let mut map = make_hex_flower();
+        let tile = |terrain| {
+            vec![Segment {
+                pos: HexPos::ZERO,
+                form: Form::Size6,
+                terrain,
+                rotation: 0,
+                unit_count: 1,
+            }]
+        };
+        map.tile_queue = vec![tile(Terrain::Forest), tile(Terrain::House)];
+        map.tile_queue_quests = vec![
+            None,
+            Some(Quest {
+                terrain: Terrain::House,
+                target_value: 20,
+                active: false,
+                quest_type: QuestType::MoreThan,
+                quest_id: 10,
+                quest_level: 1,
+                quest_queue_index: 0,
+                unlocked_challenge_id: 0,
+            }),
+        ];
+
+        let upcoming: Vec<_> = map.upcoming_tiles().collect();
+        assert_eq!(upcoming.len(), 2);
+        assert_eq!(upcoming[0].0[0].terrain, Terrain::Forest);
+        assert!(upcoming[0].1.is_none());
+        assert_eq!(upcoming[1].0[0].terrain, Terrain::House);
+        assert_eq!(upcoming[1].1.map(|q| q.target_value), Some(20));
+    }
+
+    #[test]
+    fn test_default_map_has_no_upcoming_tiles() {
+        assert_eq!(Map::default().upcoming_tiles().count(), 0);
+    }
+
+    #[test]
+    fn test_place_grows_the_index() {
+        let mut map = make_hex_flower();
+        assert_eq!(map.index_offset, IVec2::new(-2, -2));
+        assert_eq!(map.index_size, IVec2::new(5, 5));
+
+        let far = HexPos::new(7, -4);
+        assert!(map.place(far, &[segment(Form::Size6, Terrain::Forest, 0, 1)], 0));
+        assert_eq!(map.index_offset, IVec2::new(-2, -5));
+        assert_eq!(map.index_size, IVec2::new(11, 8));
+        assert_eq!(map.iter_tile_positions().count(), 8);
+        assert_eq!(map.segment_at(far, 3).unwrap().1.terrain, Terrain::Forest);
+        assert_eq!(
+            map.segment_at(HexPos::ZERO, 0).unwrap().1.terrain,
+            Terrain::House
+        );
+        assert_eq!(map.world_y_extents, IVec2::new(-1, 1));
+    }
+
+    #[test]
+    fn test_index_only_holds_chunks_near_tiles() {
+        let mut map = Map::default();
+        let tile = [segment(Form::Size6, Terrain::Forest, 0, 1)];
+        let (corner, far) = (HexPos::new(-1, -1), HexPos::new(500, -700));
+        assert!(map.place(corner, &tile, 0));
+        assert!(map.place(far, &tile, 0));
+
+        // The corner tile and its neighbors touch three chunks, the far one lies inside one.
+        assert_eq!(map.chunks.len(), 4);
+        assert_eq!(map.tile_index.len(), 4 * CHUNK_AREA);
+        assert_eq!(map.index_offset, IVec2::new(-2, -701));
+        assert_eq!(map.index_size, IVec2::new(504, 702));
+
+        let positions: HashSet<HexPos> = map.iter_tile_positions().collect();
+        assert_eq!(positions, HashSet::from([corner, far]));
+        let neighbor = neighbor_pos_of(far, 3);
+        assert!(map.tile_key(neighbor).is_some() && !map.has(neighbor));
+        assert!(map.tile_key(HexPos::new(200, -300)).is_none());
+        assert_eq!(map.iter_indexed_positions().count(), map.tile_index.len());
+    }
+
+    #[test]
+    fn test_place_rotates_segments_and_keeps_occupied_cells() {
+        let mut map = Map::default();
+        let tile = [segment(Form::Size1, Terrain::River, 0, 1)];
+        let pos = HexPos::new(1, 1);
+        assert!(map.place(pos, &tile, 2));
+        let (_, river) = map.segment_at(pos, 2).unwrap();
+        assert_eq!(river.pos, pos);
+        assert_eq!(river.terrain, Terrain::River);
+        assert!(map.segment_at(pos, 0).is_none());
+
+        assert!(!map.place(pos, &[segment(Form::Size6, Terrain::House, 0, 1)], 0));
+        assert_eq!(map.segments.len(), 1);
+    }
+
+    #[test]
+    fn test_remove_shifts_later_tiles() {
+        let mut map = make_two_tile_map();
+        let pos_b = neighbor_pos_of(HexPos::ZERO, 1);
+
+        let removed = map.remove(HexPos::ZERO).unwrap();
+        assert_eq!(removed.len(), 2);
+        assert!(!map.has(HexPos::ZERO));
+        assert!(map.remove(HexPos::ZERO).is_none());
+        assert_eq!(map.segments.len(), 1);
+        assert_eq!(map.segment_indices_at(pos_b), Some(0..1));
+        assert_eq!(map.segment_at(pos_b, 4).unwrap().1.terrain, Terrain::River);
+
+        // The cell can be filled again.
+        assert!(map.place(HexPos::ZERO, &removed, 0));
+        assert_eq!(
+            map.segment_at(HexPos::ZERO, 4).unwrap().1.terrain,
+            Terrain::Wheat
+        );
+    }
+
+    #[test]
+    fn test_clone_with_leaves_the_original() {
+        let map = make_hex_flower();
+        let pos = neighbor_pos_of(neighbor_pos_of(HexPos::ZERO, 0), 0);
+        let tile = [segment(Form::Size6, Terrain::Forest, 0, 1)];
+
+        let edited = map.clone_with(pos, &tile, 0).unwrap();
+        assert!(edited.has(pos));
+        assert!(!map.has(pos));
+        assert_eq!(map.iter_tile_positions().count(), 7);
+        assert!(map.clone_with(HexPos::ZERO, &tile, 0).is_none());
+    }
 }
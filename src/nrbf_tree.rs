@@ -0,0 +1,1097 @@
+//! Owned, editable copy of an NRBF object graph, and an encoder that writes it
+//! back as a `BinaryFormatter` stream.
+//!
+//! `nrbf_rs::value::Value` can only be read and has no form for every value a
+//! savegame holds, so [`read_nrbf`] builds a [`Node`] tree straight from the
+//! stream and edits happen on that. The member type table is not kept in the
+//! tree; [`write_nrbf`] rebuilds one per class from the values it sees.
+
+use crate::raw_data::unversioned;
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufReader, Read, Write};
+use std::rc::Rc;
+
+/// Library of every class that is not in the `System.` namespace.
+pub const GAME_ASSEMBLY: &str =
+    "Assembly-CSharp, Version=0.0.0.0, Culture=neutral, PublicKeyToken=null";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Node {
+    Null,
+    Bool(bool),
+    Int32(i32),
+    Int64(i64),
+    Single(f32),
+    /// Ticks and kind, as packed by `System.DateTime`.
+    DateTime(i64),
+    String(String),
+    ByteArray(Vec<u8>),
+    Int32Array(Vec<i32>),
+    Array(Vec<Node>),
+    Object(String, Vec<(String, Node)>),
+}
+
+impl Node {
+    pub fn class_name(&self) -> Option<&str> {
+        match self {
+            Node::Object(class_name, _) => Some(class_name),
+            _ => None,
+        }
+    }
+
+    pub fn field(&self, key: &str) -> Option<&Node> {
+        match self {
+            Node::Object(_, values) => values.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn field_mut(&mut self, key: &str) -> Result<&mut Node, String> {
+        match self {
+            Node::Object(class_name, values) => values
+                .iter_mut()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v)
+                .ok_or_else(|| format!("No {key} field in {class_name}")),
+            _ => Err(format!("Expected object with field {key}; Got {self:?}")),
+        }
+    }
+
+    /// Replace the value of an existing field. Fields are never added, the
+    /// class layout has to stay what the game expects.
+    pub fn set_field(&mut self, key: &str, value: Node) -> Result<(), String> {
+        *self.field_mut(key)? = value;
+        Ok(())
+    }
+
+    /// Visit this node and every node below it, depth first.
+    pub fn walk<'a>(&'a self, visit: &mut impl FnMut(&'a Node)) {
+        visit(self);
+        match self {
+            Node::Array(items) => items.iter().for_each(|item| item.walk(visit)),
+            Node::Object(_, values) => values.iter().for_each(|(_, value)| value.walk(visit)),
+            _ => {}
+        }
+    }
+}
+
+// Record types, see [MS-NRBF] 2.1.2.1.
+const SERIALIZED_STREAM_HEADER: u8 = 0;
+const CLASS_WITH_ID: u8 = 1;
+const SYSTEM_CLASS_WITH_MEMBERS_AND_TYPES: u8 = 4;
+const CLASS_WITH_MEMBERS_AND_TYPES: u8 = 5;
+const BINARY_OBJECT_STRING: u8 = 6;
+const BINARY_ARRAY: u8 = 7;
+const MEMBER_PRIMITIVE_TYPED: u8 = 8;
+const MEMBER_REFERENCE: u8 = 9;
+const OBJECT_NULL: u8 = 10;
+const MESSAGE_END: u8 = 11;
+const BINARY_LIBRARY: u8 = 12;
+const OBJECT_NULL_MULTIPLE_256: u8 = 13;
+const OBJECT_NULL_MULTIPLE: u8 = 14;
+const ARRAY_SINGLE_PRIMITIVE: u8 = 15;
+const ARRAY_SINGLE_OBJECT: u8 = 16;
+const ARRAY_SINGLE_STRING: u8 = 17;
+
+// Primitive types, see [MS-NRBF] 2.1.2.3.
+const BOOLEAN: u8 = 1;
+const BYTE: u8 = 2;
+const INT32: u8 = 8;
+const INT64: u8 = 9;
+const SINGLE: u8 = 11;
+const DATE_TIME: u8 = 13;
+
+/// Binary type of a class member, see [MS-NRBF] 2.1.2.2.
+#[derive(Clone, Debug, PartialEq)]
+enum MemberType {
+    Primitive(u8),
+    String,
+    /// Used for members that are null in every instance.
+    Object,
+    SystemClass(String),
+    Class(String),
+    ObjectArray,
+    StringArray,
+    PrimitiveArray(u8),
+}
+
+impl MemberType {
+    fn binary_type(&self) -> u8 {
+        match self {
+            MemberType::Primitive(_) => 0,
+            MemberType::String => 1,
+            MemberType::Object => 2,
+            MemberType::SystemClass(_) => 3,
+            MemberType::Class(_) => 4,
+            MemberType::ObjectArray => 5,
+            MemberType::StringArray => 6,
+            MemberType::PrimitiveArray(_) => 7,
+        }
+    }
+
+    /// Element class of an object array member.
+    fn element_class(&self) -> Option<&str> {
+        match self {
+            MemberType::Class(name) => name.strip_suffix("[]"),
+            _ => None,
+        }
+    }
+}
+
+/// Element type of a `System.Collections.Generic.List`1[[T, assembly]]`.
+fn list_element_type(class_name: &str) -> Option<&str> {
+    let rest = class_name.strip_prefix("System.Collections.Generic.List`1[[")?;
+    rest.split(',').next()
+}
+
+/// Enums (objects with a single `value__`) are value types and are written
+/// inline. Everything else is written once and referenced.
+fn is_value_type(values: &[(String, Node)]) -> bool {
+    values.len() == 1 && values[0].0 == "value__"
+}
+
+fn is_empty_array(node: &Node) -> bool {
+    match node {
+        Node::Array(items) => items.is_empty(),
+        Node::Int32Array(items) => items.is_empty(),
+        _ => false,
+    }
+}
+
+fn infer_member_type(class_name: &str, key: &str, node: &Node) -> Option<MemberType> {
+    match node {
+        Node::Null => None,
+        Node::Bool(_) => Some(MemberType::Primitive(BOOLEAN)),
+        Node::Int32(_) => Some(MemberType::Primitive(INT32)),
+        Node::Int64(_) => Some(MemberType::Primitive(INT64)),
+        Node::Single(_) => Some(MemberType::Primitive(SINGLE)),
+        Node::DateTime(_) => Some(MemberType::Primitive(DATE_TIME)),
+        Node::String(_) => Some(MemberType::String),
+        Node::ByteArray(_) => Some(MemberType::PrimitiveArray(BYTE)),
+        Node::Object(name, _) if name.starts_with("System.") => {
+            Some(MemberType::SystemClass(name.clone()))
+        }
+        Node::Object(name, _) => Some(MemberType::Class(name.clone())),
+        Node::Int32Array(_) | Node::Array(_) => {
+            // The backing array of a list is typed by the list itself. This
+            // also settles empty arrays, which look like empty `int[]`s.
+            match list_element_type(class_name).filter(|_| key == "_items") {
+                Some("System.Int32") => Some(MemberType::PrimitiveArray(INT32)),
+                Some(element) if !element.starts_with("System.") => {
+                    Some(MemberType::Class(format!("{element}[]")))
+                }
+                _ => match node {
+                    Node::Int32Array(items) if !items.is_empty() => {
+                        Some(MemberType::PrimitiveArray(INT32))
+                    }
+                    Node::Array(items) => items
+                        .iter()
+                        .find_map(Node::class_name)
+                        .map(|element| MemberType::Class(format!("{element}[]"))),
+                    _ => None,
+                },
+            }
+        }
+    }
+}
+
+/// Types of savegame members that are usually null, as written by the game.
+/// Classes are matched without their layout version. Versioned member classes
+/// take the layout of `class_names`, the classes in the savegame, if it has
+/// one, and the layout of the game at the time of writing otherwise.
+fn known_member_type(class_name: &str, key: &str, class_names: &[&str]) -> Option<MemberType> {
+    let versioned = |default: &str| {
+        class_names
+            .iter()
+            .copied()
+            .find(|class| unversioned(class) == unversioned(default))
+            .unwrap_or(default)
+            .to_string()
+    };
+    let list_of = |element: &str| {
+        MemberType::SystemClass(format!(
+            "System.Collections.Generic.List`1[[{element}, {GAME_ASSEMBLY}]]"
+        ))
+    };
+    match (unversioned(class_name), key) {
+        ("SaveGameData", "OnUpdated") => Some(MemberType::SystemClass("System.Action".to_string())),
+        ("SaveGameData", "activeChallenges") => {
+            Some(list_of(&versioned("ActiveSessionQuestData_002")))
+        }
+        ("SaveGameData", "excludedBiomes") => Some(list_of("Dorfromantik.BiomeId")),
+        ("SaveGameData", "groupTypeConfiguration") => {
+            Some(list_of("Dorfromantik.CreativeMode.GroupTypeProbability"))
+        }
+        ("SaveGameData", "customModeData") => {
+            Some(MemberType::Class("Dorfromantik.CustomModeData".to_string()))
+        }
+        ("SaveGameData", "fileName") => Some(MemberType::String),
+        ("SaveGameData", "screenshot") => Some(MemberType::PrimitiveArray(BYTE)),
+        ("TileData", "questTileData") => Some(MemberType::Class(versioned("QuestTileData_002"))),
+        _ => None,
+    }
+}
+
+struct ClassLayout {
+    keys: Vec<String>,
+    types: Vec<MemberType>,
+}
+
+/// Collect the members of every class, merging the member types over all
+/// instances so that a member which is null in the first instance still gets
+/// its proper type. Members that are null everywhere fall back to
+/// [`known_member_type`], then to `Object`.
+fn collect_layouts(root: &Node) -> Result<HashMap<String, ClassLayout>, String> {
+    let mut partial: HashMap<&str, (Vec<&str>, Vec<Option<MemberType>>)> = HashMap::new();
+    let mut error = None;
+    root.walk(&mut |node| {
+        let Node::Object(class_name, values) = node else {
+            return;
+        };
+        let (keys, types) = partial.entry(class_name).or_insert_with(|| {
+            (
+                values.iter().map(|(k, _)| k.as_str()).collect(),
+                vec![None; values.len()],
+            )
+        });
+        if !keys
+            .iter()
+            .copied()
+            .eq(values.iter().map(|(k, _)| k.as_str()))
+        {
+            error
+                .get_or_insert_with(|| format!("Instances of {class_name} have different members"));
+            return;
+        }
+        for ((key, value), ty) in values.iter().zip(types.iter_mut()) {
+            if ty.is_none() {
+                *ty = infer_member_type(class_name, key, value);
+            }
+        }
+    });
+    if let Some(error) = error {
+        return Err(error);
+    }
+
+    let class_names: Vec<&str> = partial.keys().copied().collect();
+    Ok(partial
+        .into_iter()
+        .map(|(class_name, (keys, types))| {
+            let types = keys
+                .iter()
+                .zip(types)
+                .map(|(key, ty)| {
+                    ty.or_else(|| known_member_type(class_name, key, &class_names))
+                        .unwrap_or(MemberType::Object)
+                })
+                .collect();
+            let layout = ClassLayout {
+                keys: keys.into_iter().map(str::to_string).collect(),
+                types,
+            };
+            (class_name.to_string(), layout)
+        })
+        .collect())
+}
+
+struct Encoder<'a, W: Write> {
+    out: W,
+    layouts: HashMap<String, ClassLayout>,
+    metadata_ids: HashMap<String, i32>,
+    library_id: i32,
+    next_id: i32,
+    /// Id of the object looked up last, see [`Encoder::shared_empty`].
+    last_id: i32,
+    /// The empty backing array of each list class.
+    empty_ids: HashMap<String, i32>,
+    pending: VecDeque<(i32, &'a Node, Option<String>)>,
+}
+
+fn io_error(error: std::io::Error) -> String {
+    format!("Failed to write savegame: {error}")
+}
+
+impl<'a, W: Write> Encoder<'a, W> {
+    fn bytes(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.out.write_all(bytes).map_err(io_error)
+    }
+
+    fn u8(&mut self, value: u8) -> Result<(), String> {
+        self.bytes(&[value])
+    }
+
+    fn i32(&mut self, value: i32) -> Result<(), String> {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    fn string(&mut self, value: &str) -> Result<(), String> {
+        let mut length = value.len();
+        loop {
+            let byte = (length & 0x7f) as u8;
+            length >>= 7;
+            if length == 0 {
+                self.u8(byte)?;
+                break;
+            }
+            self.u8(byte | 0x80)?;
+        }
+        self.bytes(value.as_bytes())
+    }
+
+    fn new_id(&mut self) -> i32 {
+        self.next_id += 1;
+        self.last_id = self.next_id;
+        self.next_id
+    }
+
+    /// Empty lists share one static empty array per element type. Like
+    /// `BinaryFormatter`, looking up an object that was written before still
+    /// uses up an id, unless it is the object looked up last.
+    fn shared_empty(
+        &mut self,
+        list_class: &str,
+        node: &'a Node,
+        element_class: Option<&str>,
+    ) -> Result<(), String> {
+        let id = match self.empty_ids.get(list_class) {
+            Some(&id) => {
+                if id != self.last_id {
+                    self.next_id += 1;
+                }
+                self.last_id = id;
+                id
+            }
+            None => {
+                let id = self.new_id();
+                self.empty_ids.insert(list_class.to_string(), id);
+                self.pending
+                    .push_back((id, node, element_class.map(str::to_string)));
+                id
+            }
+        };
+        self.u8(MEMBER_REFERENCE)?;
+        self.i32(id)
+    }
+
+    fn primitive(&mut self, primitive_type: u8, node: &Node) -> Result<(), String> {
+        match (primitive_type, node) {
+            (BOOLEAN, Node::Bool(x)) => self.u8(u8::from(*x)),
+            (INT32, Node::Int32(x)) => self.i32(*x),
+            (INT64, Node::Int64(x)) | (DATE_TIME, Node::DateTime(x)) => {
+                self.bytes(&x.to_le_bytes())
+            }
+            (SINGLE, Node::Single(x)) => self.bytes(&x.to_le_bytes()),
+            _ => Err(format!(
+                "Expected primitive of type {primitive_type}; Got {node:?}"
+            )),
+        }
+    }
+
+    fn primitive_typed(&mut self, primitive_type: u8, node: &Node) -> Result<(), String> {
+        self.u8(MEMBER_PRIMITIVE_TYPED)?;
+        self.u8(primitive_type)?;
+        self.primitive(primitive_type, node)
+    }
+
+    fn null_run(&mut self, count: usize) -> Result<(), String> {
+        match count {
+            0 => Ok(()),
+            1 => self.u8(OBJECT_NULL),
+            2..=255 => {
+                self.u8(OBJECT_NULL_MULTIPLE_256)?;
+                self.u8(count as u8)
+            }
+            _ => {
+                self.u8(OBJECT_NULL_MULTIPLE)?;
+                self.i32(count as i32)
+            }
+        }
+    }
+
+    /// Write a value that is stored as a record: strings and value types
+    /// inline, everything else as a reference to an object written later.
+    fn record(&mut self, node: &'a Node, element_class: Option<&str>) -> Result<(), String> {
+        match node {
+            Node::Null => self.u8(OBJECT_NULL),
+            Node::Bool(_) => self.primitive_typed(BOOLEAN, node),
+            Node::Int32(_) => self.primitive_typed(INT32, node),
+            Node::Int64(_) => self.primitive_typed(INT64, node),
+            Node::Single(_) => self.primitive_typed(SINGLE, node),
+            Node::DateTime(_) => self.primitive_typed(DATE_TIME, node),
+            Node::String(value) => {
+                self.u8(BINARY_OBJECT_STRING)?;
+                let id = self.new_id();
+                self.i32(id)?;
+                self.string(value)
+            }
+            // Value types get negative ids.
+            Node::Object(class_name, values) if is_value_type(values) => {
+                let id = -self.new_id();
+                self.object(id, class_name, values)
+            }
+            Node::Object(..) | Node::Array(_) | Node::ByteArray(_) | Node::Int32Array(_) => {
+                let id = self.new_id();
+                self.u8(MEMBER_REFERENCE)?;
+                self.i32(id)?;
+                self.pending
+                    .push_back((id, node, element_class.map(str::to_string)));
+                Ok(())
+            }
+        }
+    }
+
+    fn object(
+        &mut self,
+        id: i32,
+        class_name: &str,
+        values: &'a [(String, Node)],
+    ) -> Result<(), String> {
+        let layout = &self.layouts[class_name];
+        let types = layout.types.clone();
+        if let Some(&metadata_id) = self.metadata_ids.get(class_name) {
+            self.u8(CLASS_WITH_ID)?;
+            self.i32(id)?;
+            self.i32(metadata_id)?;
+        } else {
+            self.metadata_ids.insert(class_name.to_string(), id);
+            let keys = layout.keys.clone();
+            let is_system = class_name.starts_with("System.");
+            self.u8(if is_system {
+                SYSTEM_CLASS_WITH_MEMBERS_AND_TYPES
+            } else {
+                CLASS_WITH_MEMBERS_AND_TYPES
+            })?;
+            self.i32(id)?;
+            self.string(class_name)?;
+            self.i32(keys.len() as i32)?;
+            for key in &keys {
+                self.string(key)?;
+            }
+            for ty in &types {
+                self.u8(ty.binary_type())?;
+            }
+            for ty in &types {
+                match ty {
+                    MemberType::Primitive(primitive_type)
+                    | MemberType::PrimitiveArray(primitive_type) => self.u8(*primitive_type)?,
+                    MemberType::SystemClass(name) => self.string(name)?,
+                    MemberType::Class(name) => {
+                        self.string(name)?;
+                        self.i32(self.library_id)?;
+                    }
+                    MemberType::String
+                    | MemberType::Object
+                    | MemberType::ObjectArray
+                    | MemberType::StringArray => {}
+                }
+            }
+            if !is_system {
+                self.i32(self.library_id)?;
+            }
+        }
+
+        for ((key, value), ty) in values.iter().zip(&types) {
+            match ty {
+                MemberType::Primitive(primitive_type) => self
+                    .primitive(*primitive_type, value)
+                    .map_err(|error| format!("While writing {class_name}.{key}:\n{error}"))?,
+                _ if key == "_items"
+                    && list_element_type(class_name).is_some()
+                    && is_empty_array(value) =>
+                {
+                    self.shared_empty(class_name, value, ty.element_class())?
+                }
+                _ => self.record(value, ty.element_class())?,
+            }
+        }
+        Ok(())
+    }
+
+    fn class_array(
+        &mut self,
+        id: i32,
+        items: &'a [Node],
+        element_class: Option<&str>,
+    ) -> Result<(), String> {
+        self.u8(BINARY_ARRAY)?;
+        self.i32(id)?;
+        self.u8(0)?; // Single
+        self.i32(1)?; // Rank
+        self.i32(items.len() as i32)?;
+        match element_class {
+            Some(name) => {
+                self.u8(MemberType::Class(String::new()).binary_type())?;
+                self.string(name)?;
+                self.i32(self.library_id)?;
+            }
+            None => self.u8(MemberType::Object.binary_type())?,
+        }
+
+        let mut nulls = 0;
+        for item in items {
+            if matches!(item, Node::Null) {
+                nulls += 1;
+                continue;
+            }
+            self.null_run(nulls)?;
+            nulls = 0;
+            self.record(item, None)?;
+        }
+        self.null_run(nulls)
+    }
+
+    fn deferred(
+        &mut self,
+        id: i32,
+        node: &'a Node,
+        element_class: Option<&str>,
+    ) -> Result<(), String> {
+        match node {
+            Node::Object(class_name, values) => self.object(id, class_name, values),
+            Node::Array(items) => {
+                let element_class = items.iter().find_map(Node::class_name).or(element_class);
+                self.class_array(id, items, element_class)
+            }
+            Node::Int32Array(items) if items.is_empty() && element_class.is_some() => {
+                self.class_array(id, &[], element_class)
+            }
+            Node::ByteArray(items) => {
+                self.u8(ARRAY_SINGLE_PRIMITIVE)?;
+                self.i32(id)?;
+                self.i32(items.len() as i32)?;
+                self.u8(BYTE)?;
+                self.bytes(items)
+            }
+            Node::Int32Array(items) => {
+                self.u8(ARRAY_SINGLE_PRIMITIVE)?;
+                self.i32(id)?;
+                self.i32(items.len() as i32)?;
+                self.u8(INT32)?;
+                items.iter().try_for_each(|item| self.i32(*item))
+            }
+            _ => unreachable!("only objects and arrays are referenced"),
+        }
+    }
+}
+
+/// Encode `root` as an NRBF stream. Objects are numbered the way
+/// `BinaryFormatter` does it, so a savegame that was read and not changed is
+/// written back byte for byte.
+pub fn write_nrbf(root: &Node, out: impl Write) -> Result<(), String> {
+    let Node::Object(class_name, values) = root else {
+        return Err(format!("Expected object as root; Got {root:?}"));
+    };
+
+    let mut encoder = Encoder {
+        out,
+        layouts: collect_layouts(root)?,
+        metadata_ids: HashMap::new(),
+        library_id: 2,
+        next_id: 2,
+        last_id: 0,
+        empty_ids: HashMap::new(),
+        pending: VecDeque::new(),
+    };
+
+    // Serialization header: root id, header id, major and minor version.
+    encoder.u8(0)?;
+    encoder.i32(1)?;
+    encoder.i32(-1)?;
+    encoder.i32(1)?;
+    encoder.i32(0)?;
+
+    encoder.u8(BINARY_LIBRARY)?;
+    encoder.i32(encoder.library_id)?;
+    encoder.string(GAME_ASSEMBLY)?;
+
+    encoder.object(1, class_name, values)?;
+    while let Some((id, node, element_class)) = encoder.pending.pop_front() {
+        encoder.deferred(id, node, element_class.as_deref())?;
+    }
+    encoder.u8(MESSAGE_END)?;
+
+    encoder.out.flush().map_err(io_error)
+}
+
+/// A member or array element as read. Primitives are kept in place, everything
+/// else is a record that any number of values can reference.
+enum Slot {
+    Node(Node),
+    Ref(i32),
+}
+
+enum Record {
+    Object(String, Vec<(String, Slot)>),
+    Array(Vec<Slot>),
+    Node(Node),
+}
+
+enum Item {
+    Slot(Slot),
+    Nulls(usize),
+    End,
+}
+
+struct Decoder<R: Read> {
+    input: R,
+    layouts: HashMap<i32, (String, Rc<ClassLayout>)>,
+    records: HashMap<i32, Record>,
+}
+
+fn read_error(error: std::io::Error) -> String {
+    format!("Failed to read savegame: {error}")
+}
+
+fn length(value: i32) -> Result<usize, String> {
+    usize::try_from(value).map_err(|_| format!("Invalid length {value}"))
+}
+
+impl<R: Read> Decoder<R> {
+    fn bytes<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let mut bytes = [0; N];
+        self.input.read_exact(&mut bytes).map_err(read_error)?;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        self.bytes::<1>().map(|[byte]| byte)
+    }
+
+    fn i32(&mut self) -> Result<i32, String> {
+        self.bytes().map(i32::from_le_bytes)
+    }
+
+    fn i64(&mut self) -> Result<i64, String> {
+        self.bytes().map(i64::from_le_bytes)
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let mut length = 0;
+        for shift in (0..35).step_by(7) {
+            let byte = self.u8()?;
+            length |= usize::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                let mut bytes = Vec::new();
+                (&mut self.input)
+                    .take(length as u64)
+                    .read_to_end(&mut bytes)
+                    .map_err(read_error)?;
+                if bytes.len() != length {
+                    return Err("Failed to read savegame: string cut off".to_string());
+                }
+                return String::from_utf8(bytes).map_err(|error| error.to_string());
+            }
+        }
+        Err("Invalid string length".to_string())
+    }
+
+    fn primitive(&mut self, primitive_type: u8) -> Result<Node, String> {
+        Ok(match primitive_type {
+            BOOLEAN => Node::Bool(self.u8()? != 0),
+            INT32 => Node::Int32(self.i32()?),
+            INT64 => Node::Int64(self.i64()?),
+            SINGLE => Node::Single(f32::from_le_bytes(self.bytes()?)),
+            DATE_TIME => Node::DateTime(self.i64()?),
+            _ => return Err(format!("Unsupported primitive type {primitive_type}")),
+        })
+    }
+
+    fn member_type(&mut self, binary_type: u8) -> Result<MemberType, String> {
+        Ok(match binary_type {
+            0 => MemberType::Primitive(self.u8()?),
+            1 => MemberType::String,
+            2 => MemberType::Object,
+            3 => MemberType::SystemClass(self.string()?),
+            4 => {
+                let name = self.string()?;
+                self.i32()?; // Library
+                MemberType::Class(name)
+            }
+            5 => MemberType::ObjectArray,
+            6 => MemberType::StringArray,
+            7 => MemberType::PrimitiveArray(self.u8()?),
+            _ => return Err(format!("Invalid binary type {binary_type}")),
+        })
+    }
+
+    fn store(&mut self, id: i32, record: Record) -> Item {
+        self.records.insert(id, record);
+        Item::Slot(Slot::Ref(id))
+    }
+
+    /// A member value, which can't be a run of several nulls.
+    fn slot(&mut self) -> Result<Slot, String> {
+        match self.item()? {
+            Item::Slot(slot) => Ok(slot),
+            Item::Nulls(1) => Ok(Slot::Node(Node::Null)),
+            _ => Err("Expected a member value".to_string()),
+        }
+    }
+
+    fn object(
+        &mut self,
+        id: i32,
+        class_name: String,
+        layout: &ClassLayout,
+    ) -> Result<Item, String> {
+        let mut values = Vec::with_capacity(layout.keys.len());
+        for (key, ty) in layout.keys.iter().zip(&layout.types) {
+            let value = match ty {
+                MemberType::Primitive(primitive_type) => {
+                    Slot::Node(self.primitive(*primitive_type)?)
+                }
+                _ => self.slot()?,
+            };
+            values.push((key.clone(), value));
+        }
+        Ok(self.store(id, Record::Object(class_name, values)))
+    }
+
+    fn class_with_members(&mut self, record_type: u8) -> Result<Item, String> {
+        let id = self.i32()?;
+        let class_name = self.string()?;
+        let count = length(self.i32()?)?;
+        let keys = (0..count)
+            .map(|_| self.string())
+            .collect::<Result<Vec<_>, _>>()?;
+        let binary_types = (0..count)
+            .map(|_| self.u8())
+            .collect::<Result<Vec<_>, _>>()?;
+        let types = binary_types
+            .into_iter()
+            .map(|binary_type| self.member_type(binary_type))
+            .collect::<Result<_, _>>()?;
+        if record_type == CLASS_WITH_MEMBERS_AND_TYPES {
+            self.i32()?; // Library
+        }
+        let layout = Rc::new(ClassLayout { keys, types });
+        self.layouts
+            .insert(id, (class_name.clone(), Rc::clone(&layout)));
+        self.object(id, class_name, &layout)
+    }
+
+    fn primitive_array(
+        &mut self,
+        id: i32,
+        length: usize,
+        primitive_type: u8,
+    ) -> Result<Item, String> {
+        let node = match primitive_type {
+            BYTE => {
+                let mut bytes = Vec::new();
+                (&mut self.input)
+                    .take(length as u64)
+                    .read_to_end(&mut bytes)
+                    .map_err(read_error)?;
+                if bytes.len() != length {
+                    return Err("Failed to read savegame: byte array cut off".to_string());
+                }
+                Node::ByteArray(bytes)
+            }
+            INT32 => Node::Int32Array((0..length).map(|_| self.i32()).collect::<Result<_, _>>()?),
+            _ => {
+                return Err(format!(
+                    "Unsupported array of primitive type {primitive_type}"
+                ))
+            }
+        };
+        Ok(self.store(id, Record::Node(node)))
+    }
+
+    fn elements(&mut self, id: i32, length: usize) -> Result<Item, String> {
+        let mut items = Vec::new();
+        while items.len() < length {
+            match self.item()? {
+                Item::Slot(slot) => items.push(slot),
+                Item::Nulls(count) => items.extend((0..count).map(|_| Slot::Node(Node::Null))),
+                Item::End => return Err(format!("Array {id} cut off")),
+            }
+        }
+        if items.len() > length {
+            return Err(format!("Array {id} has more than {length} elements"));
+        }
+        Ok(self.store(id, Record::Array(items)))
+    }
+
+    fn item(&mut self) -> Result<Item, String> {
+        let record_type = self.u8()?;
+        match record_type {
+            CLASS_WITH_ID => {
+                let id = self.i32()?;
+                let metadata_id = self.i32()?;
+                let (class_name, layout) =
+                    self.layouts.get(&metadata_id).cloned().ok_or_else(|| {
+                        format!("Object {id} refers to missing class {metadata_id}")
+                    })?;
+                self.object(id, class_name, &layout)
+            }
+            SYSTEM_CLASS_WITH_MEMBERS_AND_TYPES | CLASS_WITH_MEMBERS_AND_TYPES => {
+                self.class_with_members(record_type)
+            }
+            BINARY_OBJECT_STRING => {
+                let id = self.i32()?;
+                let value = self.string()?;
+                Ok(self.store(id, Record::Node(Node::String(value))))
+            }
+            BINARY_ARRAY => {
+                let id = self.i32()?;
+                let array_type = self.u8()?;
+                let rank = self.i32()?;
+                if array_type != 0 || rank != 1 {
+                    return Err(format!("Unsupported array {id} of type {array_type}"));
+                }
+                let length = length(self.i32()?)?;
+                let binary_type = self.u8()?;
+                match self.member_type(binary_type)? {
+                    MemberType::Primitive(primitive_type) => {
+                        self.primitive_array(id, length, primitive_type)
+                    }
+                    _ => self.elements(id, length),
+                }
+            }
+            MEMBER_PRIMITIVE_TYPED => {
+                let primitive_type = self.u8()?;
+                Ok(Item::Slot(Slot::Node(self.primitive(primitive_type)?)))
+            }
+            MEMBER_REFERENCE => Ok(Item::Slot(Slot::Ref(self.i32()?))),
+            OBJECT_NULL => Ok(Item::Nulls(1)),
+            MESSAGE_END => Ok(Item::End),
+            BINARY_LIBRARY => {
+                self.i32()?;
+                self.string()?;
+                self.item()
+            }
+            OBJECT_NULL_MULTIPLE_256 => Ok(Item::Nulls(usize::from(self.u8()?))),
+            OBJECT_NULL_MULTIPLE => Ok(Item::Nulls(length(self.i32()?)?)),
+            ARRAY_SINGLE_PRIMITIVE => {
+                let id = self.i32()?;
+                let length = length(self.i32()?)?;
+                let primitive_type = self.u8()?;
+                self.primitive_array(id, length, primitive_type)
+            }
+            ARRAY_SINGLE_OBJECT | ARRAY_SINGLE_STRING => {
+                let id = self.i32()?;
+                let length = length(self.i32()?)?;
+                self.elements(id, length)
+            }
+            _ => Err(format!("Unsupported record type {record_type}")),
+        }
+    }
+
+    /// Build the tree below `slot`. An object referenced from several places
+    /// is copied into each of them.
+    fn resolve(&self, slot: &Slot, path: &mut Vec<i32>) -> Result<Node, String> {
+        let id = match slot {
+            Slot::Node(node) => return Ok(node.clone()),
+            Slot::Ref(id) => *id,
+        };
+        if path.contains(&id) {
+            return Err(format!("Object {id} contains itself"));
+        }
+        let record = self
+            .records
+            .get(&id)
+            .ok_or_else(|| format!("Missing object {id}"))?;
+        path.push(id);
+        let node = match record {
+            Record::Node(node) => node.clone(),
+            Record::Array(items) => Node::Array(
+                items
+                    .iter()
+                    .map(|item| self.resolve(item, path))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Record::Object(class_name, values) => Node::Object(
+                class_name.clone(),
+                values
+                    .iter()
+                    .map(|(key, value)| Ok((key.clone(), self.resolve(value, path)?)))
+                    .collect::<Result<_, String>>()?,
+            ),
+        };
+        path.pop();
+        Ok(node)
+    }
+}
+
+/// Decode an NRBF stream into the tree of its root object.
+pub fn read_nrbf(input: impl Read) -> Result<Node, String> {
+    let mut decoder = Decoder {
+        input: BufReader::new(input),
+        layouts: HashMap::new(),
+        records: HashMap::new(),
+    };
+    if decoder.u8()? != SERIALIZED_STREAM_HEADER {
+        return Err("Not an NRBF stream".to_string());
+    }
+    let root = decoder.i32()?;
+    // Header id, major and minor version.
+    for _ in 0..3 {
+        decoder.i32()?;
+    }
+    while !matches!(decoder.item()?, Item::End) {}
+    decoder.resolve(&Slot::Ref(root), &mut Vec::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn id_object(class_name: &str, value: i32) -> Node {
+        Node::Object(
+            class_name.to_string(),
+            vec![("value__".to_string(), Node::Int32(value))],
+        )
+    }
+
+    fn list(element: &str, items: Vec<Node>) -> Node {
+        let size = items.iter().filter(|item| **item != Node::Null).count();
+        Node::Object(
+            format!("System.Collections.Generic.List`1[[{element}, {GAME_ASSEMBLY}]]"),
+            vec![
+                ("_items".to_string(), Node::Array(items)),
+                ("_size".to_string(), Node::Int32(size as i32)),
+                ("_version".to_string(), Node::Int32(0)),
+            ],
+        )
+    }
+
+    fn entry(x: i32, name: Node) -> Node {
+        Node::Object(
+            "Entry".to_string(),
+            vec![
+                ("kind".to_string(), id_object("Kind", x)),
+                ("pos".to_string(), Node::Int32Array(vec![x, -x])),
+                ("name".to_string(), name),
+                ("active".to_string(), Node::Bool(x % 2 == 0)),
+            ],
+        )
+    }
+
+    fn sample() -> Node {
+        let mut items: Vec<Node> = (0..3)
+            .map(|x| entry(x, Node::String(format!("entry {x}"))))
+            .collect();
+        // First instance has a null name, its type comes from the others.
+        items.insert(0, entry(7, Node::Null));
+        items.extend(vec![Node::Null; 300]);
+        Node::Object(
+            "Root".to_string(),
+            vec![
+                ("entries".to_string(), list("Entry", items)),
+                ("empty".to_string(), list("Entry", vec![])),
+                ("time".to_string(), Node::Single(1.5)),
+                ("missing".to_string(), Node::Null),
+                // Shares its empty array with `empty`.
+                ("none".to_string(), list("Entry", vec![])),
+            ],
+        )
+    }
+
+    fn round_trip(root: &Node) -> Node {
+        let mut bytes = Vec::new();
+        write_nrbf(root, &mut bytes).unwrap();
+        read_nrbf(Cursor::new(&bytes)).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip_keeps_tree() {
+        let root = sample();
+        assert_eq!(round_trip(&root), root);
+    }
+
+    #[test]
+    fn test_null_member_type_merged_over_instances() {
+        let layouts = collect_layouts(&sample()).unwrap();
+        let entry = &layouts["Entry"];
+        assert_eq!(entry.keys, ["kind", "pos", "name", "active"]);
+        assert_eq!(entry.types[2], MemberType::String);
+        assert_eq!(layouts["Root"].types[3], MemberType::Object);
+    }
+
+    #[test]
+    fn test_known_member_types_follow_the_savegame_layout() {
+        let quest =
+            |class_names: &[&str]| known_member_type("TileData_004", "questTileData", class_names);
+        assert_eq!(
+            quest(&["TileData_004", "QuestTileData_003"]),
+            Some(MemberType::Class("QuestTileData_003".to_string()))
+        );
+        assert_eq!(
+            quest(&["TileData_004"]),
+            Some(MemberType::Class("QuestTileData_002".to_string()))
+        );
+        assert_eq!(
+            known_member_type("SaveGameData_004", "fileName", &[]),
+            Some(MemberType::String)
+        );
+    }
+
+    #[test]
+    fn test_empty_list_items_typed_by_list() {
+        let root = sample();
+        let empty = root.field("empty").unwrap();
+        assert_eq!(
+            infer_member_type(
+                empty.class_name().unwrap(),
+                "_items",
+                &Node::Int32Array(vec![])
+            ),
+            Some(MemberType::Class("Entry[]".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_round_trip_keeps_bytes_and_64_bit_values() {
+        let root = Node::Object(
+            "Root".to_string(),
+            vec![
+                (
+                    "blob".to_string(),
+                    Node::ByteArray(vec![0x89, b'P', b'N', b'G']),
+                ),
+                ("count".to_string(), Node::Int64(-1 << 40)),
+                ("saved".to_string(), Node::DateTime(0x48d9_0f3c_6b2e_a000)),
+                (
+                    "entry".to_string(),
+                    entry(1, Node::Array(vec![Node::Int64(7), Node::DateTime(8)])),
+                ),
+            ],
+        );
+        assert_eq!(round_trip(&root), root);
+    }
+
+    #[test]
+    fn test_truncated_stream_rejected() {
+        let mut bytes = Vec::new();
+        write_nrbf(&sample(), &mut bytes).unwrap();
+        bytes.truncate(bytes.len() - 10);
+        assert!(read_nrbf(Cursor::new(&bytes)).is_err());
+    }
+
+    #[test]
+    fn test_inconsistent_instances_rejected() {
+        let root = Node::Object(
+            "Root".to_string(),
+            vec![
+                ("a".to_string(), entry(1, Node::Null)),
+                (
+                    "b".to_string(),
+                    Node::Object("Entry".to_string(), vec![("kind".to_string(), Node::Null)]),
+                ),
+            ],
+        );
+        assert!(write_nrbf(&root, &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_string_length_prefix() {
+        let long = "x".repeat(200);
+        let root = Node::Object(
+            "Root".to_string(),
+            vec![("text".to_string(), Node::String(long))],
+        );
+        assert_eq!(round_trip(&root), root);
+    }
+}
@@ -0,0 +1,327 @@
+//! Whether the active quests can still be completed. `Group::remaining_per_quest` only says how
+//! far a group is from its target. An exact quest fails once its group overshoots and a flag
+//! quest needs its group closed, so each quest is checked against what a single placement at
+//! one of its group's open edges can add, trying every known tile pattern in every rotation.
+
+use crate::{
+    best_placements::constraints_at,
+    data::{EdgeMatch, EdgeProfile, HexPos, Rotation, Terrain, HEX_SIDES},
+    group::GroupIndex,
+    group_assignments::GroupAssignments,
+    lookahead::{distinct_rotations, sorted},
+    map::{Map, Quest, QuestType},
+    score,
+    tile_frequency::{EdgePattern, TileFrequencies},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Infeasible {
+    /// An exact quest whose group has more units than the target.
+    Overshot,
+    /// The group is closed short of (or past) the target.
+    Closed,
+    /// Every placement at the open edges of an exact quest adds too many units.
+    OvershootsEverywhere,
+    /// No known pattern can be placed at an open edge, so the group can never close.
+    Blocked,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuestStatus {
+    /// The group meets the quest already.
+    Reached,
+    /// The quest can still be completed.
+    Feasible,
+    Infeasible(Infeasible),
+}
+
+/// A placement that brings a group exactly to the target of its exact quest.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExactHit {
+    pub pos: HexPos,
+    pub rotation: Rotation,
+    pub pattern: EdgePattern,
+    /// The group has no open edges left afterwards, which completes the quest.
+    pub closes: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct QuestFeasibility {
+    pub group: GroupIndex,
+    pub quest: Quest,
+    pub units: u32,
+    /// Units to the target. Negative means past it.
+    pub remaining: i32,
+    pub open_edges: usize,
+    pub status: QuestStatus,
+    /// Fewest and most units one placement at an open edge adds to the group, over all known
+    /// patterns. `None` if nothing fits any open edge.
+    pub gain: Option<(u32, u32)>,
+    /// Open edges where no known pattern can be placed at all.
+    pub blocked_edges: usize,
+    /// Exact quests only.
+    pub exact_hits: Vec<ExactHit>,
+}
+
+impl QuestFeasibility {
+    pub fn is_feasible(&self) -> bool {
+        !matches!(self.status, QuestStatus::Infeasible(_))
+    }
+
+    /// Short marker for quest labels: `ok`, `xN` for N exact hits, `close` for an exact quest
+    /// at its target whose group still has to be closed, or why it is lost.
+    pub fn label(&self) -> String {
+        match self.status {
+            QuestStatus::Reached => "ok".to_string(),
+            QuestStatus::Feasible
+                if self.quest.quest_type == QuestType::Exact && self.remaining == 0 =>
+            {
+                "close".to_string()
+            }
+            QuestStatus::Feasible if self.quest.quest_type == QuestType::Exact => {
+                format!("x{}", self.exact_hits.len())
+            }
+            QuestStatus::Feasible => String::new(),
+            QuestStatus::Infeasible(Infeasible::Overshot) => "overshot".to_string(),
+            QuestStatus::Infeasible(Infeasible::Closed) => "closed".to_string(),
+            QuestStatus::Infeasible(Infeasible::OvershootsEverywhere) => "too big".to_string(),
+            QuestStatus::Infeasible(Infeasible::Blocked) => "blocked".to_string(),
+        }
+    }
+}
+
+/// Whether the game allows a tile with `profile` at `constraints`. Only rail and river edges
+/// that do not connect forbid a placement. Unlike a fit, mismatched edges are fine, as they close
+/// the group just as well.
+fn is_legal(profile: &EdgeProfile, constraints: &[Option<Terrain>; HEX_SIDES]) -> bool {
+    constraints.iter().enumerate().all(|(side, constraint)| {
+        constraint.is_none_or(|neighbor| {
+            profile.at_index(side).connects_and_matches(neighbor) != EdgeMatch::Illegal
+        })
+    })
+}
+
+/// Whether some known pattern can legally be placed at `pos` in some rotation.
+fn is_placeable(map: &Map, freqs: &TileFrequencies, pos: HexPos) -> bool {
+    let constraints = constraints_at(map, pos);
+    freqs.entries.iter().any(|entry| {
+        let profile = EdgeProfile::from_segments(&entry.segments);
+        (0..HEX_SIDES).any(|rotation| is_legal(&profile.rotated(rotation), &constraints))
+    })
+}
+
+/// One legal placement at an open edge of a group.
+struct Growth {
+    pos: HexPos,
+    rotation: Rotation,
+    pattern: usize,
+    gain: u32,
+    closes: bool,
+}
+
+/// Every legal placement of every known pattern at the open edges of `group_index`.
+fn growths(
+    map: &Map,
+    groups: &GroupAssignments,
+    freqs: &TileFrequencies,
+    group_index: GroupIndex,
+) -> Vec<Growth> {
+    let group = &groups.groups[group_index];
+    let mut growths = Vec::new();
+    for pos in sorted(group.open_edges.iter().copied()) {
+        let constraints = constraints_at(map, pos);
+        for (pattern, entry) in freqs.entries.iter().enumerate() {
+            let profile = EdgeProfile::from_segments(&entry.segments);
+            for (rotation, rotated) in distinct_rotations(&profile) {
+                if !is_legal(&rotated, &constraints) {
+                    continue;
+                }
+                let Some(component) =
+                    score::components(map, groups, &entry.segments, pos, rotation)
+                        .into_iter()
+                        .find(|component| component.groups.contains(&group_index))
+                else {
+                    continue;
+                };
+                growths.push(Growth {
+                    pos,
+                    rotation,
+                    pattern,
+                    gain: component.units_after(groups) - group.unit_count,
+                    closes: component.closes(groups, pos),
+                });
+            }
+        }
+    }
+    growths
+}
+
+/// Feasibility of the active quests of one group.
+pub fn analyze_group(
+    map: &Map,
+    groups: &GroupAssignments,
+    freqs: &TileFrequencies,
+    group_index: GroupIndex,
+) -> Vec<QuestFeasibility> {
+    let group = &groups.groups[group_index];
+    let quests: Vec<(&Quest, i32)> = group
+        .remaining_per_quest()
+        .into_iter()
+        .filter(|(quest, _)| quest.active)
+        .collect();
+    if quests.is_empty() {
+        return Vec::new();
+    }
+
+    let closed = group.is_closed();
+    let blocked_edges = group
+        .open_edges
+        .iter()
+        .filter(|&&pos| !is_placeable(map, freqs, pos))
+        .count();
+    let needs_growth = !closed
+        && quests
+            .iter()
+            .any(|(quest, _)| quest.quest_type != QuestType::Flag);
+    let growths = if needs_growth {
+        growths(map, groups, freqs, group_index)
+    } else {
+        Vec::new()
+    };
+    let gain = growths
+        .iter()
+        .map(|growth| growth.gain)
+        .min()
+        .zip(growths.iter().map(|growth| growth.gain).max());
+
+    quests
+        .into_iter()
+        .map(|(quest, remaining)| {
+            let mut exact_hits = Vec::new();
+            // An exact quest at its target only needs its group closed, like a flag quest.
+            let close_only = quest.quest_type == QuestType::Flag
+                || (quest.quest_type == QuestType::Exact && remaining == 0);
+            let status = match quest.quest_type {
+                QuestType::MoreThan | QuestType::Unknown if remaining <= 0 => QuestStatus::Reached,
+                QuestType::Exact if remaining < 0 => QuestStatus::Infeasible(Infeasible::Overshot),
+                QuestType::Exact if closed && remaining == 0 => QuestStatus::Reached,
+                QuestType::Flag if closed => QuestStatus::Reached,
+                _ if closed => QuestStatus::Infeasible(Infeasible::Closed),
+                _ if close_only && blocked_edges > 0 => {
+                    QuestStatus::Infeasible(Infeasible::Blocked)
+                }
+                _ if close_only => QuestStatus::Feasible,
+                _ => match gain {
+                    None => QuestStatus::Infeasible(Infeasible::Blocked),
+                    Some((min, _)) if quest.quest_type == QuestType::Exact => {
+                        exact_hits = growths
+                            .iter()
+                            .filter(|growth| growth.gain as i32 == remaining)
+                            .map(|growth| ExactHit {
+                                pos: growth.pos,
+                                rotation: growth.rotation,
+                                pattern: freqs.entries[growth.pattern].edges.clone(),
+                                closes: growth.closes,
+                            })
+                            .collect();
+                        if min as i32 > remaining {
+                            QuestStatus::Infeasible(Infeasible::OvershootsEverywhere)
+                        } else {
+                            QuestStatus::Feasible
+                        }
+                    }
+                    Some(_) => QuestStatus::Feasible,
+                },
+            };
+            QuestFeasibility {
+                group: group_index,
+                quest: quest.clone(),
+                units: group.unit_count,
+                remaining,
+                open_edges: group.open_edges.len(),
+                status,
+                gain,
+                blocked_edges,
+                exact_hits,
+            }
+        })
+        .collect()
+}
+
+/// Feasibility of every active quest, ordered by group.
+pub fn analyze(
+    map: &Map,
+    groups: &GroupAssignments,
+    freqs: &TileFrequencies,
+) -> Vec<QuestFeasibility> {
+    (0..groups.groups.len())
+        .flat_map(|group_index| analyze_group(map, groups, freqs, group_index))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feasibility(quest_type: QuestType, status: QuestStatus, hits: usize) -> QuestFeasibility {
+        let hit = ExactHit {
+            pos: HexPos::new(0, 0),
+            rotation: 0,
+            pattern: EdgePattern(EdgeProfile::from_segments(&[])),
+            closes: false,
+        };
+        QuestFeasibility {
+            group: 0,
+            quest: Quest {
+                terrain: Terrain::Forest,
+                target_value: 10,
+                active: true,
+                quest_type,
+                quest_id: 0,
+                quest_level: 0,
+                quest_queue_index: 0,
+                unlocked_challenge_id: 0,
+            },
+            units: 8,
+            remaining: 2,
+            open_edges: 3,
+            status,
+            gain: Some((1, 4)),
+            blocked_edges: 0,
+            exact_hits: vec![hit; hits],
+        }
+    }
+
+    #[test]
+    fn test_mismatched_edges_are_legal() {
+        let empty = EdgeProfile::default();
+        let mut constraints = [None; HEX_SIDES];
+        constraints[2] = Some(Terrain::Forest);
+        assert!(is_legal(&empty, &constraints));
+        constraints[4] = Some(Terrain::Rail);
+        assert!(!is_legal(&empty, &constraints));
+    }
+
+    #[test]
+    fn test_labels() {
+        let exact = feasibility(QuestType::Exact, QuestStatus::Feasible, 3);
+        assert!(exact.is_feasible());
+        assert_eq!(exact.label(), "x3");
+        assert_eq!(
+            feasibility(QuestType::MoreThan, QuestStatus::Feasible, 0).label(),
+            ""
+        );
+        let lost = feasibility(
+            QuestType::Exact,
+            QuestStatus::Infeasible(Infeasible::OvershootsEverywhere),
+            0,
+        );
+        assert!(!lost.is_feasible());
+        assert_eq!(lost.label(), "too big");
+        let at_target = QuestFeasibility {
+            remaining: 0,
+            ..feasibility(QuestType::Exact, QuestStatus::Feasible, 0)
+        };
+        assert_eq!(at_target.label(), "close");
+    }
+}
@@ -1,10 +1,170 @@
+use crate::nrbf_tree::Node;
 use nrbf_rs::value::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
 
-fn expected_got<T>(expected: &str, got: &str) -> Result<T, String> {
-    Err(format!(
-        "Expected {expected}; Got {}",
-        &got[..100.min(got.len())]
-    ))
+/// Step from a value to one of its children.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PathSegment {
+    Field(String),
+    Index(usize),
+}
+
+/// Why a savegame value could not be decoded, and where it is.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodeError {
+    /// Outermost first, see [`DecodeError::path_string`].
+    pub path: Vec<PathSegment>,
+    pub expected: String,
+    /// Class name of the value that was found, `None` if it is no object.
+    pub actual: Option<String>,
+    /// Start of the text form of the value that was found.
+    pub preview: String,
+    /// Extra context, e.g. that the layout is newer than any adapter.
+    pub note: Option<String>,
+}
+
+const PREVIEW_CHARS: usize = 60;
+
+fn preview_of(value: &Value) -> String {
+    let text = match value {
+        // Printing a whole object can mean printing the whole savegame.
+        Value::Object(class_name, values) => {
+            let keys: Vec<&str> = values.iter().map(|(k, _)| k.as_str()).collect();
+            format!("{class_name} {{ {} }}", keys.join(", "))
+        }
+        _ => value.to_string(),
+    };
+    if text.chars().count() > PREVIEW_CHARS {
+        text.chars()
+            .take(PREVIEW_CHARS)
+            .chain("...".chars())
+            .collect()
+    } else {
+        text
+    }
+}
+
+impl DecodeError {
+    fn new(expected: impl Into<String>, value: &Value) -> Self {
+        Self {
+            path: Vec::new(),
+            expected: expected.into(),
+            actual: match value {
+                Value::Object(class_name, _) => Some(class_name.clone()),
+                _ => None,
+            },
+            preview: preview_of(value),
+            note: None,
+        }
+    }
+
+    fn missing_field() -> Self {
+        Self {
+            path: Vec::new(),
+            expected: "field".to_string(),
+            actual: None,
+            preview: "nothing".to_string(),
+            note: None,
+        }
+    }
+
+    fn with_note(mut self, note: String) -> Self {
+        self.note = Some(note);
+        self
+    }
+
+    /// Prefix the path with the step that led to the failing value.
+    fn at(mut self, segment: PathSegment) -> Self {
+        self.path.insert(0, segment);
+        self
+    }
+
+    /// E.g. `tiles[1832].segments[2].segmentType`.
+    pub fn path_string(&self) -> String {
+        let mut path = String::new();
+        for segment in &self.path {
+            match segment {
+                PathSegment::Field(key) if path.is_empty() => path.push_str(key),
+                PathSegment::Field(key) => {
+                    path.push('.');
+                    path.push_str(key);
+                }
+                PathSegment::Index(index) => path.push_str(&format!("[{index}]")),
+            }
+        }
+        path
+    }
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path_string())?;
+        }
+        write!(f, "expected {}, got ", self.expected)?;
+        match &self.actual {
+            Some(class_name) if *class_name != self.preview => {
+                write!(f, "{class_name} ({})", self.preview)?
+            }
+            _ => write!(f, "{}", self.preview)?,
+        }
+        if let Some(note) = &self.note {
+            write!(f, " [{note}]")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl From<DecodeError> for String {
+    fn from(error: DecodeError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Decoding that reports failures as [`DecodeError`]s.
+trait Decode: Sized {
+    fn decode(value: &Value) -> Result<Self, DecodeError>;
+}
+
+macro_rules! primitive_decode {
+    ($type:ty, $name:literal) => {
+        impl Decode for $type {
+            fn decode(value: &Value) -> Result<Self, DecodeError> {
+                <$type>::try_from(value).map_err(|_| DecodeError::new($name, value))
+            }
+        }
+    };
+}
+
+primitive_decode!(i32, "Int32");
+primitive_decode!(bool, "Boolean");
+primitive_decode!(f32, "Single");
+primitive_decode!(String, "String");
+
+/// Decodes one array element without failing the whole array, so that the
+/// error can be tagged with the element's index.
+struct Element<T>(Result<T, DecodeError>);
+
+impl<T: Decode> TryFrom<&Value> for Element<T> {
+    type Error = String;
+
+    fn try_from(value: &Value) -> Result<Self, String> {
+        Ok(Element(T::decode(value)))
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(value: &Value) -> Result<Self, DecodeError> {
+        Vec::<Element<T>>::try_from(value)
+            .map_err(|_| DecodeError::new("array", value))?
+            .into_iter()
+            .enumerate()
+            .map(|(index, Element(item))| item.map_err(|error| error.at(PathSegment::Index(index))))
+            .collect()
+    }
 }
 
 enum Maybe<T> {
@@ -19,13 +179,11 @@ impl<T> Maybe<T> {
         }
     }
 }
-impl<T: for<'a> TryFrom<&'a Value, Error = std::string::String>> TryFrom<&Value> for Maybe<T> {
-    type Error = String;
-
-    fn try_from(value: &Value) -> Result<Self, String> {
+impl<T: Decode> Decode for Maybe<T> {
+    fn decode(value: &Value) -> Result<Self, DecodeError> {
         match value {
             Value::Null => Ok(Maybe::Nothing),
-            _ => T::try_from(value).map(Maybe::Just),
+            _ => T::decode(value).map(Maybe::Just),
         }
     }
 }
@@ -37,30 +195,22 @@ fn filter_none<T>(vec: Vec<Maybe<T>>) -> Vec<T> {
 fn try_object_from<'a>(
     expected_class: &str,
     value: &'a Value,
-) -> Result<&'a [(String, Value)], String> {
-    if let Value::Object(class_name, values) = value {
-        if class_name != expected_class {
-            return expected_got(expected_class, class_name);
-        }
-
-        Ok(values.as_slice())
-    } else {
-        expected_got(expected_class, &value.to_string())
+) -> Result<&'a [(String, Value)], DecodeError> {
+    match value {
+        Value::Object(class_name, values) if class_name == expected_class => Ok(values.as_slice()),
+        _ => Err(DecodeError::new(expected_class, value)),
     }
 }
 
 fn try_prefix_object_from<'a>(
     class_prefix: &str,
     value: &'a Value,
-) -> Result<&'a [(String, Value)], String> {
-    if let Value::Object(class_name, values) = value {
-        if !class_name.starts_with(class_prefix) {
-            return expected_got(class_prefix, class_name);
+) -> Result<&'a [(String, Value)], DecodeError> {
+    match value {
+        Value::Object(class_name, values) if class_name.starts_with(class_prefix) => {
+            Ok(values.as_slice())
         }
-
-        Ok(values.as_slice())
-    } else {
-        expected_got(class_prefix, &value.to_string())
+        _ => Err(DecodeError::new(format!("{class_prefix}..."), value)),
     }
 }
 
@@ -70,80 +220,175 @@ impl<T> From<GenericList<T>> for Vec<T> {
         val.0
     }
 }
-impl<T: for<'a> TryFrom<&'a Value, Error = String>> TryFrom<&Value> for GenericList<T> {
-    type Error = String;
-
-    fn try_from(value: &Value) -> Result<Self, String> {
+impl<T: Decode> Decode for GenericList<T> {
+    fn decode(value: &Value) -> Result<Self, DecodeError> {
         let values = try_prefix_object_from("System.Collections.Generic.List`1[", value)?;
-        try_key_as::<Vec<T>>(values, "_items").map(GenericList)
+        // Index the list itself, `_items` is an implementation detail.
+        Vec::decode(try_key_of(values, "_items")?).map(GenericList)
     }
 }
 
-fn try_key_of<'a>(values: &'a [(String, Value)], key: &str) -> Result<&'a Value, String> {
+fn try_key_of<'a>(values: &'a [(String, Value)], key: &str) -> Result<&'a Value, DecodeError> {
     values
         .iter()
         .find(|(k, _)| k == key)
         .map(|(_, v)| v)
-        .ok_or_else(|| format!("No {key} field in object"))
+        .ok_or_else(|| DecodeError::missing_field().at(PathSegment::Field(key.to_string())))
 }
 
-fn try_key_as<'a, T: TryFrom<&'a Value, Error = std::string::String>>(
-    values: &'a [(String, Value)],
-    key: &str,
-) -> Result<T, String> {
-    T::try_from(try_key_of(values, key)?)
-        .map_err(|error| format!("While converting key {key}:\n{error}"))
+fn try_key_as<T: Decode>(values: &[(String, Value)], key: &str) -> Result<T, DecodeError> {
+    T::decode(try_key_of(values, key)?)
+        .map_err(|error| error.at(PathSegment::Field(key.to_string())))
 }
 
-fn from_id_object(expected_class: &str, value: &Value) -> Result<i32, String> {
+fn from_id_object(expected_class: &str, value: &Value) -> Result<i32, DecodeError> {
     try_key_as(try_object_from(expected_class, value)?, "value__")
 }
 
-#[derive(Debug)]
+macro_rules! decode_via_try_from {
+    ($($type:ty),*) => {
+        $(
+            impl Decode for $type {
+                fn decode(value: &Value) -> Result<Self, DecodeError> {
+                    <$type>::try_from(value)
+                }
+            }
+        )*
+    };
+}
+
+decode_via_try_from!(
+    ChallengeId,
+    GroupTypeId,
+    Segment,
+    QuestTile,
+    Tile,
+    PreplacedTile,
+    SaveGame,
+    GameModeId,
+    QuestTileId,
+    QuestId,
+    SegmentTypeId,
+    SpecialTileId
+);
+
+/// Split a versioned class name like `TileData_003` or `SegmentData002` into
+/// its name and layout version.
+pub fn split_class_version(class_name: &str) -> Option<(&str, u32)> {
+    let name = class_name.trim_end_matches(|c: char| c.is_ascii_digit());
+    let version = class_name[name.len()..].parse().ok()?;
+    Some((name.strip_suffix('_').unwrap_or(name), version))
+}
+
+/// The name of `class_name` without its layout version, if it has one.
+pub fn unversioned(class_name: &str) -> &str {
+    split_class_version(class_name).map_or(class_name, |(name, _)| name)
+}
+
+type Adapter<T> = fn(&[(String, Value)]) -> Result<T, DecodeError>;
+
+/// Decoders for every known layout of a versioned class.
+struct Schema<T: 'static> {
+    name: &'static str,
+    /// Sorted by version.
+    adapters: &'static [(u32, Adapter<T>)],
+}
+
+impl<T> Schema<T> {
+    fn versions(&self) -> Vec<u32> {
+        self.adapters.iter().map(|(version, _)| *version).collect()
+    }
+
+    /// Pick the adapter by the object's `version` field, or by the class name
+    /// suffix if it has none. Layouts newer than the newest adapter are read
+    /// with that one, game updates mostly add fields.
+    fn decode(&self, value: &Value) -> Result<T, DecodeError> {
+        let class_version = match value {
+            Value::Object(class_name, values) => split_class_version(class_name)
+                .filter(|(name, _)| *name == self.name)
+                .map(|(_, version)| (class_name, values, version)),
+            _ => None,
+        };
+        let Some((class_name, values, class_version)) = class_version else {
+            return Err(DecodeError::new(format!("{}_*", self.name), value));
+        };
+        let version = try_key_as::<i32>(values, "version")
+            .ok()
+            .and_then(|version| u32::try_from(version).ok())
+            .unwrap_or(class_version);
+
+        if let Some((_, adapter)) = self.adapters.iter().find(|(known, _)| *known == version) {
+            return adapter(values);
+        }
+        match self.adapters.last() {
+            Some((newest, adapter)) if version > *newest => adapter(values).map_err(|error| {
+                error.with_note(format!(
+                    "{class_name} (version {version}) is newer than the known layouts {:?}",
+                    self.versions()
+                ))
+            }),
+            _ => Err(DecodeError::new(
+                format!("{} layout {:?}", self.name, self.versions()),
+                value,
+            )
+            .with_note(format!("{class_name} has version {version}"))),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct ChallengeId(pub i32);
 
 impl TryFrom<&Value> for ChallengeId {
-    type Error = String;
+    type Error = DecodeError;
 
-    fn try_from(value: &Value) -> Result<Self, String> {
+    fn try_from(value: &Value) -> Result<Self, DecodeError> {
         from_id_object("Dorfromantik.ChallengeId", value).map(Self)
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct GroupTypeId(pub i32);
 
 impl TryFrom<&Value> for GroupTypeId {
-    type Error = String;
+    type Error = DecodeError;
 
-    fn try_from(value: &Value) -> Result<Self, String> {
+    fn try_from(value: &Value) -> Result<Self, DecodeError> {
         from_id_object("GroupTypeId", value).map(Self)
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Segment {
     pub group_type: GroupTypeId,
     pub segment_type: SegmentTypeId,
     pub rotation: i32,
-    _version: i32,
+    pub version: i32,
 }
 
 impl TryFrom<&Value> for Segment {
-    type Error = String;
+    type Error = DecodeError;
 
-    fn try_from(value: &Value) -> Result<Self, String> {
-        let values = try_object_from("SegmentData002", value)?;
-        Ok(Self {
-            group_type: try_key_as(values, "groupType")?,
-            segment_type: try_key_as(values, "segmentType")?,
-            rotation: try_key_as(values, "rotation")?,
-            _version: try_key_as(values, "version")?,
-        })
+    fn try_from(value: &Value) -> Result<Self, DecodeError> {
+        SEGMENT_SCHEMA.decode(value)
     }
 }
 
-#[derive(Debug)]
+const SEGMENT_SCHEMA: Schema<Segment> = Schema {
+    name: "SegmentData",
+    adapters: &[(2, segment_v2)],
+};
+
+fn segment_v2(values: &[(String, Value)]) -> Result<Segment, DecodeError> {
+    Ok(Segment {
+        group_type: try_key_as(values, "groupType")?,
+        segment_type: try_key_as(values, "segmentType")?,
+        rotation: try_key_as(values, "rotation")?,
+        version: try_key_as(values, "version")?,
+    })
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct QuestTile {
     pub quest_tile_id: QuestTileId,
     pub quest_active: bool,
@@ -152,64 +397,84 @@ pub struct QuestTile {
     pub quest_level: i32,
     pub quest_id: QuestId,
     pub unlocked_challenge_id: ChallengeId,
-    _version: i32,
+    pub version: i32,
 }
 
 impl TryFrom<&Value> for QuestTile {
-    type Error = String;
+    type Error = DecodeError;
 
-    fn try_from(value: &Value) -> Result<Self, String> {
-        let values = try_object_from("QuestTileData_002", value)?;
-        Ok(Self {
-            quest_tile_id: try_key_as(values, "questTileId")?,
-            quest_active: try_key_as(values, "questActive")?,
-            quest_queue_index: try_key_as(values, "questQueueIndex")?,
-            target_value: try_key_as(values, "targetValue")?,
-            quest_level: try_key_as(values, "questLevel")?,
-            quest_id: try_key_as(values, "questId")?,
-            unlocked_challenge_id: try_key_as(values, "unlockedChallengeId")?,
-            _version: try_key_as(values, "version")?,
-        })
+    fn try_from(value: &Value) -> Result<Self, DecodeError> {
+        QUEST_TILE_SCHEMA.decode(value)
     }
 }
 
-#[derive(Debug)]
+const QUEST_TILE_SCHEMA: Schema<QuestTile> = Schema {
+    name: "QuestTileData",
+    adapters: &[(2, quest_tile_v2)],
+};
+
+fn quest_tile_v2(values: &[(String, Value)]) -> Result<QuestTile, DecodeError> {
+    Ok(QuestTile {
+        quest_tile_id: try_key_as(values, "questTileId")?,
+        quest_active: try_key_as(values, "questActive")?,
+        quest_queue_index: try_key_as(values, "questQueueIndex")?,
+        target_value: try_key_as(values, "targetValue")?,
+        quest_level: try_key_as(values, "questLevel")?,
+        quest_id: try_key_as(values, "questId")?,
+        unlocked_challenge_id: try_key_as(values, "unlockedChallengeId")?,
+        version: try_key_as(values, "version")?,
+    })
+}
+
+#[derive(Clone, Debug)]
 pub struct Tile {
     pub s: i32,
     pub t: i32,
     pub rotation: i32,
-    _seed: i32,
+    pub seed: i32,
     pub segments: Vec<Segment>,
     pub special_tile_id: SpecialTileId,
     pub quest_tile: Option<QuestTile>,
-    _version: i32,
+    pub version: i32,
 }
 
 impl TryFrom<&Value> for Tile {
-    type Error = String;
+    type Error = DecodeError;
 
-    fn try_from(value: &Value) -> Result<Self, String> {
-        let values = try_object_from("TileData_003", value)?;
-        let grid_pos: Vec<i32> = try_key_as(values, "gridPos")?;
-        Ok(Tile {
-            s: grid_pos[0],
-            t: grid_pos[1],
-            rotation: try_key_as(values, "rotation")?,
-            _seed: try_key_as(values, "seed")?,
-            segments: filter_none(
-                try_key_as::<Maybe<GenericList<_>>>(values, "segments")?
-                    .into_option()
-                    .map(Into::into)
-                    .unwrap_or(vec![]),
-            ),
-            special_tile_id: try_key_as(values, "specialTileId")?,
-            quest_tile: try_key_as::<Maybe<_>>(values, "questTileData")?.into_option(),
-            _version: try_key_as(values, "version")?,
-        })
+    fn try_from(value: &Value) -> Result<Self, DecodeError> {
+        TILE_SCHEMA.decode(value)
     }
 }
 
-#[derive(Debug)]
+const TILE_SCHEMA: Schema<Tile> = Schema {
+    name: "TileData",
+    adapters: &[(3, tile_v3)],
+};
+
+fn tile_v3(values: &[(String, Value)]) -> Result<Tile, DecodeError> {
+    let grid_pos: Vec<i32> = try_key_as(values, "gridPos")?;
+    let [s, t] = grid_pos[..] else {
+        return Err(DecodeError::new("[s, t]", try_key_of(values, "gridPos")?)
+            .at(PathSegment::Field("gridPos".to_string())));
+    };
+    Ok(Tile {
+        s,
+        t,
+        rotation: try_key_as(values, "rotation")?,
+        seed: try_key_as(values, "seed")?,
+        segments: filter_none(
+            try_key_as::<Maybe<GenericList<_>>>(values, "segments")?
+                .into_option()
+                .map(Into::into)
+                .unwrap_or(vec![]),
+        ),
+        special_tile_id: try_key_as(values, "specialTileId")?,
+        quest_tile: try_key_as::<Maybe<_>>(values, "questTileData")?.into_option(),
+        version: try_key_as(values, "version")?,
+    })
+}
+
+#[derive(Clone, Debug)]
 pub struct PreplacedTile {
     pub section_grid_pos_x: i32,
     pub section_grid_pos_y: i32,
@@ -218,20 +483,28 @@ pub struct PreplacedTile {
 }
 
 impl TryFrom<&Value> for PreplacedTile {
-    type Error = String;
+    type Error = DecodeError;
 
-    fn try_from(value: &Value) -> Result<Self, String> {
-        let values = try_object_from("PreplacedTileData_002", value)?;
-        Ok(Self {
-            section_grid_pos_x: try_key_as(values, "sectionGridPosX")?,
-            section_grid_pos_y: try_key_as(values, "sectionGridPosY")?,
-            preplaced_tile_id: try_key_as(values, "preplacedTileId")?,
-            version: try_key_as(values, "version")?,
-        })
+    fn try_from(value: &Value) -> Result<Self, DecodeError> {
+        PREPLACED_TILE_SCHEMA.decode(value)
     }
 }
 
-#[derive(Debug)]
+const PREPLACED_TILE_SCHEMA: Schema<PreplacedTile> = Schema {
+    name: "PreplacedTileData",
+    adapters: &[(2, preplaced_tile_v2)],
+};
+
+fn preplaced_tile_v2(values: &[(String, Value)]) -> Result<PreplacedTile, DecodeError> {
+    Ok(PreplacedTile {
+        section_grid_pos_x: try_key_as(values, "sectionGridPosX")?,
+        section_grid_pos_y: try_key_as(values, "sectionGridPosY")?,
+        preplaced_tile_id: try_key_as(values, "preplacedTileId")?,
+        version: try_key_as(values, "version")?,
+    })
+}
+
+#[derive(Clone, Debug)]
 pub struct SaveGame {
     pub game_mode: GameModeId,
     pub level: i32,
@@ -262,104 +535,679 @@ pub struct SaveGame {
 }
 
 impl TryFrom<&Value> for SaveGame {
-    type Error = String;
+    type Error = DecodeError;
 
-    fn try_from(value: &Value) -> Result<Self, String> {
-        let values = try_object_from("SaveGameData_003", value)?;
-        Ok(Self {
-            game_mode: try_key_as(values, "gameMode")?,
-            level: try_key_as(values, "level")?,
-            score: try_key_as(values, "score")?,
-            perfect_placements: try_key_as(values, "perfectPlacements")?,
-            quests_fulfilled: try_key_as(values, "questsFulfilled")?,
-            quests_failed: try_key_as(values, "questsFailed")?,
-            consecutive_perfect_fits: try_key_as(values, "consecutivePerfectFits")?,
-            consecutive_placements_without_rotate: try_key_as(
-                values,
-                "consecutivePlacementsWithoutRotate",
-            )?,
-            playtime: try_key_as(values, "playtime")?,
-            biome_seed: try_key_as(values, "biomeSeed")?,
-            preplaced_tile_seed: try_key_as(values, "preplacedTileSeed")?,
-            placed_tile_count: try_key_as(values, "placedTileCount")?,
-            generated_tile_count: try_key_as(values, "generatedTileCount")?,
-            generated_quest_count: try_key_as(values, "generatedQuestCount")?,
-            surrounded_tiles_count: try_key_as(values, "surroundedTilesCount")?,
-            tiles: filter_none(try_key_as::<GenericList<_>>(values, "tiles")?.into()),
-            tile_stack: filter_none(try_key_as::<GenericList<_>>(values, "tileStack")?.into()),
-            preplaced_tiles: filter_none(
-                try_key_as::<GenericList<_>>(values, "preplacedTiles")?.into(),
-            ),
+    fn try_from(value: &Value) -> Result<Self, DecodeError> {
+        SAVE_GAME_SCHEMA.decode(value)
+    }
+}
+
+const SAVE_GAME_SCHEMA: Schema<SaveGame> = Schema {
+    name: "SaveGameData",
+    adapters: &[(2, save_game_v2), (3, save_game_v3)],
+};
+
+/// Layout 2 predates the reward milestones.
+fn save_game_v2(values: &[(String, Value)]) -> Result<SaveGame, DecodeError> {
+    Ok(SaveGame {
+        game_mode: try_key_as(values, "gameMode")?,
+        level: try_key_as(values, "level")?,
+        score: try_key_as(values, "score")?,
+        perfect_placements: try_key_as(values, "perfectPlacements")?,
+        quests_fulfilled: try_key_as(values, "questsFulfilled")?,
+        quests_failed: try_key_as(values, "questsFailed")?,
+        consecutive_perfect_fits: try_key_as(values, "consecutivePerfectFits")?,
+        consecutive_placements_without_rotate: try_key_as(
+            values,
+            "consecutivePlacementsWithoutRotate",
+        )?,
+        playtime: try_key_as(values, "playtime")?,
+        biome_seed: try_key_as(values, "biomeSeed")?,
+        preplaced_tile_seed: try_key_as(values, "preplacedTileSeed")?,
+        placed_tile_count: try_key_as(values, "placedTileCount")?,
+        generated_tile_count: try_key_as(values, "generatedTileCount")?,
+        generated_quest_count: try_key_as(values, "generatedQuestCount")?,
+        surrounded_tiles_count: try_key_as(values, "surroundedTilesCount")?,
+        tiles: filter_none(try_key_as::<GenericList<_>>(values, "tiles")?.into()),
+        tile_stack: filter_none(try_key_as::<GenericList<_>>(values, "tileStack")?.into()),
+        preplaced_tiles: filter_none(
+            try_key_as::<GenericList<_>>(values, "preplacedTiles")?.into(),
+        ),
 
-            pending_locked_challenges: try_key_as::<GenericList<_>>(
-                values,
-                "pendingLockedChallenges",
-            )?
+        pending_locked_challenges: try_key_as::<GenericList<_>>(values, "pendingLockedChallenges")?
             .into(),
 
-            tile_stack_count: try_key_as(values, "tileStackCount")?,
-            file_name: try_key_as::<Maybe<_>>(values, "fileName")?.into_option(),
-            initial_version: try_key_as(values, "initialVersion")?,
-            last_played_version: try_key_as(values, "lastPlayedVersion")?,
-            last_rewarded_step: try_key_as::<GenericList<_>>(values, "lastRewardedStep")?.into(),
-            last_rewarded_score: try_key_as::<GenericList<_>>(values, "lastRewardedScore")?.into(),
-            // onUpdated
-            version: try_key_as(values, "version")?,
-        })
-    }
+        tile_stack_count: try_key_as(values, "tileStackCount")?,
+        file_name: try_key_as::<Maybe<_>>(values, "fileName")?.into_option(),
+        initial_version: try_key_as(values, "initialVersion")?,
+        last_played_version: try_key_as(values, "lastPlayedVersion")?,
+        last_rewarded_step: vec![],
+        last_rewarded_score: vec![],
+        version: try_key_as(values, "version")?,
+    })
+}
+
+fn save_game_v3(values: &[(String, Value)]) -> Result<SaveGame, DecodeError> {
+    Ok(SaveGame {
+        last_rewarded_step: try_key_as::<GenericList<_>>(values, "lastRewardedStep")?.into(),
+        last_rewarded_score: try_key_as::<GenericList<_>>(values, "lastRewardedScore")?.into(),
+        ..save_game_v2(values)?
+    })
 }
 
+/// Why a savegame file could not be loaded.
 #[derive(Debug)]
+pub enum LoadError {
+    Open(PathBuf, std::io::Error),
+    /// The NRBF parser panicked on malformed data.
+    Parse(PathBuf),
+    Decode(PathBuf, Box<DecodeError>),
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Open(path, e) => write!(f, "Failed to open file {}: {e}", path.display()),
+            LoadError::Parse(path) => write!(f, "NRBF parsing panicked for {}", path.display()),
+            LoadError::Decode(path, e) => {
+                write!(f, "Failed to parse savegame {}: {e}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Open(_, e) => Some(e),
+            LoadError::Parse(_) => None,
+            LoadError::Decode(_, e) => Some(e.as_ref()),
+        }
+    }
+}
+
+impl From<LoadError> for String {
+    fn from(error: LoadError) -> Self {
+        error.to_string()
+    }
+}
+
+impl SaveGame {
+    /// Read, parse and decode the savegame file at `path`.
+    pub fn load(path: &Path) -> Result<Self, LoadError> {
+        let mut stream =
+            std::fs::File::open(path).map_err(|e| LoadError::Open(path.to_owned(), e))?;
+
+        // parse_nrbf can panic on malformed data; catch and report.
+        let parsed = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            nrbf_rs::parse_nrbf(&mut stream)
+        }))
+        .map_err(|_| LoadError::Parse(path.to_owned()))?;
+
+        SaveGame::try_from(&parsed).map_err(|e| LoadError::Decode(path.to_owned(), Box::new(e)))
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct GameModeId(pub i32);
 
 impl TryFrom<&Value> for GameModeId {
-    type Error = String;
+    type Error = DecodeError;
 
-    fn try_from(value: &Value) -> Result<Self, String> {
+    fn try_from(value: &Value) -> Result<Self, DecodeError> {
         from_id_object("GameModeId", value).map(Self)
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct QuestTileId(pub i32);
 
 impl TryFrom<&Value> for QuestTileId {
-    type Error = String;
+    type Error = DecodeError;
 
-    fn try_from(value: &Value) -> Result<Self, String> {
+    fn try_from(value: &Value) -> Result<Self, DecodeError> {
         from_id_object("QuestTileId", value).map(Self)
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct QuestId(pub i32);
 
 impl TryFrom<&Value> for QuestId {
-    type Error = String;
+    type Error = DecodeError;
 
-    fn try_from(value: &Value) -> Result<Self, String> {
+    fn try_from(value: &Value) -> Result<Self, DecodeError> {
         from_id_object("Dorfromantik.QuestId", value).map(Self)
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct SegmentTypeId(pub i32);
 
 impl TryFrom<&Value> for SegmentTypeId {
-    type Error = String;
+    type Error = DecodeError;
 
-    fn try_from(value: &Value) -> Result<Self, String> {
+    fn try_from(value: &Value) -> Result<Self, DecodeError> {
         from_id_object("Dorfromantik.SegmentTypeId", value).map(Self)
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct SpecialTileId(pub i32);
 
 impl TryFrom<&Value> for SpecialTileId {
-    type Error = String;
+    type Error = DecodeError;
 
-    fn try_from(value: &Value) -> Result<Self, String> {
+    fn try_from(value: &Value) -> Result<Self, DecodeError> {
         from_id_object("Dorfromantik.SpecialTileId", value).map(Self)
     }
 }
+
+/// Versioned classes and the layout versions that have an adapter.
+pub fn known_layouts() -> Vec<(&'static str, Vec<u32>)> {
+    vec![
+        (SEGMENT_SCHEMA.name, SEGMENT_SCHEMA.versions()),
+        (QUEST_TILE_SCHEMA.name, QUEST_TILE_SCHEMA.versions()),
+        (TILE_SCHEMA.name, TILE_SCHEMA.versions()),
+        (PREPLACED_TILE_SCHEMA.name, PREPLACED_TILE_SCHEMA.versions()),
+        (SAVE_GAME_SCHEMA.name, SAVE_GAME_SCHEMA.versions()),
+    ]
+}
+
+/// Fields the decoders above read, per class. Versioned classes are looked up
+/// by name only, so a newer layout reports just the fields it added. `None`
+/// for classes that are not decoded at all.
+pub fn mapped_fields(class_name: &str) -> Option<&'static [&'static str]> {
+    if class_name.starts_with("System.Collections.Generic.List`1[") {
+        return Some(&["_items", "_size", "_version"]);
+    }
+    Some(match unversioned(class_name) {
+        "Dorfromantik.ChallengeId"
+        | "GroupTypeId"
+        | "GameModeId"
+        | "QuestTileId"
+        | "Dorfromantik.QuestId"
+        | "Dorfromantik.SegmentTypeId"
+        | "Dorfromantik.SpecialTileId" => &["value__"],
+        "SegmentData" => &["groupType", "segmentType", "rotation", "version"],
+        "QuestTileData" => &[
+            "questTileId",
+            "questActive",
+            "questQueueIndex",
+            "targetValue",
+            "questLevel",
+            "questId",
+            "unlockedChallengeId",
+            "version",
+        ],
+        "TileData" => &[
+            "gridPos",
+            "rotation",
+            "seed",
+            "segments",
+            "specialTileId",
+            "questTileData",
+            "version",
+        ],
+        "PreplacedTileData" => &[
+            "sectionGridPosX",
+            "sectionGridPosY",
+            "preplacedTileId",
+            "version",
+        ],
+        "SaveGameData" => &[
+            "gameMode",
+            "level",
+            "score",
+            "perfectPlacements",
+            "questsFulfilled",
+            "questsFailed",
+            "consecutivePerfectFits",
+            "consecutivePlacementsWithoutRotate",
+            "playtime",
+            "biomeSeed",
+            "preplacedTileSeed",
+            "placedTileCount",
+            "generatedTileCount",
+            "generatedQuestCount",
+            "surroundedTilesCount",
+            "tiles",
+            "tileStack",
+            "preplacedTiles",
+            "pendingLockedChallenges",
+            "tileStackCount",
+            "fileName",
+            "initialVersion",
+            "lastPlayedVersion",
+            "lastRewardedStep",
+            "lastRewardedScore",
+            "version",
+        ],
+        _ => return None,
+    })
+}
+
+/// A field that is present in the savegame but not read by the decoders.
+#[derive(Debug, PartialEq)]
+pub struct UnmappedField {
+    pub class_name: String,
+    pub field: String,
+    /// Number of objects that have this field.
+    pub occurrences: usize,
+    /// Number of those where it is not null.
+    pub non_null: usize,
+}
+
+impl std::fmt::Display for UnmappedField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}.{} (set in {}/{})",
+            self.class_name, self.field, self.non_null, self.occurrences
+        )
+    }
+}
+
+/// Every field of a savegame, including class names, `version` fields and
+/// whatever the typed structs don't know about. Read with
+/// [`crate::nrbf_tree::read_nrbf`].
+#[derive(Clone, Debug)]
+pub struct SaveGameTree {
+    pub root: Node,
+}
+
+impl TryFrom<Node> for SaveGameTree {
+    type Error = String;
+
+    fn try_from(root: Node) -> Result<Self, String> {
+        match root.class_name() {
+            Some(class_name) if class_name.starts_with("SaveGameData_") => Ok(Self { root }),
+            class_name => Err(format!("Expected SaveGameData_*; Got {class_name:?}")),
+        }
+    }
+}
+
+impl SaveGameTree {
+    /// Values of the `version` field seen per class.
+    pub fn class_versions(&self) -> BTreeMap<String, BTreeSet<i32>> {
+        let mut versions: BTreeMap<String, BTreeSet<i32>> = BTreeMap::new();
+        self.root.walk(&mut |node| {
+            if let (Some(class_name), Some(Node::Int32(version))) =
+                (node.class_name(), node.field("version"))
+            {
+                versions
+                    .entry(class_name.to_string())
+                    .or_default()
+                    .insert(*version);
+            }
+        });
+        versions
+    }
+
+    /// Fields the typed structs drop, sorted by class and field name. Classes
+    /// without a decoder (e.g. a new `TileData_004`) list all their fields.
+    pub fn unmapped_fields(&self) -> Vec<UnmappedField> {
+        let mut counts: BTreeMap<(&str, &str), (usize, usize)> = BTreeMap::new();
+        self.root.walk(&mut |node| {
+            let Node::Object(class_name, values) = node else {
+                return;
+            };
+            let mapped = mapped_fields(class_name).unwrap_or(&[]);
+            for (key, value) in values {
+                if mapped.contains(&key.as_str()) {
+                    continue;
+                }
+                let (occurrences, non_null) = counts.entry((class_name, key)).or_default();
+                *occurrences += 1;
+                if *value != Node::Null {
+                    *non_null += 1;
+                }
+            }
+        });
+        counts
+            .into_iter()
+            .map(
+                |((class_name, field), (occurrences, non_null))| UnmappedField {
+                    class_name: class_name.to_string(),
+                    field: field.to_string(),
+                    occurrences,
+                    non_null,
+                },
+            )
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nrbf_tree::write_nrbf;
+
+    fn object(class_name: &str, values: Vec<(&str, Node)>) -> Node {
+        Node::Object(
+            class_name.to_string(),
+            values
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    fn segment(version: i32, extra: Option<Node>) -> Node {
+        let id = |class_name| object(class_name, vec![("value__", Node::Int32(1))]);
+        let mut values = vec![
+            ("groupType", id("GroupTypeId")),
+            ("segmentType", id("Dorfromantik.SegmentTypeId")),
+            ("rotation", Node::Int32(0)),
+            ("version", Node::Int32(version)),
+        ];
+        if let Some(extra) = extra {
+            values.push(("color", extra));
+        }
+        object("SegmentData002", values)
+    }
+
+    fn tile(class_name: &str, version: i32, extra: Vec<(&str, Node)>) -> Node {
+        let mut values = vec![
+            ("gridPos", Node::Int32Array(vec![1, 2])),
+            ("rotation", Node::Int32(3)),
+            ("seed", Node::Int32(42)),
+            ("segments", Node::Null),
+            (
+                "specialTileId",
+                object(
+                    "Dorfromantik.SpecialTileId",
+                    vec![("value__", Node::Int32(0))],
+                ),
+            ),
+            ("questTileData", Node::Null),
+            ("version", Node::Int32(version)),
+        ];
+        values.extend(extra);
+        object(class_name, values)
+    }
+
+    /// Turn a synthetic tree into the `Value` the parser would produce for it.
+    fn to_value(node: &Node) -> Value {
+        let mut bytes = Vec::new();
+        write_nrbf(node, &mut bytes).unwrap();
+        nrbf_rs::parse_nrbf(&mut std::io::Cursor::new(&bytes))
+    }
+
+    fn savegame(segments: Vec<Node>) -> SaveGameTree {
+        SaveGameTree {
+            root: object(
+                "SaveGameData_003",
+                vec![
+                    ("version", Node::Int32(3)),
+                    ("segments", Node::Array(segments)),
+                    ("OnUpdated", Node::Null),
+                ],
+            ),
+        }
+    }
+
+    #[test]
+    fn test_mapped_savegame_has_only_known_extras() {
+        let save = savegame(vec![segment(2, None), segment(2, None)]);
+        assert_eq!(
+            save.unmapped_fields(),
+            [
+                UnmappedField {
+                    class_name: "SaveGameData_003".to_string(),
+                    field: "OnUpdated".to_string(),
+                    occurrences: 1,
+                    non_null: 0,
+                },
+                UnmappedField {
+                    class_name: "SaveGameData_003".to_string(),
+                    field: "segments".to_string(),
+                    occurrences: 1,
+                    non_null: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_new_field_is_reported() {
+        let save = savegame(vec![
+            segment(2, Some(Node::Int32(7))),
+            segment(2, Some(Node::Null)),
+        ]);
+        let color = save
+            .unmapped_fields()
+            .into_iter()
+            .find(|field| field.field == "color")
+            .expect("color not reported");
+        assert_eq!(color.class_name, "SegmentData002");
+        assert_eq!((color.occurrences, color.non_null), (2, 1));
+        assert_eq!(color.to_string(), "SegmentData002.color (set in 1/2)");
+    }
+
+    #[test]
+    fn test_unknown_class_reports_all_fields() {
+        let save = savegame(vec![object(
+            "BiomeData_001",
+            vec![("gridPos", Node::Int32Array(vec![0, 0]))],
+        )]);
+        assert!(save
+            .unmapped_fields()
+            .iter()
+            .any(|field| field.class_name == "BiomeData_001" && field.field == "gridPos"));
+    }
+
+    #[test]
+    fn test_newer_layout_reports_added_fields() {
+        let save = savegame(vec![tile(
+            "TileData_004",
+            4,
+            vec![("biome", Node::Int32(1))],
+        )]);
+        let unmapped: Vec<_> = save
+            .unmapped_fields()
+            .into_iter()
+            .filter(|field| field.class_name == "TileData_004")
+            .map(|field| field.field)
+            .collect();
+        assert_eq!(unmapped, ["biome"]);
+    }
+
+    #[test]
+    fn test_class_versions() {
+        let save = savegame(vec![segment(2, None), segment(1, None)]);
+        let versions = save.class_versions();
+        assert_eq!(versions["SaveGameData_003"], BTreeSet::from([3]));
+        assert_eq!(versions["SegmentData002"], BTreeSet::from([1, 2]));
+    }
+
+    #[test]
+    fn test_split_class_version() {
+        assert_eq!(split_class_version("TileData_003"), Some(("TileData", 3)));
+        assert_eq!(
+            split_class_version("SegmentData002"),
+            Some(("SegmentData", 2))
+        );
+        assert_eq!(split_class_version("GroupTypeId"), None);
+        assert_eq!(unversioned("TileData_003"), "TileData");
+        assert_eq!(unversioned("GroupTypeId"), "GroupTypeId");
+    }
+
+    fn pair_v1(values: &[(String, Value)]) -> Result<(i32, i32), DecodeError> {
+        Ok((try_key_as(values, "a")?, 0))
+    }
+
+    fn pair_v2(values: &[(String, Value)]) -> Result<(i32, i32), DecodeError> {
+        Ok((try_key_as(values, "a")?, try_key_as(values, "b")?))
+    }
+
+    const PAIR_SCHEMA: Schema<(i32, i32)> = Schema {
+        name: "PairData",
+        adapters: &[(1, pair_v1), (2, pair_v2)],
+    };
+
+    #[test]
+    fn test_schema_dispatches_on_version() {
+        let old = object(
+            "PairData_001",
+            vec![("a", Node::Int32(1)), ("version", Node::Int32(1))],
+        );
+        let new = object(
+            "PairData_002",
+            vec![
+                ("a", Node::Int32(1)),
+                ("b", Node::Int32(2)),
+                ("version", Node::Int32(2)),
+            ],
+        );
+        assert_eq!(PAIR_SCHEMA.decode(&to_value(&old)), Ok((1, 0)));
+        assert_eq!(PAIR_SCHEMA.decode(&to_value(&new)), Ok((1, 2)));
+    }
+
+    #[test]
+    fn test_schema_version_field_wins_over_suffix() {
+        // A version 1 layout under a bumped class name.
+        let renamed = object(
+            "PairData_002",
+            vec![("a", Node::Int32(1)), ("version", Node::Int32(1))],
+        );
+        assert_eq!(PAIR_SCHEMA.decode(&to_value(&renamed)), Ok((1, 0)));
+
+        let unversioned = object(
+            "PairData002",
+            vec![("a", Node::Int32(1)), ("b", Node::Int32(2))],
+        );
+        assert_eq!(PAIR_SCHEMA.decode(&to_value(&unversioned)), Ok((1, 2)));
+    }
+
+    #[test]
+    fn test_schema_rejects_other_classes() {
+        let other = object("PairInfo_001", vec![("a", Node::Int32(1))]);
+        assert!(PAIR_SCHEMA.decode(&to_value(&other)).is_err());
+    }
+
+    #[test]
+    fn test_newer_tile_layout_uses_newest_adapter() {
+        let value = to_value(&tile("TileData_004", 4, vec![("biome", Node::Int32(1))]));
+        let decoded = Tile::try_from(&value).unwrap();
+        assert_eq!((decoded.s, decoded.t, decoded.rotation), (1, 2, 3));
+        assert_eq!((decoded.seed, decoded.version), (42, 4));
+    }
+
+    #[test]
+    fn test_newer_tile_layout_without_known_fields_fails() {
+        let mut node = tile("TileData_004", 4, vec![]);
+        if let Node::Object(_, values) = &mut node {
+            values.retain(|(key, _)| key != "seed");
+        }
+        let error = Tile::try_from(&to_value(&node)).unwrap_err();
+        assert_eq!(error.path_string(), "seed");
+        assert_eq!(
+            error.to_string(),
+            "seed: expected field, got nothing \
+             [TileData_004 (version 4) is newer than the known layouts [3]]"
+        );
+    }
+
+    #[test]
+    fn test_older_tile_layout_rejected() {
+        let error = Tile::try_from(&to_value(&tile("TileData_002", 2, vec![]))).unwrap_err();
+        assert_eq!(error.expected, "TileData layout [3]");
+        assert_eq!(error.actual.as_deref(), Some("TileData_002"));
+    }
+
+    fn segment_list(segments: Vec<Node>) -> Node {
+        object(
+            "System.Collections.Generic.List`1[[SegmentData002, Assembly-CSharp]]",
+            vec![
+                ("_items", Node::Array(segments)),
+                ("_size", Node::Int32(2)),
+                ("_version", Node::Int32(0)),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_decode_error_path_and_classes() {
+        let mut broken = segment(2, None);
+        broken
+            .set_field(
+                "segmentType",
+                object("GroupTypeId", vec![("value__", Node::Int32(1))]),
+            )
+            .unwrap();
+        let mut node = tile("TileData_003", 3, vec![]);
+        node.set_field("segments", segment_list(vec![segment(2, None), broken]))
+            .unwrap();
+
+        let error = Tile::try_from(&to_value(&node)).unwrap_err();
+        assert_eq!(error.path_string(), "segments[1].segmentType");
+        assert_eq!(error.expected, "Dorfromantik.SegmentTypeId");
+        assert_eq!(error.actual.as_deref(), Some("GroupTypeId"));
+        assert_eq!(
+            error.to_string(),
+            "segments[1].segmentType: expected Dorfromantik.SegmentTypeId, \
+             got GroupTypeId (GroupTypeId { value__ })"
+        );
+    }
+
+    #[test]
+    fn test_decode_error_for_short_grid_pos() {
+        let mut node = tile("TileData_003", 3, vec![]);
+        node.set_field("gridPos", Node::Int32Array(vec![1]))
+            .unwrap();
+        let error = Tile::try_from(&to_value(&node)).unwrap_err();
+        assert_eq!(error.path_string(), "gridPos");
+        assert_eq!(error.expected, "[s, t]");
+    }
+
+    #[test]
+    fn test_decode_error_preview_is_short() {
+        let node = object("Root", vec![("text", Node::String("x".repeat(1000)))]);
+        let value = to_value(&node);
+        let Value::Object(_, values) = &value else {
+            panic!("root is no object");
+        };
+        let error = try_key_as::<i32>(values, "text").unwrap_err();
+        assert_eq!(error.path_string(), "text");
+        assert_eq!(error.actual, None);
+        assert!(error.preview.chars().count() <= PREVIEW_CHARS + 3);
+    }
+
+    #[test]
+    fn test_decode_error_works_with_question_mark() {
+        fn as_string(value: &Value) -> Result<Tile, String> {
+            Ok(Tile::try_from(value)?)
+        }
+        fn as_boxed(value: &Value) -> Result<Tile, Box<dyn std::error::Error>> {
+            Ok(Tile::try_from(value)?)
+        }
+        let value = to_value(&tile("TileData_002", 2, vec![]));
+        assert!(as_string(&value)
+            .unwrap_err()
+            .starts_with("expected TileData layout [3]"));
+        assert!(as_boxed(&value).is_err());
+    }
+
+    #[test]
+    fn test_load_reports_the_failing_step() {
+        let missing = Path::new("/nonexistent/dorfromantische2_rs_test.sav");
+        assert!(matches!(SaveGame::load(missing), Err(LoadError::Open(..))));
+
+        // A valid NRBF stream that holds a tile instead of a savegame.
+        let path = std::env::temp_dir().join("dorfromantische2_rs_load_test.sav");
+        let mut bytes = Vec::new();
+        write_nrbf(&tile("TileData_003", 3, vec![]), &mut bytes).unwrap();
+        std::fs::write(&path, bytes).unwrap();
+        let error = SaveGame::load(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(error, LoadError::Decode(..)));
+        assert!(error.to_string().starts_with("Failed to parse savegame"));
+    }
+
+    #[test]
+    fn test_known_layouts() {
+        assert!(known_layouts().contains(&("TileData", vec![3])));
+        assert!(known_layouts().contains(&("SaveGameData", vec![2, 3])));
+    }
+}
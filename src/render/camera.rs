@@ -1,4 +1,4 @@
-use glam::{UVec2, Vec2};
+use glam::{IVec2, UVec2, Vec2};
 
 use crate::{
     coords::{PixelPos, WorldPos},
@@ -73,20 +73,12 @@ impl Camera {
     pub fn zoom_fit(&mut self, map: &Map) {
         use crate::hex::hex_to_world;
 
-        let offset = map.index_offset;
-        let size = map.index_size;
-
         // Compute world-space bounding box from all occupied tiles.
         let mut world_min = Vec2::new(f32::MAX, f32::MAX);
         let mut world_max = Vec2::new(f32::MIN, f32::MIN);
         let mut any = false;
-        for (key, entry) in map.tile_index.iter().enumerate() {
-            if entry.is_none() {
-                continue;
-            }
-            let col = key as i32 % size.x + offset.x;
-            let row = key as i32 / size.x + offset.y;
-            let w = hex_to_world(HexPos::new(col, row)).0;
+        for pos in map.iter_tile_positions() {
+            let w = hex_to_world(pos).0;
             world_min = world_min.min(w);
             world_max = world_max.max(w);
             any = true;
@@ -128,6 +120,24 @@ impl Camera {
         PixelPos((uv_2 * Vec2::new(1.0, -1.0) + 0.5) * self.size.as_vec2())
     }
 
+    /// Smallest and largest hex coordinates on screen, with one cell of margin for rounding.
+    pub fn visible_hex_bounds(&self) -> (IVec2, IVec2) {
+        let size = self.size.as_vec2();
+        let corners = [
+            Vec2::ZERO,
+            Vec2::new(size.x, 0.0),
+            Vec2::new(0.0, size.y),
+            size,
+        ];
+        let (min, max) = corners
+            .into_iter()
+            .map(|corner| crate::hex::world_to_hex(self.pixel_to_world(PixelPos(corner))).0)
+            .fold((IVec2::MAX, IVec2::MIN), |(min, max), pos| {
+                (min.min(pos), max.max(pos))
+            });
+        (min - IVec2::ONE, max + IVec2::ONE)
+    }
+
     /// Compute pixel coordinates of hex position.
     pub fn hex_to_pixel(&self, pos: HexPos) -> PixelPos {
         self.world_to_pixel(crate::hex::hex_to_world(pos))
@@ -183,6 +193,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_visible_hex_bounds_cover_the_screen() {
+        let mut cam = Camera::default();
+        cam.resize(UVec2::new(800, 600));
+        cam.inv_scale.set(30.0);
+        cam.origin.set(Vec2::new(-40.0, 12.0));
+
+        let (min, max) = cam.visible_hex_bounds();
+        for x in [0.0, 250.0, 800.0] {
+            for y in [0.0, 300.0, 600.0] {
+                let world = cam.pixel_to_world(PixelPos::new(x, y));
+                let pos = crate::hex::world_to_hex(world).0;
+                assert!(
+                    pos.cmpge(min).all() && pos.cmple(max).all(),
+                    "{pos:?} outside {min:?}..{max:?}"
+                );
+            }
+        }
+        // About 30 cells high and 40 wide, so the bounds stay small.
+        assert!((max - min).max_element() < 80, "{min:?}..{max:?}");
+    }
+
     #[test]
     fn test_on_scroll_clamps() {
         let mut cam = Camera::default();
@@ -122,6 +122,7 @@ layout(std140, binding=0) uniform View {
     ivec2 _pad2;
 };
 
+// Only the part of the map in view is uploaded, `index_offset` and `index_size` describe it.
 layout(std140, binding=1) readonly buffer Tiles {
     ivec2 index_offset;
     ivec2 index_size;
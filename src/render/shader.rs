@@ -1,12 +1,13 @@
 use std::time::SystemTime;
 
 use bitfield_struct::bitfield;
+use glam::IVec2;
 
 use crate::{
     best_placements::BestPlacements,
-    data::{Terrain, HEX_SIDES},
+    data::{HexPos, Terrain, HEX_SIDES},
     group_assignments::GroupAssignments,
-    map::Map,
+    map::{Map, CHUNK_SIZE},
     ui::input_state::InputState,
     ui::ui_state::UiState,
 };
@@ -40,30 +41,78 @@ struct PackedTile {
     _pad: u32,
 }
 
-/// Write the map data into a GPU buffer at `ptr`.
+/// The part of the map that is uploaded to the GPU: the chunks in view, clipped to the extents
+/// of the map. The shader indexes it densely, row by row.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TileWindow {
+    pub offset: IVec2,
+    pub size: IVec2,
+}
+
+impl TileWindow {
+    pub fn in_view(camera: &Camera, map: &Map) -> Self {
+        if camera.size.x == 0 || camera.size.y == 0 {
+            return Self::default();
+        }
+        let (min, max) = camera.visible_hex_bounds();
+        // Whole chunks, so that panning only uploads again once another chunk comes into view.
+        let chunk_size = IVec2::splat(CHUNK_SIZE);
+        let lower = (min.div_euclid(chunk_size) * chunk_size).max(map.index_offset);
+        let upper = ((max.div_euclid(chunk_size) + IVec2::ONE) * chunk_size)
+            .min(map.index_offset + map.index_size);
+        Self {
+            offset: lower,
+            size: (upper - lower).max(IVec2::ZERO),
+        }
+    }
+
+    fn tile_count(&self) -> usize {
+        usize::try_from(self.size.x * self.size.y).unwrap()
+    }
+
+    fn position(&self, index: usize) -> HexPos {
+        let index = i32::try_from(index).unwrap();
+        HexPos::new(
+            self.offset.x + index % self.size.x,
+            self.offset.y + index / self.size.x,
+        )
+    }
+
+    fn index_of(&self, pos: HexPos) -> Option<usize> {
+        let local = pos.0 - self.offset;
+        let inside = local.cmpge(IVec2::ZERO).all() && local.cmplt(self.size).all();
+        inside.then(|| usize::try_from(local.y * self.size.x + local.x).unwrap())
+    }
+}
+
+/// Write the tiles of `window` into a GPU buffer at `ptr`.
 ///
 /// # Safety
 ///
-/// `ptr` must point to a buffer of at least `byte_size(map)` bytes,
+/// `ptr` must point to a buffer of at least `byte_size(window)` bytes,
 /// be valid for writes, and be properly aligned for `i32` / `PackedTile`.
 pub(crate) unsafe fn write_map_to(
     ptr: *mut u8,
     map: &Map,
+    window: TileWindow,
     groups: &GroupAssignments,
     best_placements: &BestPlacements,
 ) {
     let header = ptr.cast::<i32>();
 
-    *header.add(0) = map.index_offset.x;
-    *header.add(1) = map.index_offset.y;
-    *header.add(2) = map.index_size.x;
-    *header.add(3) = map.index_size.y;
+    *header.add(0) = window.offset.x;
+    *header.add(1) = window.offset.y;
+    *header.add(2) = window.size.x;
+    *header.add(3) = window.size.y;
 
     let tiles_ptr = header.add(4).cast::<PackedTile>();
-    for (index, maybe_segments) in map.tile_index.iter().enumerate() {
+    for index in 0..window.tile_count() {
         let tile = &mut *tiles_ptr.add(index);
+        let maybe_segments = map
+            .tile_key(window.position(index))
+            .and_then(|key| map.tile_index[key]);
 
-        if let &Some((base_index, segment_count)) = maybe_segments {
+        if let Some((base_index, segment_count)) = maybe_segments {
             // Tile exists.
             for nth_segment in 0..segment_count {
                 let segment_index = base_index + nth_segment;
@@ -92,9 +141,10 @@ pub(crate) unsafe fn write_map_to(
         if rank >= crate::best_placements::MAX_SHOWN_PLACEMENTS {
             break;
         }
-        let tile_index = map.tile_key(score.pos).unwrap();
-        let tile = &mut *tiles_ptr.add(tile_index);
-        tile.placement_rank = rank as i32;
+        if let Some(index) = window.index_of(score.pos) {
+            let tile = &mut *tiles_ptr.add(index);
+            tile.placement_rank = rank as i32;
+        }
     }
 }
 
@@ -103,8 +153,8 @@ pub fn byte_size_for_n_tiles(num_tiles: usize) -> usize {
     IVEC2_ + IVEC2_ + num_tiles.max(1) * TILE_
 }
 
-pub fn byte_size(map: &Map) -> usize {
-    byte_size_for_n_tiles(map.tile_index.len())
+pub fn byte_size(window: TileWindow) -> usize {
+    byte_size_for_n_tiles(window.tile_count())
 }
 
 pub fn view_buffer_size() -> u64 {
@@ -241,14 +291,15 @@ pub fn write_tiles(
     buffer: &Buffer,
     gpu: &Gpu,
     map: &Map,
+    window: TileWindow,
     groups: &GroupAssignments,
     best_placements: &BestPlacements,
 ) {
     let mut buffer_view = buffer.write(gpu);
-    // SAFETY: The buffer was created with shader::byte_size() bytes, which
+    // SAFETY: The buffer was created with shader::byte_size(window) bytes, which
     // accounts for the header and all tiles. write_map_to writes within those bounds.
     unsafe {
         let ptr = buffer_view.as_mut_ptr();
-        write_map_to(ptr, map, groups, best_placements);
+        write_map_to(ptr, map, window, groups, best_placements);
     }
 }
@@ -0,0 +1,397 @@
+//! Replays the placements of a game tile by tile and compares each one with what
+//! `BestPlacements` recommended for that tile on the board as it was. Consecutive saves only
+//! say which tiles were added, so the tiles of one step are replayed in savegame order, and the
+//! board of the first save is taken as given. Quests change with the save after the placements
+//! that changed them, so the tiles of a step are placed on the quests of the step before. Points
+//! are valued with the rules of the latest save.
+
+use serde::Serialize;
+
+use crate::{
+    best_placements::BestPlacements,
+    data::{HexPos, Rotation, HEX_SIDES},
+    group_assignments::GroupAssignments,
+    hex::offset_to_hex,
+    map::Map,
+    raw_data::{SaveGame, Tile},
+    score::{self, ScoreRules},
+    scorer::PlacementScorer,
+    tile_frequency::TileFrequencies,
+    timeline::Timeline,
+};
+
+/// Bumped when a field is removed or changes its meaning. New fields keep the version.
+pub const REVIEW_VERSION: u32 = 1;
+
+/// How one placed tile compares with the recommendations.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlacementReview {
+    /// Timeline step the tile was placed in.
+    pub step: usize,
+    pub pos: HexPos,
+    pub rotation: Rotation,
+    /// Rank of the position among all positions, 1 is the top recommendation. `None` if
+    /// `BestPlacements` did not consider the position.
+    pub position_rank: Option<usize>,
+    /// Rank of the rotation among the legal rotations at the position.
+    pub rotation_rank: Option<usize>,
+    /// Positions that were ranked.
+    pub candidates: usize,
+    /// The top recommendation.
+    pub top: Option<(HexPos, Rotation)>,
+    /// The placement is the top recommendation, or a rotation the scorer ties with it.
+    pub followed: bool,
+    pub points: i32,
+    pub expected_points: f32,
+    pub top_points: i32,
+    pub top_expected_points: f32,
+    pub quests_completed: usize,
+    /// Most quests any legal placement of the tile would have completed.
+    pub quests_possible: usize,
+}
+
+impl PlacementReview {
+    fn of(
+        map: &Map,
+        groups: &GroupAssignments,
+        best: &BestPlacements,
+        step: usize,
+        tile: &Tile,
+    ) -> Self {
+        let pos = offset_to_hex(tile.s, tile.t);
+        let rotation = rotation_of(tile);
+        let rules = best.rules();
+        let chosen = score::simulate(map, groups, &map.next_tile, pos, rotation, rules);
+        let top = best.iter_best().next();
+        let quests_possible = best
+            .filter(|score| score.points.quests_completed() > 0)
+            .iter()
+            .map(|score| score.points.quests_completed())
+            .fold(chosen.quests_completed(), usize::max);
+        Self {
+            step,
+            pos,
+            rotation,
+            position_rank: best
+                .iter_best()
+                .position(|score| score.pos == pos)
+                .map(|index| index + 1),
+            rotation_rank: best
+                .by_position(pos)
+                .iter()
+                .position(|score| score.rotation == rotation)
+                .map(|index| index + 1),
+            candidates: best.iter_best().count(),
+            top: top.map(|top| (top.pos, top.rotation)),
+            followed: top.is_some_and(|top| top.pos == pos)
+                && best
+                    .tied_rotations(pos)
+                    .iter()
+                    .any(|score| score.rotation == rotation),
+            points: chosen.points,
+            expected_points: chosen.expected(rules),
+            top_points: top.map_or(0, |top| top.points.points),
+            top_expected_points: top.map_or(0.0, |top| top.expected_points),
+            quests_completed: chosen.quests_completed(),
+            quests_possible,
+        }
+    }
+
+    /// Expected points the top recommendation would have made on top of this placement.
+    /// Negative when the placement beat it.
+    pub fn expected_gap(&self) -> f32 {
+        self.top_expected_points - self.expected_points
+    }
+
+    pub fn missed_quests(&self) -> usize {
+        self.quests_possible - self.quests_completed
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ReviewSummary {
+    pub placements: usize,
+    pub followed: usize,
+    /// Placements among the top three positions.
+    pub top_three: usize,
+    /// Placements at a position `BestPlacements` did not consider.
+    pub unranked: usize,
+    /// Over the ranked placements.
+    pub mean_position_rank: Option<f32>,
+    pub points: i32,
+    pub top_points: i32,
+    pub expected_gap: f32,
+    pub quests_completed: usize,
+    pub missed_quests: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Review {
+    /// In the order the tiles were placed.
+    pub placements: Vec<PlacementReview>,
+}
+
+/// The placed tile as the next tile of the stack, which is not rotated yet.
+fn unrotated(tile: &Tile) -> Tile {
+    Tile {
+        rotation: 0,
+        ..tile.clone()
+    }
+}
+
+/// Give the tiles of `state` the quests of step `to` instead of those of step `from`.
+fn apply_quest_changes(timeline: &Timeline, state: &mut SaveGame, from: usize, to: usize) {
+    for step in &timeline.steps[from + 1..=to] {
+        step.apply_quest_changes(&mut state.tiles);
+    }
+}
+
+fn rotation_of(tile: &Tile) -> Rotation {
+    tile.rotation.rem_euclid(HEX_SIDES as i32) as Rotation
+}
+
+impl Review {
+    /// Review every tile placed after the first step of `timeline`, ranking placements with
+    /// `scorer`.
+    pub fn of(timeline: &Timeline, scorer: Box<dyn PlacementScorer>) -> Self {
+        let turns: Vec<(usize, &Tile)> = timeline
+            .steps
+            .iter()
+            .enumerate()
+            .skip(1)
+            .flat_map(|(step, timeline_step)| {
+                timeline_step.added.iter().map(move |tile| (step, tile))
+            })
+            .collect();
+        let (Some(mut state), Some(&(first_step, first))) =
+            (timeline.savegame_at(0), turns.first())
+        else {
+            return Self::default();
+        };
+
+        // The board before each placement, with the placed tile as the next tile.
+        apply_quest_changes(timeline, &mut state, 0, first_step - 1);
+        state.tile_stack = vec![unrotated(first)];
+        let mut map = Map::from(&state);
+        let mut groups = GroupAssignments::from(&map);
+        let mut freqs = TileFrequencies::from_map(&map);
+        let rules = timeline.latest().map(ScoreRules::from).unwrap_or_default();
+        let mut best = BestPlacements::compute_with(&map, &groups, &freqs, scorer, rules);
+
+        let mut placements = Vec::with_capacity(turns.len());
+        for (index, &(step, tile)) in turns.iter().enumerate() {
+            if index > 0 {
+                let (previous_step, previous) = turns[index - 1];
+                state.tiles.push(previous.clone());
+                apply_quest_changes(timeline, &mut state, previous_step - 1, step - 1);
+                state.tile_stack = vec![unrotated(tile)];
+                let Some(update) = map.extend_from(&state) else {
+                    break;
+                };
+                let changed: Vec<HexPos> = update.changed_positions().collect();
+                groups.update(&map, &changed);
+                let changes = freqs.update(&map, &update);
+                best.update(&map, &groups, &freqs, &changes);
+            }
+            placements.push(PlacementReview::of(&map, &groups, &best, step, tile));
+        }
+        Self { placements }
+    }
+
+    pub fn summary(&self) -> ReviewSummary {
+        let ranks: Vec<usize> = self
+            .placements
+            .iter()
+            .filter_map(|review| review.position_rank)
+            .collect();
+        ReviewSummary {
+            placements: self.placements.len(),
+            followed: self
+                .placements
+                .iter()
+                .filter(|review| review.followed)
+                .count(),
+            top_three: ranks.iter().filter(|&&rank| rank <= 3).count(),
+            unranked: self.placements.len() - ranks.len(),
+            mean_position_rank: (!ranks.is_empty())
+                .then(|| ranks.iter().sum::<usize>() as f32 / ranks.len() as f32),
+            points: self.placements.iter().map(|review| review.points).sum(),
+            top_points: self.placements.iter().map(|review| review.top_points).sum(),
+            expected_gap: self
+                .placements
+                .iter()
+                .map(PlacementReview::expected_gap)
+                .sum(),
+            quests_completed: self
+                .placements
+                .iter()
+                .map(|review| review.quests_completed)
+                .sum(),
+            missed_quests: self
+                .placements
+                .iter()
+                .map(PlacementReview::missed_quests)
+                .sum(),
+        }
+    }
+
+    /// The summary and every placement as a versioned JSON document. Positions are axial
+    /// `[x, y]` pairs.
+    pub fn to_json(&self) -> String {
+        let export = ReviewExport {
+            version: REVIEW_VERSION,
+            summary: self.summary(),
+            placements: self.placements.iter().map(PlacementExport::from).collect(),
+        };
+        serde_json::to_string_pretty(&export).expect("The review only holds plain data")
+    }
+
+    /// One row per placement. Cells of missing ranks are empty.
+    pub fn to_csv(&self) -> String {
+        let optional =
+            |value: Option<usize>| value.map_or(String::new(), |value| value.to_string());
+        let mut csv = String::from(
+            "step,x,y,rotation,position_rank,rotation_rank,candidates,followed,top_x,top_y,\
+             top_rotation,points,top_points,expected_gap,quests_completed,missed_quests\n",
+        );
+        for review in &self.placements {
+            let top = review.top.map_or(",,".to_string(), |(pos, rotation)| {
+                format!("{},{},{rotation}", pos.x(), pos.y())
+            });
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{top},{},{},{:.1},{},{}\n",
+                review.step,
+                review.pos.x(),
+                review.pos.y(),
+                review.rotation,
+                optional(review.position_rank),
+                optional(review.rotation_rank),
+                review.candidates,
+                review.followed,
+                review.points,
+                review.top_points,
+                review.expected_gap(),
+                review.quests_completed,
+                review.missed_quests(),
+            ));
+        }
+        csv
+    }
+}
+
+#[derive(Serialize)]
+struct ReviewExport {
+    version: u32,
+    summary: ReviewSummary,
+    placements: Vec<PlacementExport>,
+}
+
+#[derive(Serialize)]
+struct PlacementExport {
+    step: usize,
+    pos: [i32; 2],
+    rotation: Rotation,
+    position_rank: Option<usize>,
+    rotation_rank: Option<usize>,
+    candidates: usize,
+    top: Option<([i32; 2], Rotation)>,
+    followed: bool,
+    points: i32,
+    expected_points: f32,
+    top_points: i32,
+    top_expected_points: f32,
+    expected_gap: f32,
+    quests_completed: usize,
+    missed_quests: usize,
+}
+
+impl From<&PlacementReview> for PlacementExport {
+    fn from(review: &PlacementReview) -> Self {
+        Self {
+            step: review.step,
+            pos: [review.pos.x(), review.pos.y()],
+            rotation: review.rotation,
+            position_rank: review.position_rank,
+            rotation_rank: review.rotation_rank,
+            candidates: review.candidates,
+            top: review
+                .top
+                .map(|(pos, rotation)| ([pos.x(), pos.y()], rotation)),
+            followed: review.followed,
+            points: review.points,
+            expected_points: review.expected_points,
+            top_points: review.top_points,
+            top_expected_points: review.top_expected_points,
+            expected_gap: review.expected_gap(),
+            quests_completed: review.quests_completed,
+            missed_quests: review.missed_quests(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn review(
+        position_rank: Option<usize>,
+        expected_points: f32,
+        quests: (usize, usize),
+    ) -> PlacementReview {
+        PlacementReview {
+            step: 1,
+            pos: HexPos::new(2, -1),
+            rotation: 3,
+            position_rank,
+            rotation_rank: position_rank.map(|_| 1),
+            candidates: 12,
+            top: Some((HexPos::new(0, 4), 0)),
+            followed: position_rank == Some(1),
+            points: 20,
+            expected_points,
+            top_points: 30,
+            top_expected_points: 60.0,
+            quests_completed: quests.0,
+            quests_possible: quests.1,
+        }
+    }
+
+    #[test]
+    fn test_summary_adds_up_placements() {
+        let review = Review {
+            placements: vec![
+                review(Some(1), 60.0, (1, 1)),
+                review(Some(5), 20.0, (0, 1)),
+                review(None, 30.0, (0, 0)),
+            ],
+        };
+        let summary = review.summary();
+        assert_eq!(summary.placements, 3);
+        assert_eq!(summary.followed, 1);
+        assert_eq!(summary.top_three, 1);
+        assert_eq!(summary.unranked, 1);
+        assert_eq!(summary.mean_position_rank, Some(3.0));
+        assert_eq!(summary.points, 60);
+        assert_eq!(summary.top_points, 90);
+        assert_eq!(summary.expected_gap, 70.0);
+        assert_eq!(summary.quests_completed, 1);
+        assert_eq!(summary.missed_quests, 1);
+        assert_eq!(Review::default().summary().mean_position_rank, None);
+    }
+
+    #[test]
+    fn test_csv_leaves_missing_ranks_empty() {
+        let review = Review {
+            placements: vec![review(None, 30.0, (0, 0))],
+        };
+        let csv = review.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0].split(',').count(),
+            lines[1].split(',').count(),
+            "{csv}"
+        );
+        assert_eq!(lines[1], "1,2,-1,3,,,12,false,0,4,0,20,30,30.0,0,0");
+    }
+}
@@ -0,0 +1,476 @@
+//! Monte Carlo rollouts. The next tile is placed at a candidate position, then tiles are drawn
+//! from the tile frequencies of the game and a policy places them for a few turns. The average
+//! outcome rates the candidate by what it leads to instead of by the fit chance of its cell.
+//!
+//! Each rollout draws its tiles from its own seeded RNG, so a seed gives the same result on any
+//! number of threads. All candidates see the same draws, which keeps them comparable.
+
+use std::cmp::Reverse;
+
+use rand::{distributions::WeightedIndex, prelude::Distribution, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+
+use crate::{
+    best_placements::{count_matches, splits_empty_neighbors, BestPlacements, FitChanceCache},
+    data::{EdgeProfile, HexPos, Rotation, Segment, Terrain, HEX_SIDES},
+    group_assignments::GroupAssignments,
+    lookahead::{distinct_rotations, sorted, step_value, Board, Game, StepValue},
+    map::Map,
+    tile_frequency::TileFrequencies,
+};
+
+pub const DEFAULT_CANDIDATES: usize = 5;
+pub const DEFAULT_TURNS: usize = 6;
+pub const DEFAULT_ROLLOUTS: usize = 32;
+pub const DEFAULT_SEED: u64 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RolloutConfig {
+    /// How many of the best placements are rated.
+    pub candidates: usize,
+    /// Tiles placed after the candidate in each rollout.
+    pub turns: usize,
+    pub rollouts: usize,
+    pub seed: u64,
+}
+
+impl Default for RolloutConfig {
+    fn default() -> Self {
+        Self {
+            candidates: DEFAULT_CANDIDATES,
+            turns: DEFAULT_TURNS,
+            rollouts: DEFAULT_ROLLOUTS,
+            seed: DEFAULT_SEED,
+        }
+    }
+}
+
+/// A legal placement of the drawn tile during a rollout.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Move {
+    pub pos: HexPos,
+    pub rotation: Rotation,
+    pub matching_edges: u8,
+    /// Chance that a random tile fits the cell, before anything is placed there.
+    pub fit_chance: f32,
+}
+
+/// Where the drawn tiles of a rollout go.
+pub trait PlacementPolicy: Sync {
+    fn name(&self) -> &str;
+
+    /// Index into `moves` of the move to play for the tile of `turn`. `moves` is never empty
+    /// and always in the same order for the same board.
+    fn choose(&self, turn: &mut Turn, moves: &[Move]) -> usize;
+}
+
+/// The game of a rollout when a tile is to be placed.
+pub struct Turn<'a, 'b> {
+    game: &'b mut Game<'a>,
+    tile: &'b [Segment],
+    freqs: &'a TileFrequencies,
+    fit_cache: &'b mut FitChanceCache,
+}
+
+/// Plays the move the `DefaultScorer` ranking of `BestPlacements` puts first, on a copy of the
+/// map with the tiles of the rollout placed. The default policy.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RankedPolicy;
+
+impl PlacementPolicy for RankedPolicy {
+    fn name(&self) -> &str {
+        "Ranked"
+    }
+
+    fn choose(&self, turn: &mut Turn, moves: &[Move]) -> usize {
+        let candidates: Vec<(HexPos, Rotation)> =
+            moves.iter().map(|play| (play.pos, play.rotation)).collect();
+        let (map, groups) = turn.game.with_next_tile(turn.tile);
+        BestPlacements::default_best_of(map, groups, turn.freqs, turn.fit_cache, &candidates)
+            .and_then(|best| candidates.iter().position(|&candidate| candidate == best))
+            .unwrap_or(0)
+    }
+}
+
+/// Plays the cell that is hardest to fill, then the most matching edges. A cheap stand-in for
+/// `RankedPolicy` that never copies the map.
+///
+/// This is only the first and a later key of the `DefaultScorer` ranking. Connection
+/// difficulty, crowding, the neighbor bonus and quests are not looked at, so a rollout can play
+/// a move the ranking would put further down.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GreedyPolicy;
+
+impl PlacementPolicy for GreedyPolicy {
+    fn name(&self) -> &str {
+        "Greedy"
+    }
+
+    fn choose(&self, _: &mut Turn, moves: &[Move]) -> usize {
+        (0..moves.len())
+            .min_by(|&a, &b| {
+                let (a, b) = (&moves[a], &moves[b]);
+                a.fit_chance
+                    .total_cmp(&b.fit_chance)
+                    .then(b.matching_edges.cmp(&a.matching_edges))
+            })
+            .unwrap_or(0)
+    }
+}
+
+/// Plays the most matching edges, then the cell that is hardest to fill.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MatchingPolicy;
+
+impl PlacementPolicy for MatchingPolicy {
+    fn name(&self) -> &str {
+        "Matching"
+    }
+
+    fn choose(&self, _: &mut Turn, moves: &[Move]) -> usize {
+        (0..moves.len())
+            .min_by(|&a, &b| {
+                let (a, b) = (&moves[a], &moves[b]);
+                b.matching_edges
+                    .cmp(&a.matching_edges)
+                    .then(a.fit_chance.total_cmp(&b.fit_chance))
+            })
+            .unwrap_or(0)
+    }
+}
+
+/// What one rollout of a candidate led to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RolloutOutcome {
+    /// Sum of the `StepValue` totals, including the candidate itself.
+    pub value: i32,
+    pub perfect: usize,
+    pub dead_cells: usize,
+    /// A drawn tile fit nowhere, so the rollout stopped early.
+    pub stuck: bool,
+}
+
+impl RolloutOutcome {
+    fn add(&mut self, step: StepValue) {
+        self.value += step.total();
+        self.perfect += usize::from(step.perfect);
+        self.dead_cells += usize::from(step.dead_cells);
+    }
+}
+
+/// Averages over all rollouts of one candidate.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RolloutResult {
+    pub pos: HexPos,
+    pub rotation: Rotation,
+    /// Rank of the candidate in `BestPlacements`.
+    pub rank: usize,
+    pub fit_chance: f32,
+    pub mean_value: f32,
+    pub mean_perfect: f32,
+    pub mean_dead_cells: f32,
+    /// Share of rollouts that got stuck.
+    pub stuck_rate: f32,
+}
+
+impl RolloutResult {
+    fn from_outcomes(
+        rank: usize,
+        pos: HexPos,
+        rotation: Rotation,
+        fit_chance: f32,
+        outcomes: &[RolloutOutcome],
+    ) -> Self {
+        let mean = |value: fn(&RolloutOutcome) -> f32| {
+            outcomes.iter().map(value).sum::<f32>() / outcomes.len().max(1) as f32
+        };
+        Self {
+            pos,
+            rotation,
+            rank,
+            fit_chance,
+            mean_value: mean(|outcome| outcome.value as f32),
+            mean_perfect: mean(|outcome| outcome.perfect as f32),
+            mean_dead_cells: mean(|outcome| outcome.dead_cells as f32),
+            stuck_rate: mean(|outcome| f32::from(u8::from(outcome.stuck))),
+        }
+    }
+}
+
+/// The best placements, rated by rollouts.
+#[derive(Clone, Debug, Default)]
+pub struct Rollouts {
+    /// Best mean value first. Ties keep the order of `BestPlacements`.
+    pub results: Vec<RolloutResult>,
+}
+
+impl Rollouts {
+    pub fn run(
+        map: &Map,
+        groups: &GroupAssignments,
+        freqs: &TileFrequencies,
+        placements: &BestPlacements,
+        config: RolloutConfig,
+        policy: &dyn PlacementPolicy,
+    ) -> Self {
+        let candidates: Vec<_> = placements.iter_best().take(config.candidates).collect();
+        let Some(sampler) = Sampler::new(freqs) else {
+            return Self::default();
+        };
+        let rollout = Rollout {
+            map,
+            groups,
+            freqs,
+            frontier: sorted(groups.possible_placements.iter().copied()),
+            sampler,
+            policy,
+            config,
+        };
+
+        let jobs: Vec<(usize, usize)> = (0..candidates.len())
+            .flat_map(|candidate| (0..config.rollouts).map(move |index| (candidate, index)))
+            .collect();
+        let outcomes: Vec<RolloutOutcome> = jobs
+            .par_iter()
+            .map_init(FitChanceCache::default, |fit_cache, &(candidate, index)| {
+                let score = candidates[candidate];
+                rollout.play(score.pos, score.rotation, index, fit_cache)
+            })
+            .collect();
+
+        let mut results: Vec<RolloutResult> = candidates
+            .iter()
+            .zip(outcomes.chunks(config.rollouts.max(1)))
+            .enumerate()
+            .map(|(rank, (score, outcomes))| {
+                RolloutResult::from_outcomes(
+                    rank,
+                    score.pos,
+                    score.rotation,
+                    score.fit_chance,
+                    outcomes,
+                )
+            })
+            .collect();
+        results.sort_by(|a, b| {
+            b.mean_value
+                .total_cmp(&a.mean_value)
+                .then(a.rank.cmp(&b.rank))
+        });
+        Self { results }
+    }
+
+    pub fn best(&self) -> Option<&RolloutResult> {
+        self.results.first()
+    }
+
+    /// Whether the rollouts prefer another placement than the top ranked one.
+    pub fn differs_from_ranking(&self) -> bool {
+        self.best().is_some_and(|best| best.rank != 0)
+    }
+}
+
+/// Draws tiles in proportion to how often their edge pattern was placed.
+struct Sampler<'a> {
+    tiles: Vec<&'a [Segment]>,
+    weights: WeightedIndex<usize>,
+}
+
+impl<'a> Sampler<'a> {
+    fn new(freqs: &'a TileFrequencies) -> Option<Self> {
+        // Entries with the same count come in hash order, so fix the order first.
+        let mut entries: Vec<(usize, [Terrain; HEX_SIDES], &[Segment])> = freqs
+            .entries
+            .iter()
+            .map(|entry| {
+                let edges = std::array::from_fn(|side| entry.edges.0.at_index(side));
+                (entry.count, edges, entry.segments.as_slice())
+            })
+            .collect();
+        entries.sort_by_key(|&(count, edges, _)| (Reverse(count), edges));
+        let weights = WeightedIndex::new(entries.iter().map(|&(count, _, _)| count)).ok()?;
+        Some(Self {
+            tiles: entries.into_iter().map(|(_, _, tile)| tile).collect(),
+            weights,
+        })
+    }
+
+    fn draw(&self, rng: &mut ChaCha8Rng) -> &'a [Segment] {
+        self.tiles[self.weights.sample(rng)]
+    }
+}
+
+struct Rollout<'a> {
+    map: &'a Map,
+    groups: &'a GroupAssignments,
+    freqs: &'a TileFrequencies,
+    frontier: Vec<HexPos>,
+    sampler: Sampler<'a>,
+    policy: &'a dyn PlacementPolicy,
+    config: RolloutConfig,
+}
+
+impl<'a> Rollout<'a> {
+    /// Legal placements of `profile` on `board`, in a fixed order.
+    fn moves(
+        &self,
+        board: &Board,
+        profile: &EdgeProfile,
+        fit_cache: &mut FitChanceCache,
+    ) -> Vec<Move> {
+        let rotations = distinct_rotations(profile);
+        let mut moves = Vec::new();
+        for pos in board.candidates(&self.frontier) {
+            let occupied =
+                std::array::from_fn(|side| !board.is_free(Map::neighbor_pos_of(pos, side)));
+            if splits_empty_neighbors(occupied) {
+                continue;
+            }
+            let constraints = board.constraints_at(pos);
+            let (fit_chance, _) = fit_cache.get(self.freqs, &constraints);
+            for (rotation, rotated) in &rotations {
+                let (matching_edges, legal) = count_matches(rotated, &constraints);
+                if legal {
+                    moves.push(Move {
+                        pos,
+                        rotation: *rotation,
+                        matching_edges,
+                        fit_chance,
+                    });
+                }
+            }
+        }
+        moves
+    }
+
+    /// Place the next tile at `pos` and play `config.turns` more tiles: first the known ones,
+    /// then drawn ones.
+    fn play(
+        &self,
+        pos: HexPos,
+        rotation: Rotation,
+        index: usize,
+        fit_cache: &mut FitChanceCache,
+    ) -> RolloutOutcome {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.config.seed);
+        rng.set_stream(index as u64);
+        let mut board = Board::new(self.map);
+        let mut game = Game::new(self.map, self.groups);
+        let mut outcome = RolloutOutcome::default();
+
+        let next = EdgeProfile::from_segments(&self.map.next_tile).rotated(rotation);
+        if let Some(step) = step_value(&board, pos, &next, self.freqs, fit_cache) {
+            outcome.add(step);
+        }
+        board.placed.push((pos, next));
+        game.place(pos, &self.map.next_tile, rotation);
+
+        for turn in 1..=self.config.turns {
+            let tile = match self.map.tile_queue.get(turn) {
+                Some(tile) => tile.as_slice(),
+                None => self.sampler.draw(&mut rng),
+            };
+            let profile = EdgeProfile::from_segments(tile);
+            let moves = self.moves(&board, &profile, fit_cache);
+            if moves.is_empty() {
+                outcome.stuck = true;
+                break;
+            }
+            let mut turn = Turn {
+                game: &mut game,
+                tile,
+                freqs: self.freqs,
+                fit_cache,
+            };
+            let chosen = moves[self.policy.choose(&mut turn, &moves).min(moves.len() - 1)];
+            let rotated = profile.rotated(chosen.rotation);
+            if let Some(step) = step_value(&board, chosen.pos, &rotated, self.freqs, fit_cache) {
+                outcome.add(step);
+            }
+            board.placed.push((chosen.pos, rotated));
+            game.place(chosen.pos, tile, chosen.rotation);
+        }
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(pos: i32, matching_edges: u8, fit_chance: f32) -> Move {
+        Move {
+            pos: HexPos::new(pos, 0),
+            rotation: 0,
+            matching_edges,
+            fit_chance,
+        }
+    }
+
+    fn choose(policy: &dyn PlacementPolicy, moves: &[Move]) -> usize {
+        let (map, groups) = (Map::default(), GroupAssignments::default());
+        let freqs = TileFrequencies::default();
+        let mut game = Game::new(&map, &groups);
+        let mut fit_cache = FitChanceCache::default();
+        let mut turn = Turn {
+            game: &mut game,
+            tile: &[],
+            freqs: &freqs,
+            fit_cache: &mut fit_cache,
+        };
+        policy.choose(&mut turn, moves)
+    }
+
+    #[test]
+    fn test_policies_pick_their_preference() {
+        let moves = [
+            play(0, 2, 0.5),
+            play(1, 5, 0.5),
+            play(2, 1, 0.1),
+            play(3, 5, 0.3),
+        ];
+        assert_eq!(choose(&GreedyPolicy, &moves), 2);
+        assert_eq!(choose(&MatchingPolicy, &moves), 3);
+        // Ties go to the first move.
+        assert_eq!(
+            choose(&GreedyPolicy, &[play(0, 1, 0.2), play(1, 1, 0.2)]),
+            0
+        );
+    }
+
+    #[test]
+    fn test_results_average_the_outcomes() {
+        let outcome = |value, stuck| RolloutOutcome {
+            value,
+            perfect: 1,
+            dead_cells: 0,
+            stuck,
+        };
+        let result = RolloutResult::from_outcomes(
+            0,
+            HexPos::new(0, 0),
+            0,
+            0.5,
+            &[outcome(4, false), outcome(8, true)],
+        );
+        assert_eq!(result.mean_value, 6.0);
+        assert_eq!(result.mean_perfect, 1.0);
+        assert_eq!(result.stuck_rate, 0.5);
+    }
+
+    #[test]
+    fn test_run_without_frequencies_is_empty() {
+        let map = Map::default();
+        let rollouts = Rollouts::run(
+            &map,
+            &GroupAssignments::default(),
+            &TileFrequencies::default(),
+            &BestPlacements::default(),
+            RolloutConfig::default(),
+            &GreedyPolicy,
+        );
+        assert!(rollouts.results.is_empty());
+        assert!(!rollouts.differs_from_ranking());
+    }
+}
@@ -0,0 +1,153 @@
+//! The save slots in the `Saves` directory of the game. The game only writes the slot that is
+//! being played, so the most recently written slot is the current game.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::raw_data::SaveGame;
+
+pub const SLOT_EXTENSION: &str = "sav";
+
+/// The `Saves` directory, relative to the Windows user directory.
+const SAVES_SUBDIR: &str = "AppData/LocalLow/Toukana Interactive/Dorfromantik/Saves";
+
+/// What the picker shows of a slot.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SlotInfo {
+    pub level: i32,
+    pub score: i32,
+    pub placed_tile_count: i32,
+    pub last_played_version: String,
+}
+
+impl From<&SaveGame> for SlotInfo {
+    fn from(savegame: &SaveGame) -> Self {
+        Self {
+            level: savegame.level,
+            score: savegame.score,
+            placed_tile_count: savegame.placed_tile_count,
+            last_played_version: savegame.last_played_version.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SaveSlot {
+    pub path: PathBuf,
+    pub modified: SystemTime,
+    /// Why the slot could not be loaded otherwise.
+    pub info: Result<SlotInfo, String>,
+}
+
+impl SaveSlot {
+    pub fn name(&self) -> String {
+        self.path
+            .file_stem()
+            .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned())
+    }
+}
+
+/// Slot files in `dir` with the time they were written, most recent first.
+pub fn slot_files(dir: &Path) -> io::Result<Vec<(PathBuf, SystemTime)>> {
+    let mut slots = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_file() || path.extension().is_none_or(|ext| ext != SLOT_EXTENSION) {
+            continue;
+        }
+        if let Ok(modified) = path.metadata().and_then(|metadata| metadata.modified()) {
+            slots.push((path, modified));
+        }
+    }
+    slots.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    Ok(slots)
+}
+
+/// The slot the game wrote last.
+pub fn latest_slot(dir: &Path) -> Option<(PathBuf, SystemTime)> {
+    slot_files(dir).ok()?.into_iter().next()
+}
+
+/// Every slot in `dir`, loaded, most recently written first.
+pub fn scan(dir: &Path) -> io::Result<Vec<SaveSlot>> {
+    Ok(slot_files(dir)?
+        .into_iter()
+        .map(|(path, modified)| {
+            let info = SaveGame::load(&path)
+                .map(|savegame| SlotInfo::from(&savegame))
+                .map_err(|error| error.to_string());
+            SaveSlot {
+                path,
+                modified,
+                info,
+            }
+        })
+        .collect())
+}
+
+/// The `Saves` directory of a Windows install, or of the first Proton prefix in the default
+/// Steam library that has one.
+pub fn default_saves_dir() -> Option<PathBuf> {
+    let home = dirs::home_dir()?;
+    let windows = home.join(SAVES_SUBDIR);
+    if windows.is_dir() {
+        return Some(windows);
+    }
+    let mut prefixes: Vec<PathBuf> =
+        std::fs::read_dir(home.join(".local/share/Steam/steamapps/compatdata"))
+            .ok()?
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .collect();
+    prefixes.sort();
+    prefixes
+        .into_iter()
+        .map(|prefix| {
+            prefix
+                .join("pfx/drive_c/users/steamuser")
+                .join(SAVES_SUBDIR)
+        })
+        .find(|dir| dir.is_dir())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_slot_files_are_newest_first() {
+        let dir = std::env::temp_dir().join("dorfromantische2_rs_test_slots");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("backup.sav")).unwrap();
+        let now = SystemTime::now();
+        for (name, age) in [
+            ("a.sav", 30),
+            ("b.sav", 10),
+            ("c.sav", 20),
+            ("notes.txt", 0),
+        ] {
+            let file = std::fs::File::create(dir.join(name)).unwrap();
+            file.set_modified(now - Duration::from_secs(age)).unwrap();
+        }
+
+        let names: Vec<String> = slot_files(&dir)
+            .unwrap()
+            .into_iter()
+            .map(|(path, _)| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, ["b.sav", "c.sav", "a.sav"]);
+        assert_eq!(latest_slot(&dir).unwrap().0, dir.join("b.sav"));
+
+        // Empty files are listed with the reason they cannot be loaded.
+        let slots = scan(&dir).unwrap();
+        assert_eq!(slots.len(), 3);
+        assert_eq!(slots[0].name(), "b");
+        assert!(slots.iter().all(|slot| slot.info.is_err()));
+
+        assert!(slot_files(&dir.join("missing")).is_err());
+        assert!(latest_slot(&dir.join("missing")).is_none());
+    }
+}
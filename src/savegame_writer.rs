@@ -0,0 +1,515 @@
+//! Write an edited [`SaveGame`] back into the savegame it was read from.
+//!
+//! The decoded structs only hold the fields we care about, so the writer keeps
+//! the full object tree of the original file and patches the known fields into
+//! it. Everything else (`customModeData`, `activeChallenges`, ...) is written
+//! back unchanged, including the `screenshot` byte array.
+
+use crate::nrbf_tree::{write_nrbf, Node, GAME_ASSEMBLY};
+use crate::raw_data::{
+    unversioned, ChallengeId, GameModeId, GroupTypeId, PreplacedTile, QuestId, QuestTile,
+    QuestTileId, SaveGame, Segment, SegmentTypeId, SpecialTileId, Tile,
+};
+use std::collections::HashMap;
+use std::io::Write;
+
+/// First instance of every class in the savegame, by class name without the
+/// layout version. New list entries (e.g. an added tile) start out as a copy
+/// of these, so they get the layout of the savegame.
+struct Templates(HashMap<String, Node>);
+
+impl Templates {
+    fn collect(root: &Node) -> Self {
+        let mut templates = HashMap::new();
+        root.walk(&mut |node| {
+            if let Node::Object(class_name, _) = node {
+                templates
+                    .entry(unversioned(class_name).to_string())
+                    .or_insert_with(|| node.clone());
+            }
+        });
+        Self(templates)
+    }
+
+    fn get(&self, name: &str) -> Result<Node, String> {
+        self.0
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("No {name} in savegame to use as template"))
+    }
+}
+
+/// Decoded struct that can be written back onto its object node.
+trait Patch {
+    /// Class name without the layout version, like the schema names in
+    /// [`crate::raw_data::known_layouts`].
+    const NAME: &'static str;
+
+    fn patch(&self, node: &mut Node, templates: &Templates) -> Result<(), String>;
+}
+
+fn patch_key<T: Patch>(
+    node: &mut Node,
+    key: &str,
+    value: &T,
+    templates: &Templates,
+) -> Result<(), String> {
+    let field = node.field_mut(key)?;
+    if field.class_name().map(unversioned) != Some(T::NAME) {
+        *field = templates.get(T::NAME)?;
+    }
+    value
+        .patch(field, templates)
+        .map_err(|error| format!("While patching key {key}:\n{error}"))
+}
+
+fn patch_optional_key<T: Patch>(
+    node: &mut Node,
+    key: &str,
+    value: Option<&T>,
+    templates: &Templates,
+) -> Result<(), String> {
+    match value {
+        Some(value) => patch_key(node, key, value, templates),
+        None => node.set_field(key, Node::Null),
+    }
+}
+
+/// `_items` of a list holds `_size` entries followed by unused capacity. A
+/// length change moves `_size` by the same amount.
+fn resize_list(list: &mut Node, old_len: usize, new_len: usize) -> Result<(), String> {
+    let Some(Node::Int32(size)) = list.field("_size") else {
+        return Err(format!("Expected list with _size; Got {list:?}"));
+    };
+    let size = (*size as i64 + new_len as i64 - old_len as i64).max(0);
+    list.set_field("_size", Node::Int32(size as i32))
+}
+
+fn items_of(list: &Node) -> Result<Vec<Node>, String> {
+    match list.field("_items") {
+        Some(Node::Array(items)) => Ok(items.clone()),
+        Some(Node::Int32Array(items)) if items.is_empty() => Ok(vec![]),
+        _ => Err(format!("Expected list with _items; Got {list:?}")),
+    }
+}
+
+/// Replace the entries of a list of references. The decoder skips null
+/// entries, so the n-th decoded item is patched onto the n-th non-null entry.
+fn patch_object_list<T: Patch>(
+    node: &mut Node,
+    key: &str,
+    items: &[T],
+    templates: &Templates,
+) -> Result<(), String> {
+    let list = node.field_mut(key)?;
+    if matches!(list, Node::Null) {
+        if items.is_empty() {
+            return Ok(());
+        }
+        let element = templates.get(T::NAME)?;
+        *list = templates.get(&format!(
+            "System.Collections.Generic.List`1[[{}, {GAME_ASSEMBLY}]]",
+            element.class_name().unwrap_or(T::NAME)
+        ))?;
+        list.set_field("_items", Node::Array(vec![]))?;
+        list.set_field("_size", Node::Int32(0))?;
+    }
+
+    let old_items = items_of(list)?;
+    let existing: Vec<&Node> = old_items
+        .iter()
+        .filter(|item| **item != Node::Null)
+        .collect();
+    let mut new_items = items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let mut entry = match existing.get(index) {
+                Some(entry) => (*entry).clone(),
+                None => templates.get(T::NAME)?,
+            };
+            item.patch(&mut entry, templates)
+                .map_err(|error| format!("While patching {key}[{index}]:\n{error}"))?;
+            Ok(entry)
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    new_items.resize(new_items.len().max(old_items.len()), Node::Null);
+
+    list.set_field("_items", Node::Array(new_items))?;
+    resize_list(list, existing.len(), items.len())
+}
+
+/// Replace the entries of a list of value types. These have no null entries
+/// and the decoder returns the whole backing array, capacity included.
+fn patch_value_list<T: Patch>(
+    node: &mut Node,
+    key: &str,
+    items: &[T],
+    templates: &Templates,
+) -> Result<(), String> {
+    let list = node.field_mut(key)?;
+    let old_items = items_of(list)?;
+    let new_items = items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let mut entry = match old_items.get(index) {
+                Some(entry) => entry.clone(),
+                None => templates.get(T::NAME)?,
+            };
+            item.patch(&mut entry, templates)?;
+            Ok(entry)
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    list.set_field("_items", Node::Array(new_items))?;
+    resize_list(list, old_items.len(), items.len())
+}
+
+fn patch_int_list(node: &mut Node, key: &str, items: &[i32]) -> Result<(), String> {
+    let list = node.field_mut(key)?;
+    let Some(Node::Int32Array(old_items)) = list.field("_items") else {
+        return Err(format!("Expected int list for {key}; Got {list:?}"));
+    };
+    let old_len = old_items.len();
+    list.set_field("_items", Node::Int32Array(items.to_vec()))?;
+    resize_list(list, old_len, items.len())
+}
+
+macro_rules! id_patch {
+    ($type:ty, $class:literal) => {
+        impl Patch for $type {
+            const NAME: &'static str = $class;
+
+            fn patch(&self, node: &mut Node, _: &Templates) -> Result<(), String> {
+                node.set_field("value__", Node::Int32(self.0))
+            }
+        }
+    };
+}
+
+id_patch!(ChallengeId, "Dorfromantik.ChallengeId");
+id_patch!(GroupTypeId, "GroupTypeId");
+id_patch!(GameModeId, "GameModeId");
+id_patch!(QuestTileId, "QuestTileId");
+id_patch!(QuestId, "Dorfromantik.QuestId");
+id_patch!(SegmentTypeId, "Dorfromantik.SegmentTypeId");
+id_patch!(SpecialTileId, "Dorfromantik.SpecialTileId");
+
+impl Patch for Segment {
+    const NAME: &'static str = "SegmentData";
+
+    fn patch(&self, node: &mut Node, templates: &Templates) -> Result<(), String> {
+        patch_key(node, "groupType", &self.group_type, templates)?;
+        patch_key(node, "segmentType", &self.segment_type, templates)?;
+        node.set_field("rotation", Node::Int32(self.rotation))?;
+        node.set_field("version", Node::Int32(self.version))
+    }
+}
+
+impl Patch for QuestTile {
+    const NAME: &'static str = "QuestTileData";
+
+    fn patch(&self, node: &mut Node, templates: &Templates) -> Result<(), String> {
+        patch_key(node, "questTileId", &self.quest_tile_id, templates)?;
+        node.set_field("questActive", Node::Bool(self.quest_active))?;
+        node.set_field("questQueueIndex", Node::Int32(self.quest_queue_index))?;
+        node.set_field("targetValue", Node::Int32(self.target_value))?;
+        node.set_field("questLevel", Node::Int32(self.quest_level))?;
+        patch_key(node, "questId", &self.quest_id, templates)?;
+        patch_key(
+            node,
+            "unlockedChallengeId",
+            &self.unlocked_challenge_id,
+            templates,
+        )?;
+        node.set_field("version", Node::Int32(self.version))
+    }
+}
+
+impl Patch for Tile {
+    const NAME: &'static str = "TileData";
+
+    fn patch(&self, node: &mut Node, templates: &Templates) -> Result<(), String> {
+        node.set_field("gridPos", Node::Int32Array(vec![self.s, self.t]))?;
+        node.set_field("rotation", Node::Int32(self.rotation))?;
+        node.set_field("seed", Node::Int32(self.seed))?;
+        patch_object_list(node, "segments", &self.segments, templates)?;
+        patch_key(node, "specialTileId", &self.special_tile_id, templates)?;
+        patch_optional_key(node, "questTileData", self.quest_tile.as_ref(), templates)?;
+        node.set_field("version", Node::Int32(self.version))
+    }
+}
+
+impl Patch for PreplacedTile {
+    const NAME: &'static str = "PreplacedTileData";
+
+    fn patch(&self, node: &mut Node, templates: &Templates) -> Result<(), String> {
+        node.set_field("sectionGridPosX", Node::Int32(self.section_grid_pos_x))?;
+        node.set_field("sectionGridPosY", Node::Int32(self.section_grid_pos_y))?;
+        patch_key(node, "preplacedTileId", &self.preplaced_tile_id, templates)?;
+        node.set_field("version", Node::Int32(self.version))
+    }
+}
+
+impl Patch for SaveGame {
+    const NAME: &'static str = "SaveGameData";
+
+    fn patch(&self, node: &mut Node, templates: &Templates) -> Result<(), String> {
+        patch_key(node, "gameMode", &self.game_mode, templates)?;
+        for (key, value) in [
+            ("level", self.level),
+            ("score", self.score),
+            ("perfectPlacements", self.perfect_placements),
+            ("questsFulfilled", self.quests_fulfilled),
+            ("questsFailed", self.quests_failed),
+            ("consecutivePerfectFits", self.consecutive_perfect_fits),
+            (
+                "consecutivePlacementsWithoutRotate",
+                self.consecutive_placements_without_rotate,
+            ),
+            ("biomeSeed", self.biome_seed),
+            ("preplacedTileSeed", self.preplaced_tile_seed),
+            ("placedTileCount", self.placed_tile_count),
+            ("generatedTileCount", self.generated_tile_count),
+            ("generatedQuestCount", self.generated_quest_count),
+            ("surroundedTilesCount", self.surrounded_tiles_count),
+            ("tileStackCount", self.tile_stack_count),
+            ("version", self.version),
+        ] {
+            node.set_field(key, Node::Int32(value))?;
+        }
+        node.set_field("playtime", Node::Single(self.playtime))?;
+
+        patch_object_list(node, "tiles", &self.tiles, templates)?;
+        patch_object_list(node, "tileStack", &self.tile_stack, templates)?;
+        patch_object_list(node, "preplacedTiles", &self.preplaced_tiles, templates)?;
+        patch_value_list(
+            node,
+            "pendingLockedChallenges",
+            &self.pending_locked_challenges,
+            templates,
+        )?;
+        // Layout 2 has no reward milestones.
+        if node.field("lastRewardedStep").is_some() {
+            patch_int_list(node, "lastRewardedStep", &self.last_rewarded_step)?;
+            patch_int_list(node, "lastRewardedScore", &self.last_rewarded_score)?;
+        }
+
+        node.set_field(
+            "fileName",
+            self.file_name.clone().map_or(Node::Null, Node::String),
+        )?;
+        node.set_field("initialVersion", Node::String(self.initial_version.clone()))?;
+        node.set_field(
+            "lastPlayedVersion",
+            Node::String(self.last_played_version.clone()),
+        )
+    }
+}
+
+/// Object tree of a loaded savegame that edits can be applied to.
+pub struct SaveGameWriter {
+    root: Node,
+    templates: Templates,
+}
+
+impl TryFrom<Node> for SaveGameWriter {
+    type Error = String;
+
+    fn try_from(root: Node) -> Result<Self, String> {
+        if root.class_name().map(unversioned) != Some(SaveGame::NAME) {
+            return Err(format!(
+                "Expected {}_*; Got {:?}",
+                SaveGame::NAME,
+                root.class_name()
+            ));
+        }
+        let templates = Templates::collect(&root);
+        Ok(Self { root, templates })
+    }
+}
+
+impl SaveGameWriter {
+    pub fn root(&self) -> &Node {
+        &self.root
+    }
+
+    /// Overwrite every field that `savegame` knows about. Adding tiles needs
+    /// an existing tile in the file to copy the class layout from.
+    pub fn apply(&mut self, savegame: &SaveGame) -> Result<(), String> {
+        let mut root = self.root.clone();
+        savegame.patch(&mut root, &self.templates)?;
+        self.root = root;
+        Ok(())
+    }
+
+    /// Write the savegame.
+    pub fn write_to(&self, out: impl Write) -> Result<(), String> {
+        write_nrbf(&self.root, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int_list(items: Vec<i32>, size: i32) -> Node {
+        Node::Object(
+            "System.Collections.Generic.List`1[[System.Int32, mscorlib]]".to_string(),
+            vec![
+                ("_items".to_string(), Node::Int32Array(items)),
+                ("_size".to_string(), Node::Int32(size)),
+                ("_version".to_string(), Node::Int32(0)),
+            ],
+        )
+    }
+
+    fn preplaced(x: i32) -> Node {
+        Node::Object(
+            "PreplacedTileData_002".to_string(),
+            vec![
+                ("sectionGridPosX".to_string(), Node::Int32(x)),
+                ("sectionGridPosY".to_string(), Node::Int32(0)),
+                (
+                    "preplacedTileId".to_string(),
+                    Node::Object(
+                        "QuestTileId".to_string(),
+                        vec![("value__".to_string(), Node::Int32(0))],
+                    ),
+                ),
+                ("version".to_string(), Node::Int32(2)),
+            ],
+        )
+    }
+
+    fn holder(items: Vec<Node>, size: i32) -> Node {
+        Node::Object(
+            "Holder".to_string(),
+            vec![
+                (
+                    "list".to_string(),
+                    Node::Object(
+                        format!(
+                            "System.Collections.Generic.List`1[[PreplacedTileData_002, {GAME_ASSEMBLY}]]"
+                        ),
+                        vec![
+                            ("_items".to_string(), Node::Array(items)),
+                            ("_size".to_string(), Node::Int32(size)),
+                            ("_version".to_string(), Node::Int32(0)),
+                        ],
+                    ),
+                ),
+                ("ints".to_string(), int_list(vec![1, 2, 0, 0], 2)),
+            ],
+        )
+    }
+
+    fn preplaced_tile(x: i32) -> PreplacedTile {
+        PreplacedTile {
+            section_grid_pos_x: x,
+            section_grid_pos_y: 0,
+            preplaced_tile_id: QuestTileId(5),
+            version: 2,
+        }
+    }
+
+    #[test]
+    fn test_object_list_keeps_capacity() {
+        let mut node = holder(vec![preplaced(1), preplaced(2), Node::Null, Node::Null], 2);
+        let templates = Templates::collect(&node);
+        patch_object_list(&mut node, "list", &[preplaced_tile(9)], &templates).unwrap();
+
+        let list = node.field("list").unwrap();
+        let Some(Node::Array(items)) = list.field("_items") else {
+            panic!("list lost its items");
+        };
+        assert_eq!(items.len(), 4);
+        assert_eq!(items[0].field("sectionGridPosX"), Some(&Node::Int32(9)));
+        assert_eq!(items[1], Node::Null);
+        assert_eq!(list.field("_size"), Some(&Node::Int32(1)));
+    }
+
+    #[test]
+    fn test_object_list_grows_from_template() {
+        let mut node = holder(vec![preplaced(1)], 1);
+        let templates = Templates::collect(&node);
+        let tiles: Vec<_> = (0..3).map(preplaced_tile).collect();
+        patch_object_list(&mut node, "list", &tiles, &templates).unwrap();
+
+        let list = node.field("list").unwrap();
+        let Some(Node::Array(items)) = list.field("_items") else {
+            panic!("list lost its items");
+        };
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[2].field("sectionGridPosX"), Some(&Node::Int32(2)));
+        assert_eq!(
+            items[2].field("preplacedTileId").unwrap().field("value__"),
+            Some(&Node::Int32(5))
+        );
+        assert_eq!(list.field("_size"), Some(&Node::Int32(3)));
+    }
+
+    #[test]
+    fn test_new_entries_keep_the_layout_of_the_savegame() {
+        let newer = |x| {
+            let Node::Object(_, values) = preplaced(x) else {
+                unreachable!()
+            };
+            Node::Object("PreplacedTileData_007".to_string(), values)
+        };
+        let mut node = holder(vec![newer(1)], 1);
+        let templates = Templates::collect(&node);
+        let tiles: Vec<_> = (0..2).map(preplaced_tile).collect();
+        patch_object_list(&mut node, "list", &tiles, &templates).unwrap();
+
+        let Some(Node::Array(items)) = node.field("list").unwrap().field("_items") else {
+            panic!("list lost its items");
+        };
+        assert!(items
+            .iter()
+            .all(|item| item.class_name() == Some("PreplacedTileData_007")));
+    }
+
+    #[test]
+    fn test_patched_classes_are_known_layouts() {
+        let known: Vec<&str> = crate::raw_data::known_layouts()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        for name in [
+            Segment::NAME,
+            QuestTile::NAME,
+            Tile::NAME,
+            PreplacedTile::NAME,
+            SaveGame::NAME,
+        ] {
+            assert!(known.contains(&name), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_missing_template_is_an_error() {
+        let mut node = holder(vec![], 0);
+        let templates = Templates::collect(&node);
+        assert!(patch_object_list(&mut node, "list", &[preplaced_tile(0)], &templates).is_err());
+    }
+
+    #[test]
+    fn test_int_list_moves_size() {
+        let mut node = holder(vec![], 0);
+        patch_int_list(&mut node, "ints", &[1, 2, 3, 0, 0]).unwrap();
+        let list = node.field("ints").unwrap();
+        assert_eq!(
+            list.field("_items"),
+            Some(&Node::Int32Array(vec![1, 2, 3, 0, 0]))
+        );
+        assert_eq!(list.field("_size"), Some(&Node::Int32(3)));
+    }
+
+    #[test]
+    fn test_unknown_field_is_an_error() {
+        let mut node = preplaced(0);
+        assert!(node.set_field("sectionGridPosZ", Node::Int32(0)).is_err());
+    }
+}
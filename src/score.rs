@@ -0,0 +1,335 @@
+//! Points the game would award for a placement: matching edges, perfect placements and
+//! completed quests, and the tiles those events add to the stack.
+//!
+//! The reward values are not stored in the save. `ScoreRules::default` is calibrated against
+//! the totals of the test fixture; expected points count every gained tile at the average
+//! points of a placed tile.
+
+use std::collections::{BTreeSet, HashSet};
+
+use crate::{
+    best_placements::{constraints_at, count_matches},
+    data::{EdgeProfile, GroupKind, HexPos, Rotation, Segment, HEX_SIDES},
+    group::GroupIndex,
+    group_assignments::GroupAssignments,
+    map::{Map, Quest, QuestType},
+    raw_data::SaveGame,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScoreRules {
+    pub matching_edge_points: i32,
+    /// On top of the matching edges, when all six sides match.
+    pub perfect_points: i32,
+    pub perfect_tiles: i32,
+    /// For reaching the target of an at-least or exact quest.
+    pub quest_points: i32,
+    pub quest_tiles: i32,
+    /// For closing a group with a flag quest.
+    pub flag_points: i32,
+    pub flag_tiles: i32,
+    /// What one more tile in the stack is worth.
+    pub tile_value: f32,
+}
+
+/// Fitted to `tests/fixtures/dorfromantik.dump`: its 40 starting tiles, one tile per perfect
+/// placement and five per fulfilled quest add up to exactly the placed and stacked tiles. Ten
+/// points per matching edge and 60 per perfect placement leave about 90 points per fulfilled
+/// quest. The save doesn't tell flag quests apart, so they count like the others.
+impl Default for ScoreRules {
+    fn default() -> Self {
+        Self {
+            matching_edge_points: 10,
+            perfect_points: 60,
+            perfect_tiles: 1,
+            quest_points: 90,
+            quest_tiles: 5,
+            flag_points: 90,
+            flag_tiles: 5,
+            tile_value: 30.0,
+        }
+    }
+}
+
+impl ScoreRules {
+    /// Value gained tiles at the average points per tile of a game so far.
+    pub fn with_average_tile_value(self, score: i32, placed_tiles: usize) -> Self {
+        if placed_tiles == 0 {
+            return self;
+        }
+        Self {
+            tile_value: score as f32 / placed_tiles as f32,
+            ..self
+        }
+    }
+}
+
+impl From<&SaveGame> for ScoreRules {
+    /// The default rules, with a gained tile worth the points per tile of `savegame`.
+    fn from(savegame: &SaveGame) -> Self {
+        Self::default()
+            .with_average_tile_value(savegame.score, savegame.placed_tile_count.max(0) as usize)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum ScoreEvent {
+    /// All six sides match.
+    Perfect,
+    QuestCompleted(Quest),
+    /// An exact quest went over its target or was closed short of it.
+    QuestFailed(Quest),
+    GroupClosed {
+        kind: GroupKind,
+        units: u32,
+    },
+}
+
+/// What placing a tile would earn.
+#[derive(Clone, Debug, Default)]
+pub struct PointsEstimate {
+    pub points: i32,
+    /// Tiles added to the stack.
+    pub tiles: i32,
+    pub events: Vec<ScoreEvent>,
+}
+
+impl PointsEstimate {
+    /// Points plus the value of the gained tiles.
+    pub fn expected(&self, rules: &ScoreRules) -> f32 {
+        self.points as f32 + self.tiles as f32 * rules.tile_value
+    }
+
+    pub fn quests_completed(&self) -> usize {
+        self.events
+            .iter()
+            .filter(|event| matches!(event, ScoreEvent::QuestCompleted(_)))
+            .count()
+    }
+
+    pub fn is_perfect(&self) -> bool {
+        self.events
+            .iter()
+            .any(|event| matches!(event, ScoreEvent::Perfect))
+    }
+}
+
+/// Groups of one kind that a placed tile joins into one.
+pub(crate) struct Component {
+    pub(crate) kind: GroupKind,
+    pub(crate) groups: BTreeSet<GroupIndex>,
+    /// Units of the new segments.
+    pub(crate) units: u32,
+    /// Empty cells the new segments point at.
+    pub(crate) open_edges: HashSet<HexPos>,
+}
+
+impl Component {
+    /// Units of the joined group.
+    pub(crate) fn units_after(&self, groups: &GroupAssignments) -> u32 {
+        self.groups
+            .iter()
+            .map(|&index| groups.groups[index].unit_count)
+            .sum::<u32>()
+            + self.units
+    }
+
+    /// Whether the joined group has no open edges left once `pos` is filled.
+    pub(crate) fn closes(&self, groups: &GroupAssignments, pos: HexPos) -> bool {
+        self.groups
+            .iter()
+            .flat_map(|&index| &groups.groups[index].open_edges)
+            .chain(&self.open_edges)
+            .all(|&open| open == pos)
+    }
+}
+
+/// Whether a quest of a group that goes from `units_before` to `units_after` units gets
+/// completed (`true`) or failed (`false`) by it.
+fn quest_outcome(quest: &Quest, units_before: u32, units_after: u32, closed: bool) -> Option<bool> {
+    let target = quest.target_value;
+    let (before, after) = (units_before as i32, units_after as i32);
+    match quest.quest_type {
+        QuestType::MoreThan => (before < target && after >= target).then_some(true),
+        QuestType::Exact if closed => Some(after == target),
+        QuestType::Exact => (before <= target && after > target).then_some(false),
+        QuestType::Flag => closed.then_some(true),
+        QuestType::Unknown => None,
+    }
+}
+
+/// The groups that placing `tile` at `pos` with `rotation` changes: one component per group
+/// the new segments form, after merging with the neighbor groups they connect to, and one per
+/// neighbor group that only loses its open edge at `pos`.
+pub(crate) fn components(
+    map: &Map,
+    groups: &GroupAssignments,
+    tile: &[Segment],
+    pos: HexPos,
+    rotation: Rotation,
+) -> Vec<Component> {
+    // Groups of the new segments, merged where they share a neighbor group.
+    let mut components: Vec<Component> = Vec::new();
+    for segment in tile {
+        for &kind in GroupKind::memberships_of(segment.terrain) {
+            let mut component = Component {
+                kind,
+                groups: BTreeSet::new(),
+                units: segment.unit_count,
+                open_edges: HashSet::new(),
+            };
+            for side in segment.rotations().map(|r| (r + rotation) % HEX_SIDES) {
+                let neighbor_pos = Map::neighbor_pos_of(pos, side);
+                if !map.has(neighbor_pos) {
+                    component.open_edges.insert(neighbor_pos);
+                    continue;
+                }
+                let connected = map
+                    .segment_at(neighbor_pos, Map::opposite_side(side))
+                    .filter(|(_, neighbor)| kind.accepts(neighbor.terrain))
+                    .and_then(|(index, _)| groups.group_of_kind(index, kind));
+                component.groups.extend(connected);
+            }
+            while let Some(shared) = components.iter().position(|other| {
+                other.kind == kind && !other.groups.is_disjoint(&component.groups)
+            }) {
+                let other = components.swap_remove(shared);
+                component.groups.extend(other.groups);
+                component.units += other.units;
+                component.open_edges.extend(other.open_edges);
+            }
+            components.push(component);
+        }
+    }
+
+    // Neighbor groups that only lose their open edge at `pos`.
+    let mut touched: BTreeSet<GroupIndex> = BTreeSet::new();
+    for side in 0..HEX_SIDES {
+        let neighbor_pos = Map::neighbor_pos_of(pos, side);
+        if let Some((index, neighbor)) = map.segment_at(neighbor_pos, Map::opposite_side(side)) {
+            for &kind in GroupKind::memberships_of(neighbor.terrain) {
+                touched.extend(groups.group_of_kind(index, kind));
+            }
+        }
+    }
+    for component in &components {
+        touched.retain(|group_index| !component.groups.contains(group_index));
+    }
+    components.extend(touched.into_iter().map(|group_index| Component {
+        kind: groups.groups[group_index].kind,
+        groups: BTreeSet::from([group_index]),
+        units: 0,
+        open_edges: HashSet::new(),
+    }));
+    components
+}
+
+/// Points for placing `tile` at `pos` with `rotation`. The placement is assumed to be legal.
+pub fn simulate(
+    map: &Map,
+    groups: &GroupAssignments,
+    tile: &[Segment],
+    pos: HexPos,
+    rotation: Rotation,
+    rules: &ScoreRules,
+) -> PointsEstimate {
+    let mut estimate = PointsEstimate::default();
+
+    let constraints = constraints_at(map, pos);
+    let profile = EdgeProfile::from_segments(tile).rotated(rotation);
+    let (matching_edges, _) = count_matches(&profile, &constraints);
+    estimate.points += i32::from(matching_edges) * rules.matching_edge_points;
+    if constraints.iter().all(Option::is_some) && matching_edges == HEX_SIDES as u8 {
+        estimate.points += rules.perfect_points;
+        estimate.tiles += rules.perfect_tiles;
+        estimate.events.push(ScoreEvent::Perfect);
+    }
+
+    for component in components(map, groups, tile, pos, rotation) {
+        let merged = || component.groups.iter().map(|&index| &groups.groups[index]);
+        let units_after = component.units_after(groups);
+        let closed = component.closes(groups, pos);
+        if closed {
+            estimate.events.push(ScoreEvent::GroupClosed {
+                kind: component.kind,
+                units: units_after,
+            });
+        }
+        for group in merged() {
+            for quest in group.quests.iter().filter(|quest| quest.active) {
+                match quest_outcome(quest, group.unit_count, units_after, closed) {
+                    Some(true) => {
+                        if quest.quest_type == QuestType::Flag {
+                            estimate.points += rules.flag_points;
+                            estimate.tiles += rules.flag_tiles;
+                        } else {
+                            estimate.points += rules.quest_points;
+                            estimate.tiles += rules.quest_tiles;
+                        }
+                        estimate
+                            .events
+                            .push(ScoreEvent::QuestCompleted(quest.clone()));
+                    }
+                    Some(false) => estimate.events.push(ScoreEvent::QuestFailed(quest.clone())),
+                    None => {}
+                }
+            }
+        }
+    }
+    estimate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Terrain;
+
+    fn quest(quest_type: QuestType, target_value: i32) -> Quest {
+        Quest {
+            terrain: Terrain::Forest,
+            target_value,
+            active: true,
+            quest_type,
+            quest_id: 0,
+            quest_level: 0,
+            quest_queue_index: 0,
+            unlocked_challenge_id: 0,
+        }
+    }
+
+    #[test]
+    fn test_quest_outcome() {
+        let at_least = quest(QuestType::MoreThan, 10);
+        assert_eq!(quest_outcome(&at_least, 8, 11, false), Some(true));
+        assert_eq!(quest_outcome(&at_least, 10, 12, false), None);
+        assert_eq!(quest_outcome(&at_least, 3, 5, true), None);
+
+        let exact = quest(QuestType::Exact, 10);
+        assert_eq!(quest_outcome(&exact, 8, 10, false), None);
+        assert_eq!(quest_outcome(&exact, 8, 10, true), Some(true));
+        assert_eq!(quest_outcome(&exact, 8, 9, true), Some(false));
+        assert_eq!(quest_outcome(&exact, 9, 12, false), Some(false));
+
+        let flag = quest(QuestType::Flag, 0);
+        assert_eq!(quest_outcome(&flag, 4, 6, false), None);
+        assert_eq!(quest_outcome(&flag, 4, 6, true), Some(true));
+    }
+
+    #[test]
+    fn test_expected_points_value_gained_tiles() {
+        let rules = ScoreRules::default().with_average_tile_value(5000, 100);
+        assert_eq!(rules.tile_value, 50.0);
+        let estimate = PointsEstimate {
+            points: 120,
+            tiles: 2,
+            events: vec![ScoreEvent::Perfect],
+        };
+        assert_eq!(estimate.expected(&rules), 220.0);
+        assert!(estimate.is_perfect());
+        assert_eq!(estimate.quests_completed(), 0);
+        assert_eq!(
+            ScoreRules::default().with_average_tile_value(100, 0),
+            ScoreRules::default()
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! How placements are ranked against each other. Players disagree on strategy, so the ranking
+//! can be swapped at runtime: the built-in order, expected points, or a weighted sum loaded
+//! from a TOML file.
+
+use std::{
+    cmp::Ordering,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::best_placements::PlacementScore;
+
+pub trait PlacementScorer: Send + Sync {
+    /// Shown next to the ranking.
+    fn name(&self) -> &str;
+
+    /// How `a` ranks against `b`. `Greater` means `a` is the better placement.
+    fn compare(&self, a: &PlacementScore, b: &PlacementScore) -> Ordering;
+}
+
+/// The built-in order: lowest fit chance first, then connection difficulty, matching edges,
+/// crowding and neighbor bonus.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultScorer;
+
+impl PlacementScorer for DefaultScorer {
+    fn name(&self) -> &str {
+        "Default"
+    }
+
+    fn compare(&self, a: &PlacementScore, b: &PlacementScore) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+/// Ranks placements by the points the game would award, counting gained tiles at their
+/// expected value (see `score`).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PointsScorer;
+
+impl PlacementScorer for PointsScorer {
+    fn name(&self) -> &str {
+        "Points"
+    }
+
+    fn compare(&self, a: &PlacementScore, b: &PlacementScore) -> Ordering {
+        a.expected_points
+            .total_cmp(&b.expected_points)
+            .then_with(|| a.cmp(b))
+    }
+}
+
+/// Ranks placements by a weighted sum of their score components. Weights missing from the
+/// file keep their default, so a file only needs the ones it changes:
+///
+/// ```toml
+/// fit_chance = -20.0
+/// matching_edges = 2.0
+/// ```
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WeightedScorer {
+    /// Per 100% chance that a random tile fits the cell.
+    pub fit_chance: f32,
+    pub matching_edges: f32,
+    pub connection_difficulty: f32,
+    pub crowding: f32,
+    pub neighbor_bonus: f32,
+    pub preplaced_neighbors: f32,
+    /// Per expected point, see `PointsScorer`.
+    pub expected_points: f32,
+}
+
+impl Default for WeightedScorer {
+    /// Roughly the priorities of the default order.
+    fn default() -> Self {
+        Self {
+            fit_chance: -10.0,
+            matching_edges: 1.0,
+            connection_difficulty: -1.0,
+            crowding: -0.5,
+            neighbor_bonus: 0.5,
+            preplaced_neighbors: 0.0,
+            expected_points: 0.0,
+        }
+    }
+}
+
+/// Why the weights of a `WeightedScorer` could not be loaded.
+#[derive(Debug)]
+pub enum ScorerError {
+    Read(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+}
+
+impl std::fmt::Display for ScorerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScorerError::Read(path, e) => {
+                write!(f, "Failed to read weights {}: {e}", path.display())
+            }
+            ScorerError::Parse(path, e) => {
+                write!(f, "Failed to parse weights {}: {e}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScorerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ScorerError::Read(_, e) => Some(e),
+            ScorerError::Parse(_, e) => Some(e),
+        }
+    }
+}
+
+impl WeightedScorer {
+    pub fn from_toml(text: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ScorerError> {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| ScorerError::Read(path.to_owned(), e))?;
+        Self::from_toml(&text).map_err(|e| ScorerError::Parse(path.to_owned(), e))
+    }
+
+    pub fn value(&self, score: &PlacementScore) -> f32 {
+        self.fit_chance * score.fit_chance
+            + self.matching_edges * f32::from(score.matching_edges)
+            + self.connection_difficulty * f32::from(score.connection_difficulty)
+            + self.crowding * f32::from(score.crowding)
+            + self.neighbor_bonus * f32::from(score.neighbor_bonus)
+            + self.preplaced_neighbors * f32::from(score.preplaced_neighbors)
+            + self.expected_points * score.expected_points
+    }
+}
+
+impl PlacementScorer for WeightedScorer {
+    fn name(&self) -> &str {
+        "Weighted"
+    }
+
+    fn compare(&self, a: &PlacementScore, b: &PlacementScore) -> Ordering {
+        // Equal sums fall back to the default order, which also tells positions apart.
+        self.value(a)
+            .total_cmp(&self.value(b))
+            .then_with(|| a.cmp(b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::HexPos;
+
+    fn score(x: i32, matching_edges: u8, fit_chance: f32) -> PlacementScore {
+        let expected_points = f32::from(matching_edges) * 10.0;
+        PlacementScore {
+            pos: HexPos::new(x, 0),
+            rotation: 0,
+            matching_edges,
+            neighbor_bonus: 0,
+            connection_difficulty: 0,
+            crowding: 0,
+            preplaced_neighbors: 0,
+            group_effects: Vec::new(),
+            group_edge_alterations: Vec::new(),
+            fit_chance,
+            fit_unique: 0,
+            neighbor_fit_effects: Vec::new(),
+            points: Default::default(),
+            expected_points,
+        }
+    }
+
+    #[test]
+    fn test_default_scorer_prefers_hard_to_fill_cells() {
+        let hard = score(0, 1, 0.1);
+        let easy = score(1, 6, 0.9);
+        assert_eq!(DefaultScorer.compare(&hard, &easy), Ordering::Greater);
+        assert_eq!(DefaultScorer.compare(&easy, &hard), Ordering::Less);
+    }
+
+    #[test]
+    fn test_points_scorer_prefers_more_points() {
+        let hard = score(0, 1, 0.1);
+        let easy = score(1, 6, 0.9);
+        assert_eq!(PointsScorer.compare(&easy, &hard), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_weighted_scorer_follows_its_weights() {
+        let hard = score(0, 1, 0.1);
+        let easy = score(1, 6, 0.9);
+        let edges_first = WeightedScorer::from_toml("fit_chance = 0.0").unwrap();
+        assert_eq!(edges_first.compare(&easy, &hard), Ordering::Greater);
+        assert_eq!(
+            WeightedScorer::default().compare(&hard, &easy),
+            Ordering::Greater
+        );
+        // Equal sums still give a strict order.
+        let twin = score(2, 6, 0.9);
+        assert_ne!(edges_first.compare(&easy, &twin), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_weights_load_from_toml() {
+        let weights = WeightedScorer::from_toml("matching_edges = 2.5\ncrowding = -1").unwrap();
+        assert_eq!(weights.matching_edges, 2.5);
+        assert_eq!(weights.crowding, -1.0);
+        assert_eq!(weights.fit_chance, WeightedScorer::default().fit_chance);
+
+        assert!(WeightedScorer::from_toml("matching_edgs = 2.5").is_err());
+        let missing = WeightedScorer::load(Path::new("does/not/exist.toml"));
+        assert!(matches!(missing, Err(ScorerError::Read(..))));
+    }
+}
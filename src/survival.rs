@@ -0,0 +1,318 @@
+//! Whether the game runs out of tiles. Every placement uses up a tile, perfect placements and
+//! completed quests put tiles back. The model takes the refill rates of the game so far, adds
+//! the quests that are about to be completed and estimates how many turns are left.
+//!
+//! The stack is treated as a random walk: each turn it loses one tile and gains what perfect
+//! placements and quests give on average. With a positive drift the game can go on forever,
+//! so the estimate also carries the chance that the walk still hits zero.
+
+use crate::{
+    best_placements::PlacementScore,
+    group_assignments::GroupAssignments,
+    map::{Quest, QuestType},
+    raw_data::SaveGame,
+    score::{ScoreEvent, ScoreRules},
+};
+
+/// Quests at most this many units short of their target are expected to complete soon.
+pub const NEAR_UNITS: i32 = 5;
+/// Flag quests with at most this many open edges are expected to complete soon.
+pub const NEAR_EDGES: usize = 2;
+
+/// The counters of a save that the model is based on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GameStats {
+    pub placed_tiles: i32,
+    pub perfect_placements: i32,
+    pub quests_fulfilled: i32,
+    pub consecutive_perfect_fits: i32,
+}
+
+impl From<&SaveGame> for GameStats {
+    fn from(savegame: &SaveGame) -> Self {
+        Self {
+            placed_tiles: savegame.placed_tile_count,
+            perfect_placements: savegame.perfect_placements,
+            quests_fulfilled: savegame.quests_fulfilled,
+            consecutive_perfect_fits: savegame.consecutive_perfect_fits,
+        }
+    }
+}
+
+/// An active quest of an open group that is close to its target.
+#[derive(Clone, Debug)]
+pub struct PendingQuest {
+    pub quest: Quest,
+    /// Units to go, or open edges for flag quests.
+    pub remaining: i32,
+    /// How likely the quest completes soon.
+    pub chance: f32,
+    /// Tiles the quest adds to the stack.
+    pub tiles: i32,
+}
+
+impl PendingQuest {
+    pub fn expected_tiles(&self) -> f32 {
+        self.chance * self.tiles as f32
+    }
+}
+
+fn same_quest(a: &Quest, b: &Quest) -> bool {
+    a.quest_id == b.quest_id && a.terrain == b.terrain && a.target_value == b.target_value
+}
+
+/// Expected remaining turns and game-over risk at some point of the game.
+#[derive(Clone, Debug, Default)]
+pub struct TurnEstimate {
+    pub tiles_left: i32,
+    /// Expected tiles from `PendingQuest`s.
+    pub pending_tiles: f32,
+    /// Expected change of the stack per turn, including the tile that is placed.
+    pub drift: f32,
+    /// Expected turns until the stack is empty, `None` if it grows on average.
+    pub turns: Option<f32>,
+    /// Chance that the stack runs empty at some point.
+    pub risk: f32,
+}
+
+impl TurnEstimate {
+    /// Difference in expected turns, `None` if either side never runs out.
+    pub fn turns_delta(&self, before: &TurnEstimate) -> Option<f32> {
+        Some(self.turns? - before.turns?)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SurvivalModel {
+    pub rules: ScoreRules,
+    /// Share of placements that are perfect.
+    pub perfect_rate: f32,
+    /// Quests completed per placed tile, for quests that are not in sight yet.
+    pub quest_rate: f32,
+}
+
+impl SurvivalModel {
+    pub fn new(stats: &GameStats, rules: ScoreRules) -> Self {
+        // The current streak says more about the board now than the start of the game, so
+        // it is counted once more on top of the totals.
+        let streak = stats.consecutive_perfect_fits.max(0) as f32;
+        let placed = stats.placed_tiles.max(0) as f32;
+        let perfect_rate = if placed + streak > 0.0 {
+            (stats.perfect_placements.max(0) as f32 + streak) / (placed + streak)
+        } else {
+            0.0
+        };
+        let quest_rate = if placed > 0.0 {
+            stats.quests_fulfilled.max(0) as f32 / placed
+        } else {
+            0.0
+        };
+        Self {
+            rules,
+            perfect_rate: perfect_rate.min(1.0),
+            quest_rate: quest_rate.min(1.0),
+        }
+    }
+
+    /// Tiles gained per turn on average.
+    pub fn refill_rate(&self) -> f32 {
+        self.perfect_rate * self.rules.perfect_tiles as f32
+            + self.quest_rate * self.rules.quest_tiles as f32
+    }
+
+    /// Variance of the tiles gained per turn.
+    fn refill_variance(&self) -> f32 {
+        let bernoulli = |p: f32, tiles: i32| p * (1.0 - p) * (tiles * tiles) as f32;
+        bernoulli(self.perfect_rate, self.rules.perfect_tiles)
+            + bernoulli(self.quest_rate, self.rules.quest_tiles)
+    }
+
+    /// Active quests of open groups that are close to their target.
+    pub fn pending_quests(&self, groups: &GroupAssignments) -> Vec<PendingQuest> {
+        let mut pending = Vec::new();
+        for group in groups.groups.iter().filter(|group| !group.is_closed()) {
+            for (quest, remaining) in group.remaining_per_quest() {
+                if !quest.active {
+                    continue;
+                }
+                let near = |remaining: i32, reach: i32| {
+                    (reach + 1 - remaining) as f32 / (reach + 1) as f32
+                };
+                let (remaining, chance, tiles) = match quest.quest_type {
+                    QuestType::MoreThan if (1..=NEAR_UNITS).contains(&remaining) => (
+                        remaining,
+                        near(remaining, NEAR_UNITS),
+                        self.rules.quest_tiles,
+                    ),
+                    // Also has to be closed at the right size.
+                    QuestType::Exact if (0..=NEAR_UNITS).contains(&remaining) => (
+                        remaining,
+                        near(remaining, NEAR_UNITS) / 2.0,
+                        self.rules.quest_tiles,
+                    ),
+                    QuestType::Flag if group.open_edges.len() <= NEAR_EDGES => {
+                        let edges = group.open_edges.len() as i32;
+                        (edges, near(edges, NEAR_EDGES as i32), self.rules.flag_tiles)
+                    }
+                    _ => continue,
+                };
+                pending.push(PendingQuest {
+                    quest: quest.clone(),
+                    remaining,
+                    chance,
+                    tiles,
+                });
+            }
+        }
+        pending
+    }
+
+    fn estimate_with(&self, stack: f32, tiles_left: i32, pending_tiles: f32) -> TurnEstimate {
+        let drift = self.refill_rate() - 1.0;
+        let turns = (drift < 0.0).then(|| stack.max(0.0) / -drift);
+        let variance = self.refill_variance();
+        let risk = if stack <= 0.0 || drift <= 0.0 {
+            1.0
+        } else if variance == 0.0 {
+            0.0
+        } else {
+            // Chance that a random walk with this drift ever drops by `stack`.
+            (-2.0 * drift * stack / variance).exp()
+        };
+        TurnEstimate {
+            tiles_left,
+            pending_tiles,
+            drift,
+            turns,
+            risk,
+        }
+    }
+
+    /// How the game stands with `tiles_left` tiles in the stack.
+    pub fn estimate(&self, tiles_left: i32, groups: &GroupAssignments) -> TurnEstimate {
+        let pending_tiles: f32 = self
+            .pending_quests(groups)
+            .iter()
+            .map(PendingQuest::expected_tiles)
+            .sum();
+        self.estimate_with(tiles_left as f32 + pending_tiles, tiles_left, pending_tiles)
+    }
+
+    /// How the game stands after `placement` uses up a tile. Quests the placement completes
+    /// or fails are no longer pending, the tiles it gains are added right away.
+    pub fn estimate_after(
+        &self,
+        tiles_left: i32,
+        groups: &GroupAssignments,
+        placement: &PlacementScore,
+    ) -> TurnEstimate {
+        let settled: Vec<&Quest> = placement
+            .points
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                ScoreEvent::QuestCompleted(quest) | ScoreEvent::QuestFailed(quest) => Some(quest),
+                _ => None,
+            })
+            .collect();
+        let pending_tiles: f32 = self
+            .pending_quests(groups)
+            .iter()
+            .filter(|pending| {
+                !settled
+                    .iter()
+                    .any(|quest| same_quest(quest, &pending.quest))
+            })
+            .map(PendingQuest::expected_tiles)
+            .sum();
+        let tiles_left = tiles_left - 1 + placement.points.tiles;
+        self.estimate_with(tiles_left as f32 + pending_tiles, tiles_left, pending_tiles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(placed_tiles: i32, perfect_placements: i32, quests_fulfilled: i32) -> SurvivalModel {
+        let stats = GameStats {
+            placed_tiles,
+            perfect_placements,
+            quests_fulfilled,
+            consecutive_perfect_fits: 0,
+        };
+        SurvivalModel::new(&stats, ScoreRules::default())
+    }
+
+    #[test]
+    fn test_turns_follow_the_refill_rate() {
+        let groups = GroupAssignments::default();
+        // Half the tiles come back, so 10 tiles last 20 turns.
+        let half = model(100, 50, 0);
+        assert_eq!(half.refill_rate(), 0.5);
+        let estimate = half.estimate(10, &groups);
+        assert_eq!(estimate.turns, Some(20.0));
+        assert_eq!(estimate.risk, 1.0);
+
+        // Growing stacks never run out on average, but still can by bad luck.
+        let growing = model(100, 80, 10);
+        let estimate = growing.estimate(10, &groups);
+        assert_eq!(estimate.turns, None);
+        assert!(estimate.risk > 0.0 && estimate.risk < 1.0);
+        assert!(growing.estimate(30, &groups).risk < estimate.risk);
+
+        assert_eq!(model(0, 0, 0).estimate(0, &groups).turns, Some(0.0));
+    }
+
+    #[test]
+    fn test_streak_raises_the_perfect_rate() {
+        let base = GameStats {
+            placed_tiles: 100,
+            perfect_placements: 50,
+            quests_fulfilled: 0,
+            consecutive_perfect_fits: 0,
+        };
+        let streak = GameStats {
+            consecutive_perfect_fits: 20,
+            ..base
+        };
+        let rules = ScoreRules::default();
+        assert!(
+            SurvivalModel::new(&streak, rules.clone()).perfect_rate
+                > SurvivalModel::new(&base, rules).perfect_rate
+        );
+    }
+
+    #[test]
+    fn test_estimate_after_counts_gained_tiles() {
+        use crate::{data::HexPos, score::PointsEstimate};
+
+        let model = model(100, 50, 0);
+        let groups = GroupAssignments::default();
+        let mut placement = PlacementScore {
+            pos: HexPos::new(0, 0),
+            rotation: 0,
+            matching_edges: 6,
+            neighbor_bonus: 0,
+            connection_difficulty: 0,
+            crowding: 0,
+            preplaced_neighbors: 0,
+            group_effects: Vec::new(),
+            group_edge_alterations: Vec::new(),
+            fit_chance: 0.0,
+            fit_unique: 0,
+            neighbor_fit_effects: Vec::new(),
+            points: PointsEstimate::default(),
+            expected_points: 0.0,
+        };
+        let now = model.estimate(10, &groups);
+        let plain = model.estimate_after(10, &groups, &placement);
+        assert_eq!(plain.tiles_left, 9);
+        assert_eq!(plain.turns_delta(&now), Some(-2.0));
+
+        placement.points.tiles = 5;
+        let rewarding = model.estimate_after(10, &groups, &placement);
+        assert_eq!(rewarding.tiles_left, 14);
+        assert_eq!(rewarding.turns_delta(&now), Some(8.0));
+    }
+}
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use crate::data::{EdgeProfile, Segment};
-use crate::map::Map;
+use crate::map::{Map, MapUpdate};
 
 /// A canonicalized edge profile (rotation-normalized) used as a frequency key.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -21,6 +21,14 @@ pub struct TileFrequency {
     pub fraction: f64,
 }
 
+/// How the count of one pattern changed in `TileFrequencies::update`.
+#[derive(Clone, Debug)]
+pub struct FrequencyChange {
+    pub edges: EdgePattern,
+    pub count_before: usize,
+    pub count_after: usize,
+}
+
 #[derive(Default)]
 pub struct TileFrequencies {
     pub entries: Vec<TileFrequency>,
@@ -73,4 +81,60 @@ impl TileFrequencies {
             total_tiles,
         }
     }
+
+    /// Count the tiles added by `Map::extend_from` and replace the previous next tile by the
+    /// current one. Returns the patterns whose count changed.
+    pub fn update(&mut self, map: &Map, update: &MapUpdate) -> Vec<FrequencyChange> {
+        let mut deltas: HashMap<EdgePattern, (isize, Vec<Segment>)> = HashMap::new();
+        let mut count = |segments: &[Segment], delta: isize| {
+            if !segments.is_empty() {
+                deltas
+                    .entry(EdgePattern::from_segments(segments))
+                    .or_insert_with(|| (0, segments.to_vec()))
+                    .0 += delta;
+            }
+        };
+        for &pos in &update.added {
+            if let Some(indices) = map.segment_indices_at(pos) {
+                count(&map.segments[indices], 1);
+            }
+        }
+        count(&update.previous_next_tile, -1);
+        count(&map.next_tile, 1);
+
+        let mut changes = Vec::new();
+        for (edges, (delta, segments)) in deltas {
+            if delta == 0 {
+                continue;
+            }
+            let index = self.entries.iter().position(|entry| entry.edges == edges);
+            let count_before = index.map_or(0, |index| self.entries[index].count);
+            let count_after = count_before
+                .checked_add_signed(delta)
+                .expect("Tile frequency dropped below zero");
+            match index {
+                Some(index) => self.entries[index].count = count_after,
+                None => self.entries.push(TileFrequency {
+                    edges: edges.clone(),
+                    segments,
+                    count: count_after,
+                    fraction: 0.0,
+                }),
+            }
+            self.total_tiles = self.total_tiles.checked_add_signed(delta).unwrap();
+            changes.push(FrequencyChange {
+                edges,
+                count_before,
+                count_after,
+            });
+        }
+
+        self.entries.retain(|entry| entry.count > 0);
+        for entry in &mut self.entries {
+            entry.fraction = entry.count as f64 / self.total_tiles as f64;
+        }
+        self.entries
+            .sort_by_key(|entry| std::cmp::Reverse(entry.count));
+        changes
+    }
 }
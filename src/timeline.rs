@@ -0,0 +1,265 @@
+//! History of the saves of one game. Every reload that places tiles or changes the counters
+//! becomes a step that holds the tiles added since the step before and the quests of earlier
+//! tiles that changed, so the save of any step can be rebuilt from the latest one.
+//!
+//! Only the loaded saves are known. Quests that changed between two of them change with the
+//! later step, not with the placement that changed them.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    data::HexPos,
+    hex::offset_to_hex,
+    raw_data::{PreplacedTile, QuestTile, SaveGame, Tile},
+};
+
+/// The counters of a save that change while playing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Counters {
+    pub score: i32,
+    pub level: i32,
+    pub quests_fulfilled: i32,
+    pub quests_failed: i32,
+    pub perfect_placements: i32,
+    pub consecutive_perfect_fits: i32,
+    pub placed_tile_count: i32,
+    pub tile_stack_count: i32,
+}
+
+impl From<&SaveGame> for Counters {
+    fn from(savegame: &SaveGame) -> Self {
+        Self {
+            score: savegame.score,
+            level: savegame.level,
+            quests_fulfilled: savegame.quests_fulfilled,
+            quests_failed: savegame.quests_failed,
+            perfect_placements: savegame.perfect_placements,
+            consecutive_perfect_fits: savegame.consecutive_perfect_fits,
+            placed_tile_count: savegame.placed_tile_count,
+            tile_stack_count: savegame.tile_stack_count,
+        }
+    }
+}
+
+impl Counters {
+    /// How the counters changed since `previous`.
+    pub fn since(&self, previous: &Self) -> Self {
+        Self {
+            score: self.score - previous.score,
+            level: self.level - previous.level,
+            quests_fulfilled: self.quests_fulfilled - previous.quests_fulfilled,
+            quests_failed: self.quests_failed - previous.quests_failed,
+            perfect_placements: self.perfect_placements - previous.perfect_placements,
+            consecutive_perfect_fits: self.consecutive_perfect_fits
+                - previous.consecutive_perfect_fits,
+            placed_tile_count: self.placed_tile_count - previous.placed_tile_count,
+            tile_stack_count: self.tile_stack_count - previous.tile_stack_count,
+        }
+    }
+
+    fn apply_to(&self, savegame: &mut SaveGame) {
+        savegame.score = self.score;
+        savegame.level = self.level;
+        savegame.quests_fulfilled = self.quests_fulfilled;
+        savegame.quests_failed = self.quests_failed;
+        savegame.perfect_placements = self.perfect_placements;
+        savegame.consecutive_perfect_fits = self.consecutive_perfect_fits;
+        savegame.placed_tile_count = self.placed_tile_count;
+        savegame.tile_stack_count = self.tile_stack_count;
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TimelineStep {
+    /// Tiles placed since the step before, in savegame order. The first step holds all tiles
+    /// of the first save.
+    pub added: Vec<Tile>,
+    /// Tiles of earlier steps whose quest changed since the step before.
+    pub quests_changed: Vec<(HexPos, Option<QuestTile>)>,
+    pub counters: Counters,
+    tile_stack: Vec<Tile>,
+    preplaced_tiles: Vec<PreplacedTile>,
+}
+
+impl TimelineStep {
+    /// Where the tiles of this step were placed.
+    pub fn positions(&self) -> impl Iterator<Item = HexPos> + '_ {
+        self.added.iter().map(|tile| offset_to_hex(tile.s, tile.t))
+    }
+
+    /// Give the tiles of earlier steps among `tiles` the quests they had at this step.
+    pub fn apply_quest_changes(&self, tiles: &mut [Tile]) {
+        if self.quests_changed.is_empty() {
+            return;
+        }
+        let changed: HashMap<HexPos, &Option<QuestTile>> = self
+            .quests_changed
+            .iter()
+            .map(|(pos, quest)| (*pos, quest))
+            .collect();
+        for tile in tiles {
+            if let Some(&quest) = changed.get(&offset_to_hex(tile.s, tile.t)) {
+                tile.quest_tile = quest.clone();
+            }
+        }
+    }
+}
+
+/// What recording a save did to the timeline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Recorded {
+    /// The save continues the game and became a new step.
+    Step,
+    /// Neither tiles nor counters changed.
+    Unchanged,
+    /// The first save, or one of another game. The timeline starts over with it.
+    NewGame,
+}
+
+#[derive(Clone, Default)]
+pub struct Timeline {
+    pub steps: Vec<TimelineStep>,
+    /// The latest save, the template for the saves of earlier steps.
+    latest: Option<SaveGame>,
+    /// Positions of all recorded tiles.
+    occupied: HashSet<HexPos>,
+    /// Quests of the recorded tiles that have one, as of the last step.
+    quests: HashMap<HexPos, QuestTile>,
+}
+
+impl Timeline {
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Add a loaded save. Like `Map::extend_from`, a save that lacks some of the recorded
+    /// tiles belongs to another game.
+    pub fn record(&mut self, savegame: &SaveGame) -> Recorded {
+        let positions: Vec<HexPos> = savegame
+            .tiles
+            .iter()
+            .map(|tile| offset_to_hex(tile.s, tile.t))
+            .collect();
+        let current: HashSet<HexPos> = positions.iter().copied().collect();
+        let continues = !self.steps.is_empty() && self.occupied.is_subset(&current);
+        if !continues {
+            self.steps.clear();
+            self.occupied.clear();
+            self.quests.clear();
+        }
+
+        let counters = Counters::from(savegame);
+        let added: Vec<Tile> = positions
+            .iter()
+            .zip(&savegame.tiles)
+            .filter(|(pos, _)| !self.occupied.contains(pos))
+            .map(|(_, tile)| tile.clone())
+            .collect();
+        let quests_changed: Vec<(HexPos, Option<QuestTile>)> = positions
+            .iter()
+            .zip(&savegame.tiles)
+            .filter(|(pos, tile)| {
+                self.occupied.contains(pos) && self.quests.get(pos) != tile.quest_tile.as_ref()
+            })
+            .map(|(pos, tile)| (*pos, tile.quest_tile.clone()))
+            .collect();
+        self.latest = Some(savegame.clone());
+        if continues
+            && added.is_empty()
+            && quests_changed.is_empty()
+            && self.steps.last().map(|step| step.counters) == Some(counters)
+        {
+            return Recorded::Unchanged;
+        }
+
+        self.occupied = current;
+        self.quests = positions
+            .iter()
+            .zip(&savegame.tiles)
+            .filter_map(|(pos, tile)| Some((*pos, tile.quest_tile.clone()?)))
+            .collect();
+        self.steps.push(TimelineStep {
+            added,
+            quests_changed,
+            counters,
+            tile_stack: savegame.tile_stack.clone(),
+            preplaced_tiles: savegame.preplaced_tiles.clone(),
+        });
+        if continues {
+            Recorded::Step
+        } else {
+            Recorded::NewGame
+        }
+    }
+
+    /// How the counters changed with step `index`. `None` for the first step.
+    pub fn change(&self, index: usize) -> Option<Counters> {
+        let previous = self.steps.get(index.checked_sub(1)?)?;
+        Some(self.steps.get(index)?.counters.since(&previous.counters))
+    }
+
+    /// The save as it was at step `index`. The last step is the latest save.
+    pub fn savegame_at(&self, index: usize) -> Option<SaveGame> {
+        let step = self.steps.get(index)?;
+        if index + 1 == self.steps.len() {
+            return self.latest.clone();
+        }
+        let mut savegame = self.latest.clone()?;
+        savegame.tiles = Vec::new();
+        for step in &self.steps[..=index] {
+            step.apply_quest_changes(&mut savegame.tiles);
+            savegame.tiles.extend(step.added.iter().cloned());
+        }
+        savegame.tile_stack = step.tile_stack.clone();
+        savegame.preplaced_tiles = step.preplaced_tiles.clone();
+        step.counters.apply_to(&mut savegame);
+        Some(savegame)
+    }
+
+    /// The save of the last step as it was loaded.
+    pub fn latest(&self) -> Option<&SaveGame> {
+        self.latest.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counters_since() {
+        let before = Counters {
+            score: 1200,
+            quests_fulfilled: 4,
+            tile_stack_count: 20,
+            placed_tile_count: 50,
+            ..Counters::default()
+        };
+        let after = Counters {
+            score: 1290,
+            quests_fulfilled: 5,
+            tile_stack_count: 23,
+            placed_tile_count: 52,
+            ..before
+        };
+        let change = after.since(&before);
+        assert_eq!(change.score, 90);
+        assert_eq!(change.quests_fulfilled, 1);
+        assert_eq!(change.tile_stack_count, 3);
+        assert_eq!(change.placed_tile_count, 2);
+        assert_eq!(change.level, 0);
+    }
+
+    #[test]
+    fn test_empty_timeline() {
+        let timeline = Timeline::default();
+        assert!(timeline.is_empty());
+        assert!(timeline.savegame_at(0).is_none());
+        assert!(timeline.change(0).is_none());
+        assert!(timeline.latest().is_none());
+    }
+}
@@ -1,11 +1,19 @@
+use std::{collections::HashMap, time::SystemTime};
+
 use egui::{Color32, Label, Pos2, Sense};
 
 use crate::{
-    data::Terrain, file_watcher::FileWatcher, game_data::GameData, render::camera::Camera,
+    data::Terrain,
+    file_watcher::{FileWatcher, ReviewExport},
+    game_data::GameData,
+    render::camera::Camera,
+    tile_frequency::EdgePattern,
 };
 
 use super::input_state::InputState;
-use super::ui_state::{ClosedGroupStyle, QuestDisplay, SectionStyle, TooltipMode, UiState};
+use super::ui_state::{
+    ClosedGroupStyle, QuestDisplay, ScorerChoice, SectionStyle, TooltipMode, UiState,
+};
 
 /// Top panel with title and some menus.
 fn render_top_panel(ui_state: &mut UiState, file_watcher: &mut FileWatcher, ctx: &egui::Context) {
@@ -19,7 +27,20 @@ fn render_top_panel(ui_state: &mut UiState, file_watcher: &mut FileWatcher, ctx:
                 )
                 .clicked()
             {
-                file_watcher.file_choose_dialog.open();
+                file_watcher.open_file_dialog();
+            }
+            if ui
+                .add_enabled(
+                    !file_watcher.file_choose_dialog.is_open(),
+                    egui::Button::new("Watch saves folder"),
+                )
+                .on_hover_text("Follow the slot the game wrote last")
+                .clicked()
+            {
+                file_watcher.open_folder_dialog();
+            }
+            if file_watcher.saves_dir.is_some() {
+                ui.toggle_value(&mut ui_state.show_save_slots, "Save slots");
             }
             ui.toggle_value(&mut ui_state.sidebar_expanded, "Visual settings");
             ui.toggle_value(&mut ui_state.show_tile_frequencies, "Tile frequencies");
@@ -81,6 +102,11 @@ fn render_side_panel(
                     TooltipMode::Placement,
                     "Placement",
                 );
+                ui.selectable_value(
+                    &mut ui_state.tooltip_mode,
+                    TooltipMode::Rotations,
+                    "Rotations",
+                );
                 ui.selectable_value(&mut ui_state.tooltip_mode, TooltipMode::Chance, "Chance");
             });
             ui.checkbox(&mut ui_state.show_biggest_groups, "Show biggest groups");
@@ -88,6 +114,10 @@ fn render_side_panel(
                 &mut ui_state.show_imperfect_tiles,
                 "Highlight imperfect tiles",
             );
+            ui.checkbox(&mut ui_state.show_holes, "Highlight holes");
+            ui.checkbox(&mut ui_state.show_lookahead, "Show lookahead");
+            ui.checkbox(&mut ui_state.show_rollouts, "Show rollouts");
+            ui.checkbox(&mut ui_state.show_timeline, "Show timeline");
             ui.add_space(10.0);
 
             ui.label(egui::RichText::new("Section style").size(20.0).underline());
@@ -159,6 +189,27 @@ fn render_side_panel(
                     .size(20.0)
                     .underline(),
             );
+            ui.horizontal(|ui| {
+                ui.label("Ranking");
+                ui.selectable_value(&mut ui_state.scorer, ScorerChoice::Default, "Default");
+                ui.selectable_value(&mut ui_state.scorer, ScorerChoice::Points, "Points");
+                ui.selectable_value(&mut ui_state.scorer, ScorerChoice::Weighted, "Weighted");
+                if ui_state.scorer == ScorerChoice::Weighted
+                    && ui.button("Reload weights").clicked()
+                {
+                    ui_state.reload_weights = true;
+                }
+            });
+            let survival = data.survival();
+            let turns = match survival.turns {
+                Some(turns) => format!("~{turns:.0} turns left"),
+                None => "stack grows".to_string(),
+            };
+            ui.label(format!(
+                "{} tiles, {turns}, {:.0}% game-over risk",
+                survival.tiles_left,
+                survival.risk * 100.0
+            ));
             // Select/unselect all.
             let all_count = data
                 .best_placements
@@ -532,22 +583,113 @@ fn draw_mini_hex_segments(ui: &mut egui::Ui, segments: &[crate::data::Segment],
     ));
 }
 
-fn render_tile_frequencies(data: &GameData, ui_state: &mut UiState, ctx: &egui::Context) {
+/// How long ago `time` was, roughly.
+fn ago(time: SystemTime) -> String {
+    let seconds = SystemTime::now()
+        .duration_since(time)
+        .unwrap_or_default()
+        .as_secs();
+    match seconds {
+        0..=59 => format!("{seconds} s ago"),
+        60..=3599 => format!("{} min ago", seconds / 60),
+        3600..=86399 => format!("{} h ago", seconds / 3600),
+        _ => format!("{} d ago", seconds / 86400),
+    }
+}
+
+/// The slots of the saves directory. Picking one watches it until the game writes another.
+fn render_save_slots(file_watcher: &mut FileWatcher, ui_state: &mut UiState, ctx: &egui::Context) {
+    let Some(dir) = file_watcher.saves_dir.clone() else {
+        return;
+    };
+    if !ui_state.show_save_slots {
+        return;
+    }
+    egui::Window::new("Save slots")
+        .open(&mut ui_state.show_save_slots)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(dir.display().to_string());
+                let scanner = &mut file_watcher.slot_scanner;
+                if scanner.in_progress() {
+                    ui.add(egui::Spinner::default().size(14.0));
+                } else if ui.button("Refresh").clicked() {
+                    scanner.scan(&dir);
+                }
+            });
+            if let Some(error) = &file_watcher.slot_scanner.last_error {
+                ui.colored_label(Color32::LIGHT_RED, error);
+            }
+
+            let mut chosen = None;
+            egui::Grid::new("save_slots").striped(true).show(ui, |ui| {
+                for header in ["Slot", "Level", "Score", "Tiles", "Version", "Written"] {
+                    ui.label(egui::RichText::new(header).strong());
+                }
+                ui.end_row();
+                for slot in &file_watcher.slot_scanner.slots {
+                    let mut name = egui::RichText::new(slot.name());
+                    if file_watcher.file.as_ref() == Some(&slot.path) {
+                        name = name.strong().color(Color32::LIGHT_GREEN);
+                    }
+                    if ui.add(Label::new(name).sense(Sense::click())).clicked() {
+                        chosen = Some(slot.path.clone());
+                    }
+                    match &slot.info {
+                        Ok(info) => {
+                            ui.label(info.level.to_string());
+                            ui.label(info.score.to_string());
+                            ui.label(info.placed_tile_count.to_string());
+                            ui.label(&info.last_played_version);
+                        }
+                        Err(error) => {
+                            ui.colored_label(Color32::LIGHT_RED, "unreadable")
+                                .on_hover_text(error);
+                            ui.label("");
+                            ui.label("");
+                            ui.label("");
+                        }
+                    }
+                    ui.label(ago(slot.modified));
+                    ui.end_row();
+                }
+            });
+            if let Some(slot) = chosen {
+                file_watcher.choose_slot(&slot);
+            }
+        });
+}
+
+fn render_tile_frequencies(data: &mut GameData, ui_state: &mut UiState, ctx: &egui::Context) {
     if !ui_state.show_tile_frequencies {
         return;
     }
+    let demand = data.demand();
+    let frontier = format!(
+        "Frontier: {} cells, {} no known tile fits perfectly",
+        demand.cells.len(),
+        demand.unserved().count()
+    );
+    let served = demand.served_cells();
+    let needs: HashMap<EdgePattern, (usize, f32)> = demand
+        .patterns
+        .iter()
+        .map(|need| (need.pattern.clone(), (need.cells, need.pressure(served))))
+        .collect();
+    let freqs = &data.tile_frequencies;
     egui::Window::new("Tile Frequencies")
         .open(&mut ui_state.show_tile_frequencies)
         .default_width(500.0)
         .default_height(600.0)
         .vscroll(true)
         .show(ctx, |ui| {
-            let freqs = &data.tile_frequencies;
             ui.label(format!(
                 "Total: {} tiles, {} distinct patterns",
                 freqs.total_tiles,
                 freqs.entries.len()
             ));
+            ui.label(&frontier);
             ui.add_space(5.0);
 
             let hex_size = 20.0;
@@ -557,12 +699,27 @@ fn render_tile_frequencies(data: &GameData, ui_state: &mut UiState, ctx: &egui::
                     ui.label("");
                     ui.label("Count");
                     ui.label("%");
+                    ui.label("Cells")
+                        .on_hover_text("Frontier cells the pattern fits perfectly");
+                    ui.label("Pressure").on_hover_text(
+                        "How much more often the frontier needs the pattern than it comes up",
+                    );
                     ui.end_row();
 
                     for entry in &freqs.entries {
                         draw_mini_hex_segments(ui, &entry.segments, hex_size);
                         ui.label(format!("{}", entry.count));
                         ui.label(format!("{:.1}%", entry.fraction * 100.0));
+                        match needs.get(&entry.edges) {
+                            Some((cells, pressure)) => {
+                                ui.label(cells.to_string());
+                                ui.label(format!("{pressure:.2}"));
+                            }
+                            None => {
+                                ui.label("");
+                                ui.label("");
+                            }
+                        }
                         ui.end_row();
                     }
                 });
@@ -720,6 +877,44 @@ fn render_placement_detail(
                                     .color(crowd_color),
                             );
                             ui.end_row();
+
+                            if score.preplaced_neighbors > 0 {
+                                ui.label("Preplaced neighbors");
+                                ui.label(
+                                    egui::RichText::new(format!("{}", score.preplaced_neighbors))
+                                        .color(Color32::WHITE),
+                                );
+                                ui.end_row();
+                            }
+
+                            ui.label("Points");
+                            let tiles = if score.points.tiles > 0 {
+                                format!(" +{} tiles", score.points.tiles)
+                            } else {
+                                String::new()
+                            };
+                            ui.label(
+                                egui::RichText::new(format!("{}{tiles}", score.points.points))
+                                    .color(Color32::WHITE),
+                            );
+                            ui.end_row();
+
+                            let model = data.survival_model();
+                            let tiles_left = data.map.tile_stack_count;
+                            let groups = &data.group_assignments;
+                            let after = model.estimate_after(tiles_left, groups, score);
+                            if let Some(delta) =
+                                after.turns_delta(&model.estimate(tiles_left, groups))
+                            {
+                                ui.label("Turns");
+                                let color = if delta >= 0.0 {
+                                    Color32::from_rgb(80, 200, 80)
+                                } else {
+                                    Color32::from_rgb(220, 80, 80)
+                                };
+                                ui.label(egui::RichText::new(format!("{delta:+.1}")).color(color));
+                                ui.end_row();
+                            }
                         });
 
                     // Neighbor fit effects.
@@ -875,22 +1070,10 @@ fn render_next_tile(data: &GameData, ctx: &egui::Context) {
 
             // Draw quest info below the hex if present.
             if let Some(quest) = &data.map.next_tile_quest {
-                use crate::map::QuestType;
-                let text = if quest.quest_type == QuestType::Flag {
-                    format!("{:?} flag", quest.terrain)
-                } else {
-                    let suffix = match quest.quest_type {
-                        QuestType::MoreThan => "+",
-                        QuestType::Exact => "",
-                        QuestType::Flag => "",
-                        QuestType::Unknown => "?",
-                    };
-                    format!("{:?} {}{}", quest.terrain, quest.target_value, suffix)
-                };
                 painter.text(
                     Pos2::new(center.x, response.rect.max.y - 8.0),
                     egui::Align2::CENTER_BOTTOM,
-                    text,
+                    quest_short_label(quest),
                     egui::FontId::proportional(13.0),
                     Color32::WHITE,
                 );
@@ -898,8 +1081,54 @@ fn render_next_tile(data: &GameData, ctx: &egui::Context) {
         });
 }
 
-/// Render always-visible quest labels centered on each group that has active quests.
-/// `visible_rect` is the area not covered by panels (sidebar, top bar).
+/// Short quest description shown below upcoming tiles, e.g. "Forest 40+".
+fn quest_short_label(quest: &crate::map::Quest) -> String {
+    use crate::map::QuestType;
+    if quest.quest_type == QuestType::Flag {
+        format!("{:?} flag", quest.terrain)
+    } else {
+        let suffix = match quest.quest_type {
+            QuestType::MoreThan => "+",
+            QuestType::Exact => "",
+            QuestType::Flag => "",
+            QuestType::Unknown => "?",
+        };
+        format!("{:?} {}{}", quest.terrain, quest.target_value, suffix)
+    }
+}
+
+/// Strip of mini hexes for the known tiles after the next one, placed left of the next tile.
+fn render_tile_queue(data: &GameData, ctx: &egui::Context) {
+    let queue_len = data.map.tile_queue.len();
+    if queue_len < 2 {
+        return;
+    }
+
+    // Keep clear of the next tile widget (2 * 80 + 10 wide, 20 from the screen edge).
+    egui::Area::new("tile_queue")
+        .anchor(egui::Align2::RIGHT_BOTTOM, (-200.0, -20.0))
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.label(format!(
+                    "Upcoming ({} left in stack)",
+                    data.map.tile_stack_count
+                ));
+                ui.horizontal(|ui| {
+                    for (offset, (segments, quest)) in data.map.upcoming_tiles().enumerate().skip(1)
+                    {
+                        ui.vertical(|ui| {
+                            ui.label(format!("+{offset}"));
+                            draw_mini_hex_segments(ui, segments, 22.0);
+                            if let Some(quest) = quest {
+                                ui.label(egui::RichText::new(quest_short_label(quest)).small());
+                            }
+                        });
+                    }
+                });
+            });
+        });
+}
+
 /// Threshold for "easy" quests per terrain.
 fn easy_quest_threshold(terrain: Terrain) -> i32 {
     match terrain {
@@ -911,8 +1140,10 @@ fn easy_quest_threshold(terrain: Terrain) -> i32 {
     }
 }
 
+/// Render always-visible quest labels centered on each group that has active quests.
+/// `visible_rect` is the area not covered by panels (sidebar, top bar).
 fn render_group_quest_labels(
-    data: &GameData,
+    data: &mut GameData,
     camera: &Camera,
     ui_state: &mut UiState,
     ctx: &egui::Context,
@@ -923,6 +1154,13 @@ fn render_group_quest_labels(
     }
 
     let mode = ui_state.quest_display;
+    // Exact hits and lost quests, see `quest_feasibility`.
+    let statuses: HashMap<(usize, i32), String> = data
+        .quest_feasibility()
+        .iter()
+        .map(|f| ((f.group, f.quest.quest_id), f.label()))
+        .filter(|(_, status)| !status.is_empty())
+        .collect();
 
     for (group_idx, group) in data.group_assignments.groups.iter().enumerate() {
         let mut active_quests: Vec<_> = group
@@ -964,7 +1202,7 @@ fn render_group_quest_labels(
             .iter()
             .map(|(quest, remaining)| {
                 use crate::map::QuestType;
-                if quest.quest_type == QuestType::Flag && *remaining <= 0 {
+                let label = if quest.quest_type == QuestType::Flag && *remaining <= 0 {
                     format!("{:?} close", quest.terrain)
                 } else {
                     let suffix = match quest.quest_type {
@@ -973,6 +1211,10 @@ fn render_group_quest_labels(
                         QuestType::Unknown => "?",
                     };
                     format!("{:?} {remaining}{suffix}", quest.terrain)
+                };
+                match statuses.get(&(group_idx, quest.quest_id)) {
+                    Some(status) => format!("{label} ({status})"),
+                    None => label,
                 }
             })
             .collect::<Vec<_>>()
@@ -1003,7 +1245,6 @@ fn render_biggest_groups(
         return;
     }
 
-    use std::collections::HashMap;
     let mut top3: HashMap<Terrain, Vec<(usize, &crate::group::Group)>> = HashMap::new();
     for (idx, group) in data.group_assignments.groups.iter().enumerate() {
         if group.is_closed() {
@@ -1120,6 +1361,10 @@ fn render_status_bar(
                 ui.label("Loading map...");
                 ui.separator();
             }
+            if let Some(error) = &file_watcher.map_loader.last_error {
+                ui.colored_label(egui::Color32::LIGHT_RED, error);
+                ui.separator();
+            }
             ui.label(&game_nav.detect_status);
         });
     });
@@ -1171,12 +1416,34 @@ pub fn render_ui(
     if ui_state.tooltip_mode == TooltipMode::Placement {
         render_placement_detail(data, input, camera, ctx);
     }
+    if ui_state.tooltip_mode == TooltipMode::Rotations {
+        render_placement_rotations(data, input, camera, ctx);
+    }
     if ui_state.tooltip_mode == TooltipMode::Chance {
         render_placement_chance(data, input, camera, ctx);
     }
     if ui_state.show_imperfect_tiles {
         render_imperfect_tiles(data, camera, ctx, visible_rect);
     }
+    if ui_state.show_holes {
+        render_holes(data, camera, ctx, visible_rect);
+    }
+    if ui_state.show_lookahead {
+        render_lookahead(data, camera, ctx, visible_rect);
+    }
+    if ui_state.show_rollouts {
+        render_rollouts(data, camera, ctx, visible_rect);
+    }
+    if ui_state.show_timeline {
+        render_timeline(
+            data,
+            camera,
+            ui_state,
+            &file_watcher.review_export,
+            ctx,
+            visible_rect,
+        );
+    }
     // Highlight focused placement.
     if let Some(pos) = ui_state.focused_placement {
         let px = camera.hex_to_pixel(pos);
@@ -1210,7 +1477,9 @@ pub fn render_ui(
         }
     }
     render_tile_frequencies(data, ui_state, ctx);
+    render_save_slots(file_watcher, ui_state, ctx);
     render_next_tile(data, ctx);
+    render_tile_queue(data, ctx);
     render_game_camera_marker(game_nav, camera, ctx, visible_rect);
     visible_rect
 }
@@ -1246,10 +1515,364 @@ fn render_imperfect_tiles(
     }
 }
 
+/// Fill the cells of enclosed holes: red where no known tile matches every neighbor, amber
+/// otherwise, fainter the easier a cell is to fill. Larger holes are labeled with their size.
+fn render_holes(
+    data: &mut GameData,
+    camera: &Camera,
+    ctx: &egui::Context,
+    visible_rect: egui::Rect,
+) {
+    let holes = data.holes();
+    let mut painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Background,
+        egui::Id::new("holes"),
+    ));
+    painter.set_clip_rect(visible_rect);
+
+    let dx = camera.world_dist_to_pixels(1.0);
+    let radius = dx * 0.5;
+    let visible = |px: Pos2| visible_rect.expand(radius).contains(px);
+
+    for hole in &holes.holes {
+        for cell in &hole.cells {
+            let px = camera.hex_to_pixel(cell.pos);
+            let px = Pos2::new(px.x(), px.y());
+            if !visible(px) {
+                continue;
+            }
+            let alpha = (60.0 + 140.0 * (1.0 - cell.fit_chance)) as u8;
+            let color = if cell.perfect_chance == 0.0 {
+                Color32::from_rgba_unmultiplied(255, 40, 40, alpha)
+            } else {
+                Color32::from_rgba_unmultiplied(255, 170, 40, alpha)
+            };
+            painter.circle_filled(px, radius, color);
+        }
+        if hole.size() > 1 {
+            let px = camera.hex_to_pixel(hole.cells[0].pos);
+            let px = Pos2::new(px.x(), px.y());
+            if visible(px) {
+                painter.text(
+                    px,
+                    egui::Align2::CENTER_CENTER,
+                    hole.size().to_string(),
+                    egui::FontId::proportional(14.0),
+                    Color32::WHITE,
+                );
+            }
+        }
+    }
+}
+
+/// Number the cells of the best sequence for the known tiles, and list it next to greedy play.
+fn render_rollouts(
+    data: &mut GameData,
+    camera: &mut Camera,
+    ctx: &egui::Context,
+    visible_rect: egui::Rect,
+) {
+    let rollouts = data.rollouts();
+    egui::Window::new("Rollouts")
+        .default_pos((visible_rect.min.x + 10.0, visible_rect.max.y - 200.0))
+        .resizable(false)
+        .show(ctx, |ui| {
+            let mut clicked = None;
+            egui::Grid::new("rollouts_table").show(ui, |ui| {
+                for header in ["Pos", "Rot", "Rank", "Fit%", "Value", "Dead", "Stuck"] {
+                    ui.label(egui::RichText::new(header).strong());
+                }
+                ui.end_row();
+                for result in &rollouts.results {
+                    let pos = format!("{},{}", result.pos.x(), result.pos.y());
+                    if ui.add(Label::new(pos).sense(Sense::click())).clicked() {
+                        clicked = Some(result.pos);
+                    }
+                    ui.label(result.rotation.to_string());
+                    ui.label((result.rank + 1).to_string());
+                    ui.label(format!("{:.1}", result.fit_chance * 100.0));
+                    ui.label(format!("{:.2}", result.mean_value));
+                    ui.label(format!("{:.2}", result.mean_dead_cells));
+                    ui.label(format!("{:.0}%", result.stuck_rate * 100.0));
+                    ui.end_row();
+                }
+            });
+            if let Some(pos) = clicked {
+                camera.goto(pos);
+            }
+        });
+}
+
+/// Step through the saves of the game. The tiles placed in the shown step are circled.
+fn render_timeline(
+    data: &GameData,
+    camera: &mut Camera,
+    ui_state: &mut UiState,
+    review_export: &ReviewExport,
+    ctx: &egui::Context,
+    visible_rect: egui::Rect,
+) {
+    const LISTED_POSITIONS: usize = 12;
+
+    let timeline = &data.timeline;
+    egui::Window::new("Timeline")
+        .default_pos((visible_rect.max.x - 340.0, visible_rect.max.y - 240.0))
+        .resizable(false)
+        .show(ctx, |ui| {
+            if timeline.is_empty() {
+                ui.label("No save loaded yet");
+                return;
+            }
+            ui.horizontal(|ui| {
+                let can_export = timeline.len() > 1 && !review_export.in_progress();
+                if ui
+                    .add_enabled(can_export, egui::Button::new("Export review"))
+                    .on_hover_text("Compare every placed tile with the recommendations")
+                    .clicked()
+                {
+                    ui_state.export_review = true;
+                }
+                if review_export.in_progress() {
+                    ui.add(egui::Spinner::default().size(14.0));
+                } else if let Some(result) = &review_export.last_result {
+                    match result {
+                        Ok(path) => ui.label(format!("Saved {}", path.display())),
+                        Err(error) => ui.colored_label(Color32::LIGHT_RED, error),
+                    };
+                }
+            });
+            let last = timeline.len() - 1;
+            let mut index = ui_state.timeline_step.unwrap_or(last);
+            ui.horizontal(|ui| {
+                if ui.button("<").clicked() {
+                    index = index.saturating_sub(1);
+                }
+                ui.add(egui::Slider::new(&mut index, 0..=last).text("Step"))
+                    .on_hover_text(
+                        "Earlier steps are rebuilt from the loaded saves. Quests only change \
+                         with the next save, not with the placement that changed them",
+                    );
+                if ui.button(">").clicked() {
+                    index = (index + 1).min(last);
+                }
+                if ui.button("Latest").clicked() {
+                    index = last;
+                }
+            });
+            ui_state.timeline_step = (index < last).then_some(index);
+
+            let step = &timeline.steps[index];
+            let change = timeline.change(index).unwrap_or_default();
+            egui::Grid::new("timeline_counters").show(ui, |ui| {
+                for (label, value, delta) in [
+                    ("Score", step.counters.score, change.score),
+                    (
+                        "Quests fulfilled",
+                        step.counters.quests_fulfilled,
+                        change.quests_fulfilled,
+                    ),
+                    (
+                        "Tile stack",
+                        step.counters.tile_stack_count,
+                        change.tile_stack_count,
+                    ),
+                ] {
+                    ui.label(label);
+                    ui.label(value.to_string());
+                    ui.label(format!("{delta:+}"));
+                    ui.end_row();
+                }
+            });
+
+            if index == 0 {
+                ui.label(format!("First save, {} tiles", step.added.len()));
+                return;
+            }
+            ui.label(format!("{} tiles placed", step.added.len()));
+            let mut clicked = None;
+            ui.horizontal_wrapped(|ui| {
+                for pos in step.positions().take(LISTED_POSITIONS) {
+                    let text = format!("{},{}", pos.x(), pos.y());
+                    if ui.add(Label::new(text).sense(Sense::click())).clicked() {
+                        clicked = Some(pos);
+                    }
+                }
+                if step.added.len() > LISTED_POSITIONS {
+                    ui.label(format!("+{} more", step.added.len() - LISTED_POSITIONS));
+                }
+            });
+            if let Some(pos) = clicked {
+                camera.goto(pos);
+            }
+        });
+
+    let Some(index) = ui_state.timeline_step.or(timeline.len().checked_sub(1)) else {
+        return;
+    };
+    if index == 0 {
+        return;
+    }
+    let mut painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Middle,
+        egui::Id::new("timeline"),
+    ));
+    painter.set_clip_rect(visible_rect);
+    let radius = camera.world_dist_to_pixels(0.9);
+    for pos in timeline.steps[index].positions() {
+        let px = camera.hex_to_pixel(pos);
+        painter.circle_stroke(
+            Pos2::new(px.x(), px.y()),
+            radius,
+            egui::Stroke::new(3.0, Color32::from_rgb(80, 220, 220)),
+        );
+    }
+}
+
+fn render_lookahead(
+    data: &mut GameData,
+    camera: &mut Camera,
+    ctx: &egui::Context,
+    visible_rect: egui::Rect,
+) {
+    let lookahead = data.lookahead().clone();
+    let mut painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Middle,
+        egui::Id::new("lookahead"),
+    ));
+    painter.set_clip_rect(visible_rect);
+    let radius = camera.world_dist_to_pixels(0.6);
+    for (index, step) in lookahead.sequence.iter().enumerate() {
+        let px = camera.hex_to_pixel(step.pos);
+        let center = Pos2::new(px.x(), px.y());
+        painter.circle_filled(
+            center,
+            radius,
+            Color32::from_rgba_premultiplied(30, 30, 30, 200),
+        );
+        painter.text(
+            center,
+            egui::Align2::CENTER_CENTER,
+            (index + 1).to_string(),
+            egui::FontId::proportional(radius.clamp(10.0, 24.0)),
+            Color32::from_rgb(120, 220, 120),
+        );
+    }
+
+    egui::Window::new("Lookahead")
+        .default_pos((visible_rect.min.x + 10.0, visible_rect.min.y + 10.0))
+        .resizable(false)
+        .show(ctx, |ui| {
+            let mut clicked = None;
+            egui::Grid::new("lookahead_table").show(ui, |ui| {
+                for header in ["", "Tile", "Pos", "Rot", "Edges", "Dead", "Value"] {
+                    ui.label(egui::RichText::new(header).strong());
+                }
+                ui.end_row();
+                for (label, steps) in [("Best", &lookahead.sequence), ("Greedy", &lookahead.greedy)]
+                {
+                    for (index, step) in steps.iter().enumerate() {
+                        ui.label(if index == 0 { label } else { "" });
+                        ui.label(format!("+{index}"));
+                        let pos = format!("{},{}", step.pos.x(), step.pos.y());
+                        if ui.add(Label::new(pos).sense(Sense::click())).clicked() {
+                            clicked = Some(step.pos);
+                        }
+                        ui.label(step.rotation.to_string());
+                        let perfect = if step.value.perfect { " perfect" } else { "" };
+                        ui.label(format!("{}{perfect}", step.value.matching_edges));
+                        ui.label(step.value.dead_cells.to_string());
+                        ui.label(step.value.total().to_string());
+                        ui.end_row();
+                    }
+                }
+            });
+            ui.label(format!(
+                "Value {} vs {} greedy (+{})",
+                lookahead.total(),
+                lookahead.greedy_total(),
+                lookahead.improvement()
+            ));
+            if let Some(pos) = clicked {
+                camera.goto(pos);
+            }
+        });
+}
+
 /// Show placement chance: for each valid placement near hover, compute
 /// how many known tile patterns would fit and the probability.
 /// Show placement chance: for the hovered empty position, compute
 /// how many known tile patterns would fit (matching all occupied neighbor edges).
+/// List every legal rotation of the next tile at the hovered cell, best first. Rotations the
+/// ranking cannot tell apart from the best one are marked.
+fn render_placement_rotations(
+    data: &GameData,
+    input: &InputState,
+    camera: &Camera,
+    ctx: &egui::Context,
+) {
+    let pos = input.hover_pos;
+    let rotations = data.best_placements.by_position(pos);
+    if rotations.is_empty() {
+        return;
+    }
+    let ties = data.best_placements.tied_rotations(pos).len();
+
+    let pixel_pos = camera.hex_to_pixel(pos);
+    let window_pos = Pos2::new(pixel_pos.x() + 30.0, pixel_pos.y() - 100.0);
+
+    egui::Area::new(egui::Id::new("placement_rotations"))
+        .fixed_pos(window_pos)
+        .order(egui::Order::Tooltip)
+        .interactable(false)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style())
+                .fill(Color32::from_black_alpha(220))
+                .show(ui, |ui| {
+                    egui::Grid::new("placement_rotations_grid")
+                        .spacing([10.0, 4.0])
+                        .show(ui, |ui| {
+                            for header in ["", "Rot", "Edges", "Points", "Diff", "Crowd"] {
+                                ui.label(egui::RichText::new(header).strong());
+                            }
+                            ui.end_row();
+                            for (index, score) in rotations.iter().enumerate() {
+                                let rotated: Vec<_> = data
+                                    .map
+                                    .next_tile
+                                    .iter()
+                                    .map(|segment| crate::data::Segment {
+                                        rotation: (segment.rotation + score.rotation)
+                                            % crate::data::HEX_SIDES,
+                                        ..segment.clone()
+                                    })
+                                    .collect();
+                                draw_mini_hex_segments(ui, &rotated, 14.0);
+                                let color = if index < ties {
+                                    Color32::from_rgb(80, 200, 80)
+                                } else {
+                                    Color32::WHITE
+                                };
+                                let cell = |ui: &mut egui::Ui, text: String| {
+                                    ui.label(egui::RichText::new(text).color(color));
+                                };
+                                cell(ui, score.rotation.to_string());
+                                cell(ui, score.matching_edges.to_string());
+                                cell(ui, score.points.points.to_string());
+                                cell(ui, score.connection_difficulty.to_string());
+                                cell(ui, score.crowding.to_string());
+                                ui.end_row();
+                            }
+                        });
+                    if ties > 1 {
+                        ui.label(
+                            egui::RichText::new(format!("{ties} rotations tie for best"))
+                                .color(Color32::GRAY),
+                        );
+                    }
+                });
+        });
+}
+
 fn render_placement_chance(
     data: &GameData,
     input: &InputState,
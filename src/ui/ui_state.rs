@@ -6,6 +6,8 @@ pub enum TooltipMode {
     None,
     Group,
     Placement,
+    /// Every legal rotation of the next tile at the hovered cell.
+    Rotations,
     Chance,
 }
 
@@ -37,6 +39,16 @@ pub enum SectionStyle {
     RailRiverOnly = 4,
 }
 
+/// How placements are ranked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScorerChoice {
+    Default,
+    /// Expected points of the placement.
+    Points,
+    /// Weighted sum with the weights from the config directory.
+    Weighted,
+}
+
 #[allow(clippy::struct_excessive_bools)]
 pub struct UiState {
     pub goto_x: String,
@@ -46,11 +58,26 @@ pub struct UiState {
     pub highlight_hovered_group: bool,
     pub show_placements: [bool; MAX_SHOWN_PLACEMENTS],
     pub tooltip_mode: TooltipMode,
+    pub scorer: ScorerChoice,
+    /// Set to read the weights file again.
+    pub reload_weights: bool,
     pub show_biggest_groups: bool,
     /// Currently focused/highlighted group (from clicking in the groups overlay).
     pub focused_group: Option<usize>,
     pub show_tile_frequencies: bool,
+    pub show_save_slots: bool,
     pub show_imperfect_tiles: bool,
+    /// Highlight empty regions enclosed by placed tiles.
+    pub show_holes: bool,
+    /// Show the best placements for the known tiles together.
+    pub show_lookahead: bool,
+    /// Show the best placements rated by rollouts.
+    pub show_rollouts: bool,
+    pub show_timeline: bool,
+    /// Timeline step to show instead of the latest save.
+    pub timeline_step: Option<usize>,
+    /// Set when the user asks for a review of the timeline, cleared by the app.
+    pub export_review: bool,
     pub quest_display: QuestDisplay,
     pub sidebar_expanded: bool,
     /// The currently focused/highlighted placement position (from clicking a row).
@@ -68,9 +95,18 @@ impl Default for UiState {
             highlight_hovered_group: false,
             show_placements: [false; MAX_SHOWN_PLACEMENTS],
             tooltip_mode: TooltipMode::Placement,
+            scorer: ScorerChoice::Default,
+            reload_weights: false,
             show_biggest_groups: false,
             show_tile_frequencies: false,
+            show_save_slots: false,
             show_imperfect_tiles: false,
+            show_holes: false,
+            show_lookahead: false,
+            show_rollouts: false,
+            show_timeline: false,
+            timeline_step: None,
+            export_review: false,
             quest_display: QuestDisplay::Min,
             sidebar_expanded: true,
             focused_placement: None,
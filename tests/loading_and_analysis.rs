@@ -6,7 +6,9 @@ use dorfromantische2_rs::data::{
 };
 use dorfromantische2_rs::group_assignments::GroupAssignments;
 use dorfromantische2_rs::map::Map;
-use dorfromantische2_rs::raw_data::{QuestTileId, SaveGame};
+use dorfromantische2_rs::nrbf_tree::{read_nrbf, write_nrbf, Node};
+use dorfromantische2_rs::raw_data::{QuestTileId, SaveGame, SaveGameTree};
+use dorfromantische2_rs::savegame_writer::SaveGameWriter;
 use std::io::Cursor;
 
 // ===========================================================================
@@ -28,6 +30,11 @@ fn load_raw(path: &str) -> nrbf_rs::value::Value {
     nrbf_rs::parse_nrbf(&mut Cursor::new(&data))
 }
 
+fn read_tree(path: &str) -> Node {
+    let file = std::fs::File::open(path).unwrap_or_else(|_| panic!("{path} not found"));
+    read_nrbf(file).unwrap_or_else(|e| panic!("Failed to read object tree of {path}: {e}"))
+}
+
 fn load_savegame(path: &str) -> SaveGame {
     let parsed = load_raw(path);
     SaveGame::try_from(&parsed).unwrap_or_else(|e| panic!("Failed to parse {path}: {e}"))
@@ -474,6 +481,30 @@ fn test_map_tiles_mostly_have_segments() {
     );
 }
 
+#[test]
+fn test_chunked_index_covers_tiles_and_frontier() {
+    use dorfromantische2_rs::map::CHUNK_AREA;
+
+    let sg = require_fixture!(load_dorfromantik());
+    let map = build_map(&sg);
+    let groups = analyze_groups(&map);
+
+    assert_eq!(map.tile_index.len(), map.chunks.len() * CHUNK_AREA);
+    assert_eq!(map.rendered_tiles.len(), map.tile_index.len());
+    for pos in map.iter_tile_positions() {
+        let key = map.tile_key(pos).unwrap();
+        assert!(map.tile_index[key].is_some());
+        assert!(
+            (0..HEX_SIDES).all(|side| map.tile_key(Map::neighbor_pos_of(pos, side)).is_some()),
+            "Neighbors of {pos:?} should be indexed"
+        );
+    }
+    assert!(groups
+        .possible_placements
+        .iter()
+        .all(|&pos| map.tile_key(pos).is_some()));
+}
+
 #[test]
 fn test_map_segment_terrains_valid() {
     let sg = require_fixture!(load_dorfromantik());
@@ -526,6 +557,25 @@ fn test_map_next_tile_rendered() {
     );
 }
 
+#[test]
+fn test_map_tile_queue_matches_stack() {
+    let sg = require_fixture!(load_dorfromantik());
+    let map = build_map(&sg);
+
+    assert_eq!(map.tile_queue.len(), sg.tile_stack.len());
+    assert_eq!(map.tile_queue_quests.len(), sg.tile_stack.len());
+    assert_eq!(map.tile_stack_count, sg.tile_stack_count);
+    assert!(map.tile_stack_count as usize >= map.tile_queue.len());
+
+    // The head of the queue is the next tile.
+    let (head, head_quest) = map.upcoming_tiles().next().unwrap();
+    assert_eq!(head.len(), map.next_tile.len());
+    assert_eq!(head_quest.is_some(), map.next_tile_quest.is_some());
+    for (segments, _) in map.upcoming_tiles() {
+        assert!(!segments.is_empty(), "Queued tile should have segments");
+    }
+}
+
 #[test]
 fn test_map_tile_positions_unique() {
     let sg = require_fixture!(load_dorfromantik());
@@ -1394,3 +1444,1861 @@ fn test_fit_chance_empirical_validation() {
     );
     println!("Validated {checked} tiles, {fit_nonzero} with nonzero fit chance ({pct:.1}%)");
 }
+
+// ===========================================================================
+// Savegame writer
+// ===========================================================================
+
+fn write_and_reload(writer: &SaveGameWriter) -> SaveGame {
+    let mut bytes = Vec::new();
+    writer
+        .write_to(&mut bytes)
+        .expect("Failed to write savegame");
+    let parsed = nrbf_rs::parse_nrbf(&mut Cursor::new(&bytes));
+    SaveGame::try_from(&parsed).unwrap_or_else(|e| panic!("Failed to parse written savegame: {e}"))
+}
+
+fn try_load_writer(path: &str) -> Option<(SaveGameWriter, SaveGame)> {
+    if !std::path::Path::new(path).exists() {
+        eprintln!("Skipping test: fixture {path} not found");
+        return None;
+    }
+    let writer = SaveGameWriter::try_from(read_tree(path)).expect("Failed to read object tree");
+    let savegame = SaveGame::try_from(&load_raw(path)).expect("Failed to parse savegame");
+    Some((writer, savegame))
+}
+
+#[test]
+fn test_writer_round_trip_unchanged() {
+    let (writer, original) = require_fixture!(try_load_writer("tests/fixtures/dorfromantik.dump"));
+    let reloaded = write_and_reload(&writer);
+    assert_eq!(format!("{reloaded:?}"), format!("{original:?}"));
+}
+
+#[test]
+fn test_writer_round_trip_keeps_tree() {
+    let (writer, _) = require_fixture!(try_load_writer("tests/fixtures/dorfromantik.dump"));
+    let mut bytes = Vec::new();
+    writer.write_to(&mut bytes).unwrap();
+    let reread = SaveGameWriter::try_from(read_nrbf(Cursor::new(&bytes)).unwrap()).unwrap();
+    assert_eq!(reread.root(), writer.root());
+
+    // The screenshot comes along.
+    let Some(Node::ByteArray(screenshot)) = reread.root().field("screenshot") else {
+        panic!("screenshot is no byte array");
+    };
+    assert!(!screenshot.is_empty());
+}
+
+#[test]
+fn test_writer_reproduces_fixture_bytes() {
+    let path = "tests/fixtures/dorfromantik.dump";
+    let (writer, _) = require_fixture!(try_load_writer(path));
+    let original = std::fs::read(path).unwrap();
+    let mut bytes = Vec::new();
+    writer.write_to(&mut bytes).unwrap();
+    assert_eq!(bytes.len(), original.len());
+    let first_difference = bytes.iter().zip(&original).position(|(a, b)| a != b);
+    assert_eq!(first_difference, None);
+}
+
+#[test]
+fn test_writer_applies_edits() {
+    let (mut writer, mut savegame) =
+        require_fixture!(try_load_writer("tests/fixtures/dorfromantik.dump"));
+
+    savegame.score += 1000;
+    savegame.file_name = Some("what-if".to_string());
+    let removed = savegame.tiles.pop().unwrap();
+    let mut moved = savegame.tiles[0].clone();
+    moved.s += 1000;
+    moved.rotation = (moved.rotation + 1) % 6;
+    moved.quest_tile = None;
+    savegame.tiles.push(moved);
+    savegame.tiles.push(removed);
+    savegame.tile_stack.truncate(1);
+    savegame.last_rewarded_score.push(42);
+    writer.apply(&savegame).unwrap();
+
+    let reloaded = write_and_reload(&writer);
+    assert_eq!(reloaded.score, 949490 + 1000);
+    assert_eq!(reloaded.file_name.as_deref(), Some("what-if"));
+    assert_eq!(reloaded.tiles.len(), savegame.tiles.len());
+    assert_eq!(reloaded.tile_stack.len(), 1);
+    assert_eq!(reloaded.last_rewarded_score.last(), Some(&42));
+
+    let added = &reloaded.tiles[reloaded.tiles.len() - 2];
+    assert_eq!(added.s, savegame.tiles[0].s + 1000);
+    assert_eq!(added.rotation, (savegame.tiles[0].rotation + 1) % 6);
+    assert!(added.quest_tile.is_none());
+    assert_eq!(added.segments.len(), savegame.tiles[0].segments.len());
+    assert_eq!(format!("{reloaded:?}"), format!("{savegame:?}"));
+}
+
+// ===========================================================================
+// Savegame tree
+// ===========================================================================
+
+fn load_tree(path: &str) -> Option<SaveGameTree> {
+    if !std::path::Path::new(path).exists() {
+        eprintln!("Skipping test: fixture {path} not found");
+        return None;
+    }
+    Some(SaveGameTree::try_from(read_tree(path)).expect("Failed to read object tree"))
+}
+
+#[test]
+fn test_tree_unmapped_fields_dorfromantik() {
+    let save = require_fixture!(load_tree("tests/fixtures/dorfromantik.dump"));
+    let unmapped: Vec<String> = save
+        .unmapped_fields()
+        .iter()
+        .map(|field| format!("{}.{}", field.class_name, field.field))
+        .collect();
+    assert_eq!(
+        unmapped,
+        [
+            "SaveGameData_003.OnUpdated",
+            "SaveGameData_003.activeChallenges",
+            "SaveGameData_003.customModeData",
+            "SaveGameData_003.excludedBiomes",
+            "SaveGameData_003.groupTypeConfiguration",
+            "SaveGameData_003.lastPlayed",
+            "SaveGameData_003.screenshot",
+        ]
+    );
+    assert!(matches!(
+        save.root.field("screenshot"),
+        Some(Node::ByteArray(_))
+    ));
+}
+
+#[test]
+fn test_tree_class_versions_dorfromantik() {
+    let save = require_fixture!(load_tree("tests/fixtures/dorfromantik.dump"));
+    let versions = save.class_versions();
+    for (class_name, version) in [
+        ("SaveGameData_003", 3),
+        ("TileData_003", 3),
+        ("SegmentData002", 2),
+        ("QuestTileData_002", 2),
+        ("PreplacedTileData_002", 2),
+    ] {
+        assert_eq!(
+            versions
+                .get(class_name)
+                .map(|v| v.iter().copied().collect::<Vec<_>>()),
+            Some(vec![version]),
+            "{class_name}"
+        );
+    }
+}
+
+#[test]
+fn test_tile_seed_and_version_kept() {
+    let sg = require_fixture!(load_dorfromantik());
+    assert!(sg.tiles.iter().all(|tile| tile.version == 3));
+    assert!(sg.tiles.iter().any(|tile| tile.seed != 0));
+    assert!(sg
+        .tiles
+        .iter()
+        .flat_map(|tile| &tile.segments)
+        .all(|segment| segment.version == 2));
+}
+
+#[test]
+fn test_savegame_layout_2_has_no_reward_milestones() {
+    let (writer, original) = require_fixture!(try_load_writer("tests/fixtures/dorfromantik.dump"));
+    let mut root = writer.root().clone();
+    let Node::Object(class_name, values) = &mut root else {
+        panic!("root is no object");
+    };
+    *class_name = "SaveGameData_002".to_string();
+    values.retain(|(key, _)| key != "lastRewardedStep" && key != "lastRewardedScore");
+    root.set_field("version", Node::Int32(2)).unwrap();
+
+    let mut bytes = Vec::new();
+    write_nrbf(&root, &mut bytes).unwrap();
+    let parsed = nrbf_rs::parse_nrbf(&mut Cursor::new(&bytes));
+    let mut savegame = SaveGame::try_from(&parsed).unwrap();
+    assert_eq!(savegame.version, 2);
+    assert!(savegame.last_rewarded_step.is_empty());
+    assert!(savegame.last_rewarded_score.is_empty());
+    assert_eq!(savegame.score, original.score);
+    assert_eq!(savegame.tiles.len(), original.tiles.len());
+
+    // The writer leaves the missing fields out as well.
+    let mut writer = SaveGameWriter::try_from(read_nrbf(Cursor::new(&bytes)).unwrap()).unwrap();
+    savegame.score += 1;
+    writer.apply(&savegame).unwrap();
+    let reloaded = write_and_reload(&writer);
+    assert_eq!((reloaded.version, reloaded.score), (2, original.score + 1));
+}
+
+// ===========================================================================
+// Decode errors
+// ===========================================================================
+
+#[test]
+fn test_decode_error_points_at_broken_segment() {
+    let (writer, _) = require_fixture!(try_load_writer("tests/fixtures/dorfromantik.dump"));
+    let mut root = writer.root().clone();
+
+    // Swap the segment type of one segment for a group type.
+    let tiles = root
+        .field_mut("tiles")
+        .unwrap()
+        .field_mut("_items")
+        .unwrap();
+    let Node::Array(tiles) = tiles else {
+        panic!("tiles are no array");
+    };
+    let segments = tiles[1832]
+        .field_mut("segments")
+        .unwrap()
+        .field_mut("_items")
+        .unwrap();
+    let Node::Array(segments) = segments else {
+        panic!("segments are no array");
+    };
+    let group_type = segments[0].field("groupType").unwrap().clone();
+    segments[0].set_field("segmentType", group_type).unwrap();
+
+    let mut bytes = Vec::new();
+    write_nrbf(&root, &mut bytes).unwrap();
+    let parsed = nrbf_rs::parse_nrbf(&mut Cursor::new(&bytes));
+    let error = SaveGame::try_from(&parsed).unwrap_err();
+
+    assert_eq!(error.path_string(), "tiles[1832].segments[0].segmentType");
+    assert_eq!(error.expected, "Dorfromantik.SegmentTypeId");
+    assert_eq!(error.actual.as_deref(), Some("GroupTypeId"));
+    assert!(error.to_string().len() < 200, "{error}");
+}
+
+// ===========================================================================
+// Preplaced tiles
+// ===========================================================================
+
+/// Section grid positions and ids of the preplaced tiles whose quest tile lies in their section.
+fn reached_preplaced_tiles(sg: &SaveGame, ids: &[i32]) -> Vec<(i32, i32, i32)> {
+    use dorfromantische2_rs::hex::{hex_to_section, offset_to_hex};
+
+    sg.preplaced_tiles
+        .iter()
+        .zip(ids)
+        .map(|(raw, &id)| (raw.section_grid_pos_x, raw.section_grid_pos_y, id))
+        .filter(|&(x, y, id)| {
+            sg.tiles.iter().any(|tile| {
+                tile.quest_tile
+                    .as_ref()
+                    .is_some_and(|quest_tile| quest_tile.quest_tile_id.0 == id)
+                    && hex_to_section(offset_to_hex(tile.s, tile.t)) == (x, y)
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn test_reached_preplaced_tiles_lie_in_their_section() {
+    use dorfromantische2_rs::hex::{hex_to_world, offset_to_hex, section_to_hex, SECTION_SIZE};
+
+    let sg = require_fixture!(load_dorfromantik());
+    let ids: Vec<i32> = sg
+        .preplaced_tiles
+        .iter()
+        .map(|raw| raw.preplaced_tile_id.0)
+        .collect();
+    let reached = reached_preplaced_tiles(&sg, &ids);
+    assert_eq!((reached.len(), ids.len()), (38, 45));
+
+    // Quest tile ids repeat, so some sections hold one by chance. Giving every section the id
+    // of the next one shows how many.
+    let mut shifted = ids.clone();
+    shifted.rotate_left(1);
+    assert!(reached_preplaced_tiles(&sg, &shifted).len() < 15);
+
+    // The reached tiles are spread over their whole section, the centre is only a stand-in.
+    let offsets: Vec<glam::Vec2> = reached
+        .iter()
+        .flat_map(|&(x, y, id)| {
+            let centre = hex_to_world(section_to_hex(x, y)).0;
+            sg.tiles
+                .iter()
+                .filter(move |tile| {
+                    tile.quest_tile
+                        .as_ref()
+                        .is_some_and(|quest_tile| quest_tile.quest_tile_id.0 == id)
+                })
+                .map(move |tile| hex_to_world(offset_to_hex(tile.s, tile.t)).0 - centre)
+        })
+        .filter(|offset| offset.abs().max_element() < SECTION_SIZE / 2.0 + 1.0)
+        .collect();
+    assert!(offsets.iter().any(|offset| offset.x > SECTION_SIZE / 3.0));
+    assert!(offsets.iter().any(|offset| offset.x < -SECTION_SIZE / 3.0));
+    assert!(offsets.iter().any(|offset| offset.y > SECTION_SIZE / 3.0));
+    assert!(offsets.iter().any(|offset| offset.y < -SECTION_SIZE / 3.0));
+}
+
+#[test]
+fn test_preplaced_tiles_at_section_centres() {
+    use dorfromantische2_rs::hex::section_to_hex;
+
+    let sg = require_fixture!(load_dorfromantik());
+    let map = build_map(&sg);
+    let ids: Vec<i32> = sg
+        .preplaced_tiles
+        .iter()
+        .map(|raw| raw.preplaced_tile_id.0)
+        .collect();
+    let reached = reached_preplaced_tiles(&sg, &ids);
+
+    assert!(!map.preplaced_tiles.is_empty());
+    assert!(map.preplaced_tiles.len() <= sg.preplaced_tiles.len() - reached.len());
+    for raw in &sg.preplaced_tiles {
+        let (x, y) = (raw.section_grid_pos_x, raw.section_grid_pos_y);
+        let pos = section_to_hex(x, y);
+        let is_reached = reached.contains(&(x, y, raw.preplaced_tile_id.0));
+        match map.preplaced_at(pos) {
+            Some(tile) => {
+                assert!(!is_reached, "Reached preplaced tile at {pos} kept");
+                assert_eq!(tile.quest_tile_id, raw.preplaced_tile_id.0);
+                assert!(!tile.segments.is_empty(), "No segments for {pos}");
+                assert!(tile.segments.iter().all(|s| s.pos == pos));
+            }
+            // Otherwise the tile has been reached or its stand-in cell holds a tile.
+            None => assert!(
+                is_reached || map.has(pos),
+                "Preplaced tile at {pos} dropped"
+            ),
+        }
+    }
+}
+
+#[test]
+fn test_preplaced_tiles_block_placements() {
+    let sg = require_fixture!(load_dorfromantik());
+    let map = build_map(&sg);
+    let groups = analyze_groups(&map);
+
+    for pos in map.preplaced_tiles.keys() {
+        assert!(!map.has(*pos));
+        assert!(
+            !groups.possible_placements.contains(pos),
+            "Preplaced cell {pos} offered as placement"
+        );
+    }
+    for group in &groups.groups {
+        assert!(group.preplaced_edges.is_subset(&group.open_edges));
+        for pos in &group.preplaced_edges {
+            assert!(map.preplaced_at(*pos).is_some());
+        }
+    }
+}
+
+#[test]
+fn test_placements_count_preplaced_neighbors() {
+    let sg = require_fixture!(load_dorfromantik());
+    let map = build_map(&sg);
+    let groups = analyze_groups(&map);
+    let placements = compute_placements(&map, &groups);
+
+    for (_, score) in placements.iter_all() {
+        let expected = (0..HEX_SIDES)
+            .filter(|&side| {
+                map.preplaced_at(Map::neighbor_pos_of(score.pos, side))
+                    .is_some()
+            })
+            .count() as u8;
+        assert_eq!(score.preplaced_neighbors, expected);
+    }
+}
+
+// ===========================================================================
+// Incremental map update
+// ===========================================================================
+
+/// Build everything from `base`, extend it with `full` and compare with a rebuild of `full`.
+fn assert_incremental_matches_rebuild(base: &SaveGame, full: &SaveGame) {
+    use dorfromantische2_rs::tile_frequency::TileFrequencies;
+    use std::collections::{BTreeMap, HashSet};
+
+    let mut map = build_map(base);
+    let mut groups = analyze_groups(&map);
+    let mut freqs = TileFrequencies::from_map(&map);
+    let mut placements = BestPlacements::compute(&map, &groups, &freqs);
+
+    let update = map.extend_from(full).expect("Same game should extend");
+    let changed: Vec<HexPos> = update.changed_positions().collect();
+    groups.update(&map, &changed);
+    let changes = freqs.update(&map, &update);
+    placements.update(&map, &groups, &freqs, &changes);
+
+    let rebuilt_map = build_map(full);
+    let rebuilt_groups = analyze_groups(&rebuilt_map);
+    let rebuilt_freqs = TileFrequencies::from_map(&rebuilt_map);
+    let rebuilt_placements = BestPlacements::compute(&rebuilt_map, &rebuilt_groups, &rebuilt_freqs);
+
+    // Same tiles, with the same segments at each side.
+    let tiles_of = |map: &Map| -> BTreeMap<(i32, i32), Vec<Option<Terrain>>> {
+        map.iter_tile_positions()
+            .map(|pos| {
+                let sides = (0..HEX_SIDES)
+                    .map(|side| map.segment_at(pos, side).map(|(_, s)| s.terrain))
+                    .collect();
+                ((pos.x(), pos.y()), sides)
+            })
+            .collect()
+    };
+    assert_eq!(tiles_of(&map), tiles_of(&rebuilt_map));
+    assert_eq!(map.index_offset, rebuilt_map.index_offset);
+    assert_eq!(map.index_size, rebuilt_map.index_size);
+    assert_eq!(map.world_y_extents, rebuilt_map.world_y_extents);
+    assert_eq!(map.quests, rebuilt_map.quests);
+    assert_eq!(map.next_tile.len(), rebuilt_map.next_tile.len());
+    assert_eq!(
+        map.preplaced_tiles.keys().collect::<HashSet<_>>(),
+        rebuilt_map.preplaced_tiles.keys().collect::<HashSet<_>>()
+    );
+
+    // Same groups, compared by kind, member tiles and open edges.
+    let groups_of = |map: &Map, groups: &GroupAssignments| {
+        let mut described: Vec<_> = groups
+            .groups
+            .iter()
+            .map(|group| {
+                let mut members: Vec<(i32, i32, usize)> = group
+                    .segment_indices
+                    .iter()
+                    .map(|&index| {
+                        let segment = map.segment(index);
+                        (segment.pos.x(), segment.pos.y(), segment.rotation)
+                    })
+                    .collect();
+                members.sort();
+                let mut open: Vec<(i32, i32)> =
+                    group.open_edges.iter().map(|p| (p.x(), p.y())).collect();
+                open.sort();
+                let mut quests: Vec<String> =
+                    group.quests.iter().map(|q| format!("{q:?}")).collect();
+                quests.sort();
+                (
+                    format!("{:?}", group.kind),
+                    members,
+                    open,
+                    group.unit_count,
+                    quests,
+                )
+            })
+            .collect();
+        described.sort();
+        described
+    };
+    assert_eq!(
+        groups_of(&map, &groups),
+        groups_of(&rebuilt_map, &rebuilt_groups)
+    );
+    assert_eq!(
+        groups.possible_placements,
+        rebuilt_groups.possible_placements
+    );
+
+    // Same frequencies.
+    assert_eq!(freqs.total_tiles, rebuilt_freqs.total_tiles);
+    let counts_of = |freqs: &TileFrequencies| {
+        let mut counts: Vec<String> = freqs
+            .entries
+            .iter()
+            .map(|entry| format!("{:?} {}", entry.edges, entry.count))
+            .collect();
+        counts.sort();
+        counts
+    };
+    assert_eq!(counts_of(&freqs), counts_of(&rebuilt_freqs));
+
+    // Same placements, including the cached fit chances.
+    let placements_of = |placements: &BestPlacements| {
+        placements
+            .iter_all()
+            .into_iter()
+            .map(|(_, s)| {
+                (
+                    s.pos,
+                    s.rotation,
+                    s.fit_chance,
+                    s.fit_unique,
+                    s.matching_edges,
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        placements_of(&placements),
+        placements_of(&rebuilt_placements)
+    );
+}
+
+#[test]
+fn test_incremental_update_last_tiles() {
+    let full = require_fixture!(load_dorfromantik());
+    let mut base = full.clone();
+    let removed = base.tiles.split_off(base.tiles.len() - 10);
+    // The last placed tile was the next tile of the older save.
+    base.tile_stack.insert(0, removed.last().unwrap().clone());
+
+    assert_incremental_matches_rebuild(&base, &full);
+}
+
+#[test]
+fn test_incremental_update_grows_index() {
+    let full = require_fixture!(load_dorfromantik());
+    let max_s = full.tiles.iter().map(|t| t.s).max().unwrap();
+    let min_t = full.tiles.iter().map(|t| t.t).min().unwrap();
+    let mut base = full.clone();
+    base.tiles.retain(|t| t.s < max_s - 1 && t.t > min_t + 1);
+
+    let map = build_map(&base);
+    assert_ne!(map.index_size, build_map(&full).index_size);
+    assert_incremental_matches_rebuild(&base, &full);
+}
+
+#[test]
+fn test_incremental_update_refreshes_quests_of_existing_tiles() {
+    let full = require_fixture!(load_dorfromantik());
+    let mut base = full.clone();
+    let removed = base.tiles.split_off(base.tiles.len() - 10);
+    base.tile_stack.insert(0, removed.last().unwrap().clone());
+    // A quest on a tile placed earlier that the newer save reports differently.
+    let quest_tile = base
+        .tiles
+        .iter_mut()
+        .find(|t| t.quest_tile.is_some())
+        .expect("Fixture has quests");
+    let quest_pos = dorfromantische2_rs::hex::offset_to_hex(quest_tile.s, quest_tile.t);
+    let quest = quest_tile.quest_tile.as_mut().unwrap();
+    quest.quest_active = !quest.quest_active;
+    quest.target_value += 1;
+
+    let mut map = build_map(&base);
+    let update = map.extend_from(&full).unwrap();
+    assert_eq!(update.quests_changed, [quest_pos]);
+    assert_eq!(
+        map.quests.get(&quest_pos),
+        build_map(&full).quests.get(&quest_pos)
+    );
+
+    assert_incremental_matches_rebuild(&base, &full);
+}
+
+#[test]
+fn test_incremental_update_rejects_other_game() {
+    let full = require_fixture!(load_dorfromantik());
+    let mut map = build_map(&full);
+    let mut other = full.clone();
+    other.tiles.truncate(other.tiles.len() / 2);
+
+    assert!(map.extend_from(&other).is_none());
+    assert!(Map::default().extend_from(&full).is_none());
+}
+
+#[test]
+fn test_incremental_update_without_new_tiles() {
+    let full = require_fixture!(load_dorfromantik());
+    let mut map = build_map(&full);
+    let update = map.extend_from(&full).unwrap();
+    assert!(update.added.is_empty());
+    assert!(update.preplaced_changed.is_empty());
+    assert!(update.quests_changed.is_empty());
+}
+
+// ===========================================================================
+// Union-find groups
+// ===========================================================================
+
+/// All fixture saves that are available.
+fn fixture_saves() -> Vec<SaveGame> {
+    [load_dorfromantik(), load_biggame()]
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// Add the tiles of `map` one at a time, in the given order.
+fn add_tiles_in_order(map: &Map, order: &[HexPos]) -> GroupAssignments {
+    let mut groups = GroupAssignments::default();
+    for &pos in order {
+        groups.add_tile(map, pos);
+    }
+    groups
+}
+
+/// Check that `groups` matches the flood fill of `map`, including the derived group values
+/// and which group every segment is assigned to.
+fn assert_matches_flood_fill(map: &Map, groups: &GroupAssignments) {
+    use std::collections::BTreeMap;
+
+    let flood_filled = analyze_groups(map);
+    let describe = |groups: &GroupAssignments| {
+        let sorted = |positions: &std::collections::HashSet<HexPos>| {
+            let mut sorted: Vec<(i32, i32)> = positions.iter().map(|p| (p.x(), p.y())).collect();
+            sorted.sort();
+            sorted
+        };
+        let mut keys = Vec::new();
+        let mut described = BTreeMap::new();
+        for group in &groups.groups {
+            let mut members: Vec<usize> = group.segment_indices.iter().copied().collect();
+            members.sort();
+            let key = (format!("{:?}", group.kind), members);
+            let mut quests: Vec<i32> = group.quests.iter().map(|q| q.target_value).collect();
+            quests.sort();
+            let value = (
+                sorted(&group.open_edges),
+                sorted(&group.preplaced_edges),
+                quests,
+                group.unit_count,
+                group.centroid.0,
+                group.radius,
+            );
+            keys.push(key.clone());
+            assert!(described.insert(key, value).is_none(), "Duplicate group");
+        }
+        let assigned: Vec<_> = (0..map.segments.len())
+            .map(|index| groups.group_of(index).map(|group| keys[group].clone()))
+            .collect();
+        (described, assigned, sorted(&groups.possible_placements))
+    };
+
+    let (groups_a, assigned_a, placements_a) = describe(groups);
+    let (groups_b, assigned_b, placements_b) = describe(&flood_filled);
+    assert_eq!(placements_a, placements_b);
+    assert_eq!(
+        groups_a.keys().collect::<Vec<_>>(),
+        groups_b.keys().collect::<Vec<_>>()
+    );
+    for (key, a) in &groups_a {
+        let b = &groups_b[key];
+        assert_eq!((&a.0, &a.1, &a.2, a.3), (&b.0, &b.1, &b.2, b.3), "{key:?}");
+        assert!(a.4.distance(b.4) < 1e-3, "Centroid of {key:?}");
+        assert!((a.5 - b.5).abs() < 1e-3, "Radius of {key:?}");
+    }
+    assert_eq!(assigned_a, assigned_b);
+}
+
+#[test]
+fn test_union_find_matches_flood_fill_in_placement_order() {
+    for savegame in fixture_saves() {
+        let map = build_map(&savegame);
+        let mut order = vec![HexPos::new(0, 0)];
+        order.extend(
+            savegame
+                .tiles
+                .iter()
+                .map(|tile| dorfromantische2_rs::hex::offset_to_hex(tile.s, tile.t)),
+        );
+        assert_matches_flood_fill(&map, &add_tiles_in_order(&map, &order));
+    }
+}
+
+#[test]
+fn test_union_find_matches_flood_fill_in_shuffled_order() {
+    for savegame in fixture_saves() {
+        let map = build_map(&savegame);
+        let mut order: Vec<HexPos> = map.iter_tile_positions().collect();
+        // Fisher-Yates with a fixed linear congruential generator.
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        for i in (1..order.len()).rev() {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            order.swap(i, (state >> 33) as usize % (i + 1));
+        }
+        assert_matches_flood_fill(&map, &add_tiles_in_order(&map, &order));
+    }
+}
+
+#[test]
+fn test_union_find_add_tile_is_idempotent() {
+    let savegame = require_fixture!(load_dorfromantik());
+    let map = build_map(&savegame);
+    let mut groups = analyze_groups(&map);
+    let group_count = groups.groups.len();
+    for pos in map.iter_tile_positions().take(50) {
+        groups.add_tile(&map, pos);
+    }
+    assert_eq!(groups.groups.len(), group_count);
+    assert_matches_flood_fill(&map, &groups);
+}
+
+// ===========================================================================
+// JSON export
+// ===========================================================================
+
+fn export_json(savegame: &SaveGame) -> String {
+    use dorfromantische2_rs::export::Export;
+    use dorfromantische2_rs::tile_frequency::TileFrequencies;
+
+    let map = build_map(savegame);
+    let groups = analyze_groups(&map);
+    let freqs = TileFrequencies::from_map(&map);
+    let placements = BestPlacements::compute(&map, &groups, &freqs);
+    Export::new(savegame, &map, &groups, &freqs, &placements).to_json()
+}
+
+#[test]
+fn test_export_is_versioned_and_complete() {
+    use dorfromantische2_rs::export::EXPORT_VERSION;
+
+    let savegame = require_fixture!(load_dorfromantik());
+    let map = build_map(&savegame);
+    let groups = analyze_groups(&map);
+    let json: serde_json::Value = serde_json::from_str(&export_json(&savegame)).unwrap();
+
+    assert_eq!(json["version"], EXPORT_VERSION);
+    assert_eq!(json["game"]["score"], savegame.score);
+    let tiles = json["tiles"].as_array().unwrap();
+    assert_eq!(tiles.len(), map.iter_tile_positions().count());
+    let exported_groups = json["groups"].as_array().unwrap();
+    assert_eq!(exported_groups.len(), groups.groups.len());
+    assert!(!json["placements"].as_array().unwrap().is_empty());
+    assert!(!json["frequencies"].as_array().unwrap().is_empty());
+
+    // Every segment points at a group of a kind that accepts its terrain.
+    let mut segment_count = 0;
+    for tile in tiles {
+        for segment in tile["segments"].as_array().unwrap() {
+            segment_count += 1;
+            let Some(group) = segment["group"].as_u64() else {
+                continue;
+            };
+            let group = &exported_groups[group as usize];
+            assert!(group["tiles"].as_array().unwrap().contains(&tile["pos"]));
+        }
+    }
+    assert_eq!(segment_count, map.segments.len());
+
+    // Placements carry all score components.
+    let best = &json["placements"][0];
+    for key in [
+        "pos",
+        "rotation",
+        "matching_edges",
+        "neighbor_bonus",
+        "connection_difficulty",
+        "crowding",
+        "preplaced_neighbors",
+        "fit_chance",
+        "fit_unique",
+        "group_effects",
+        "group_edge_alterations",
+        "neighbor_fit_effects",
+    ] {
+        assert!(best.get(key).is_some(), "Missing placement field {key}");
+    }
+}
+
+#[test]
+fn test_export_is_deterministic() {
+    let savegame = require_fixture!(load_dorfromantik());
+    // Separate runs iterate their hash sets in different orders.
+    assert!(export_json(&savegame) == export_json(&savegame));
+}
+
+// ===========================================================================
+// Lookahead
+// ===========================================================================
+
+#[test]
+fn test_lookahead_places_the_known_tiles() {
+    use dorfromantische2_rs::lookahead::{Lookahead, DEFAULT_BEAM_WIDTH, DEFAULT_DEPTH};
+    use dorfromantische2_rs::tile_frequency::TileFrequencies;
+
+    let savegame = require_fixture!(load_dorfromantik());
+    let map = build_map(&savegame);
+    let groups = analyze_groups(&map);
+    let freqs = TileFrequencies::from_map(&map);
+    let lookahead = Lookahead::search(&map, &groups, &freqs, DEFAULT_DEPTH, DEFAULT_BEAM_WIDTH);
+
+    let expected_steps = map.tile_queue.len().min(DEFAULT_DEPTH);
+    assert!(expected_steps > 0, "Fixture should reveal upcoming tiles");
+    for steps in [&lookahead.sequence, &lookahead.greedy] {
+        assert_eq!(steps.len(), expected_steps);
+        let mut positions: Vec<HexPos> = steps.iter().map(|step| step.pos).collect();
+        for step in steps {
+            assert!(!map.has(step.pos), "{:?} already holds a tile", step.pos);
+            assert!(map.preplaced_at(step.pos).is_none());
+        }
+        positions.dedup();
+        assert_eq!(positions.len(), expected_steps);
+    }
+    // The next tile goes next to the existing tiles.
+    assert!(groups
+        .possible_placements
+        .contains(&lookahead.sequence[0].pos));
+    assert!(lookahead.improvement() >= 0);
+}
+
+#[test]
+fn test_lookahead_greedy_follows_the_ranking() {
+    use dorfromantische2_rs::data::EdgeProfile;
+    use dorfromantische2_rs::lookahead::{Lookahead, DEFAULT_DEPTH};
+    use dorfromantische2_rs::tile_frequency::TileFrequencies;
+
+    let savegame = require_fixture!(load_dorfromantik());
+    let map = build_map(&savegame);
+    let groups = analyze_groups(&map);
+    let freqs = TileFrequencies::from_map(&map);
+    let placements = BestPlacements::compute(&map, &groups, &freqs);
+
+    let narrow = Lookahead::search(&map, &groups, &freqs, DEFAULT_DEPTH, 1);
+    let top = placements.iter_best().next().unwrap();
+    let first = narrow.greedy[0];
+    let profile = EdgeProfile::from_segments(&map.next_tile);
+    assert_eq!(first.pos, top.pos);
+    assert_eq!(
+        profile.rotated(first.rotation),
+        profile.rotated(top.rotation)
+    );
+    assert!(narrow.improvement() >= 0);
+
+    let wide = Lookahead::search(&map, &groups, &freqs, DEFAULT_DEPTH, 32);
+    assert_eq!(wide.greedy, narrow.greedy);
+    assert!(wide.total() >= narrow.total());
+    // The same save gives the same search, whatever the hash set orders.
+    let again = Lookahead::search(&map, &groups, &freqs, DEFAULT_DEPTH, 32);
+    assert_eq!(again.sequence, wide.sequence);
+}
+
+// ===========================================================================
+// Placement scorers
+// ===========================================================================
+
+#[test]
+fn test_default_scorer_keeps_the_built_in_order() {
+    use dorfromantische2_rs::best_placements::PlacementScore;
+    use dorfromantische2_rs::tile_frequency::TileFrequencies;
+
+    let savegame = require_fixture!(load_dorfromantik());
+    let map = build_map(&savegame);
+    let groups = analyze_groups(&map);
+    let freqs = TileFrequencies::from_map(&map);
+    let placements = BestPlacements::compute(&map, &groups, &freqs);
+
+    assert_eq!(placements.scorer().name(), "Default");
+    let ranked: Vec<&PlacementScore> = placements.iter_best().collect();
+    assert!(!ranked.is_empty());
+    assert!(ranked.windows(2).all(|pair| pair[0] > pair[1]));
+}
+
+#[test]
+fn test_weighted_scorer_reranks_placements() {
+    use dorfromantische2_rs::score::ScoreRules;
+    use dorfromantische2_rs::scorer::{DefaultScorer, WeightedScorer};
+    use dorfromantische2_rs::tile_frequency::TileFrequencies;
+
+    let savegame = require_fixture!(load_dorfromantik());
+    let map = build_map(&savegame);
+    let groups = analyze_groups(&map);
+    let freqs = TileFrequencies::from_map(&map);
+    let positions = |placements: &BestPlacements| -> Vec<(HexPos, usize)> {
+        placements
+            .iter_best()
+            .map(|score| (score.pos, score.rotation))
+            .collect()
+    };
+    let default = positions(&BestPlacements::compute(&map, &groups, &freqs));
+
+    // Only matching edges count, so the ranking follows them.
+    let weights = WeightedScorer::from_toml(
+        "fit_chance = 0.0\nmatching_edges = 1.0\nconnection_difficulty = 0.0\ncrowding = 0.0\nneighbor_bonus = 0.0",
+    )
+    .unwrap();
+    let mut placements = BestPlacements::compute_with(
+        &map,
+        &groups,
+        &freqs,
+        Box::new(weights),
+        ScoreRules::default(),
+    );
+    assert_eq!(placements.scorer().name(), "Weighted");
+    let edges: Vec<u8> = placements
+        .iter_best()
+        .map(|score| score.matching_edges)
+        .collect();
+    assert!(edges.windows(2).all(|pair| pair[0] >= pair[1]));
+    assert_eq!(placements.iter_best().count(), default.len());
+    assert_ne!(positions(&placements), default);
+
+    // Switching back reproduces the default ranking.
+    placements.set_scorer(Box::new(DefaultScorer), &map, &groups, &freqs);
+    assert_eq!(positions(&placements), default);
+}
+
+// ===========================================================================
+// Score simulation
+// ===========================================================================
+
+/// Kind and unit count of every closed group, sorted.
+fn closed_groups(groups: &GroupAssignments) -> Vec<(usize, u32)> {
+    let mut closed: Vec<(usize, u32)> = groups
+        .groups
+        .iter()
+        .filter(|group| group.is_closed())
+        .map(|group| (group.kind as usize, group.unit_count))
+        .collect();
+    closed.sort();
+    closed
+}
+
+#[test]
+fn test_score_simulation_predicts_closed_groups() {
+    use dorfromantische2_rs::score::{simulate, ScoreEvent, ScoreRules};
+
+    let full = require_fixture!(load_dorfromantik());
+    let rules = ScoreRules::default();
+    for replayed in 1..=8 {
+        let mut before = full.clone();
+        let mut after = before.tiles.split_off(before.tiles.len() - replayed);
+        after.truncate(1);
+        let pos = dorfromantische2_rs::hex::offset_to_hex(after[0].s, after[0].t);
+        let map_before = build_map(&before);
+        let groups_before = analyze_groups(&map_before);
+        before.tiles.extend(after);
+        let map_after = build_map(&before);
+        let groups_after = analyze_groups(&map_after);
+
+        // Placed segments already carry the tile rotation.
+        let tile: Vec<Segment> = map_after
+            .segment_indices_at(pos)
+            .unwrap()
+            .map(|index| map_after.segment(index).clone())
+            .collect();
+        let estimate = simulate(&map_before, &groups_before, &tile, pos, 0, &rules);
+
+        let mut predicted: Vec<(usize, u32)> = estimate
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                ScoreEvent::GroupClosed { kind, units } => Some((*kind as usize, *units)),
+                _ => None,
+            })
+            .collect();
+        predicted.sort();
+        let mut newly_closed = closed_groups(&groups_after);
+        for closed in closed_groups(&groups_before) {
+            let index = newly_closed.iter().position(|&c| c == closed).unwrap();
+            newly_closed.remove(index);
+        }
+        assert_eq!(
+            predicted, newly_closed,
+            "Tile {replayed} from the end at {pos:?}"
+        );
+
+        let constraints = constraints_at(&map_before, pos);
+        let profile = EdgeProfile::from_segments(&tile);
+        let matching = (0..HEX_SIDES)
+            .filter(|&side| {
+                constraints[side].is_some_and(|neighbor| {
+                    profile.at_index(side).connects_and_matches(neighbor) == EdgeMatch::Matching
+                })
+            })
+            .count() as i32;
+        assert!(estimate.points >= matching * rules.matching_edge_points);
+        assert!(estimate.expected(&rules) >= estimate.points as f32);
+    }
+}
+
+#[test]
+fn test_default_score_rules_add_up_to_the_fixture_totals() {
+    use dorfromantische2_rs::score::ScoreRules;
+
+    let savegame = require_fixture!(load_dorfromantik());
+    let rules = ScoreRules::default();
+
+    // The classic mode starts with 40 tiles in the stack.
+    let gained = savegame.perfect_placements * rules.perfect_tiles
+        + savegame.quests_fulfilled * rules.quest_tiles;
+    assert_eq!(
+        40 + gained,
+        savegame.placed_tile_count + savegame.tile_stack_count
+    );
+
+    // Every matching edge was scored by the later of its two tiles.
+    let map = build_map(&savegame);
+    let matching_sides: i32 = map
+        .iter_tile_positions()
+        .map(|pos| {
+            let tile: Vec<Segment> = map
+                .segment_indices_at(pos)
+                .unwrap()
+                .map(|index| map.segment(index).clone())
+                .collect();
+            let profile = EdgeProfile::from_segments(&tile);
+            let constraints = constraints_at(&map, pos);
+            (0..HEX_SIDES)
+                .filter(|&side| {
+                    constraints[side].is_some_and(|neighbor| {
+                        profile.at_index(side).connects_and_matches(neighbor) == EdgeMatch::Matching
+                    })
+                })
+                .count() as i32
+        })
+        .sum();
+    let points = matching_sides / 2 * rules.matching_edge_points
+        + savegame.perfect_placements * rules.perfect_points
+        + savegame.quests_fulfilled * rules.quest_points;
+    let error = (points - savegame.score).abs() as f32 / savegame.score as f32;
+    assert!(
+        error < 0.001,
+        "{points} points for a score of {}",
+        savegame.score
+    );
+}
+
+#[test]
+fn test_placements_carry_expected_points() {
+    use dorfromantische2_rs::score::ScoreRules;
+    use dorfromantische2_rs::scorer::PointsScorer;
+    use dorfromantische2_rs::tile_frequency::TileFrequencies;
+
+    let savegame = require_fixture!(load_dorfromantik());
+    let map = build_map(&savegame);
+    let groups = analyze_groups(&map);
+    let freqs = TileFrequencies::from_map(&map);
+    // Gained tiles are worth the points per tile of the loaded game.
+    let rules = ScoreRules::from(&savegame);
+    assert_eq!(
+        rules.tile_value,
+        savegame.score as f32 / savegame.placed_tile_count as f32
+    );
+    assert_ne!(rules, ScoreRules::default());
+    let placements =
+        BestPlacements::compute_with(&map, &groups, &freqs, Box::new(PointsScorer), rules);
+
+    assert_eq!(placements.scorer().name(), "Points");
+    for score in placements.iter_best() {
+        assert_eq!(
+            score.expected_points,
+            score.points.expected(placements.rules())
+        );
+    }
+    let expected: Vec<f32> = placements
+        .iter_best()
+        .map(|score| score.expected_points)
+        .collect();
+    assert!(!expected.is_empty());
+    assert!(expected.windows(2).all(|pair| pair[0] >= pair[1]));
+    for score in placements.iter_best() {
+        assert!(score.points.points >= i32::from(score.matching_edges) * 10);
+    }
+}
+
+// ===========================================================================
+// Survival
+// ===========================================================================
+
+#[test]
+fn test_survival_estimate_from_savegame() {
+    use dorfromantische2_rs::score::ScoreRules;
+    use dorfromantische2_rs::survival::{GameStats, SurvivalModel, NEAR_UNITS};
+
+    let savegame = require_fixture!(load_dorfromantik());
+    let map = build_map(&savegame);
+    let groups = analyze_groups(&map);
+    let stats = GameStats::from(&savegame);
+    assert_eq!(stats.placed_tiles, 11366);
+    assert_eq!(stats.perfect_placements, 9783);
+
+    let model = SurvivalModel::new(&stats, ScoreRules::default());
+    assert!(model.perfect_rate > 0.8 && model.perfect_rate <= 1.0);
+    let now = model.estimate(map.tile_stack_count, &groups);
+    assert_eq!(now.tiles_left, map.tile_stack_count);
+    assert!((0.0..=1.0).contains(&now.risk));
+    assert_eq!(now.turns.is_none(), now.drift >= 0.0);
+
+    let pending = model.pending_quests(&groups);
+    for quest in &pending {
+        assert!(quest.quest.active);
+        assert!(quest.remaining <= NEAR_UNITS);
+        assert!(quest.chance > 0.0 && quest.chance <= 1.0);
+    }
+    let pending_tiles: f32 = pending.iter().map(|quest| quest.expected_tiles()).sum();
+    assert!((now.pending_tiles - pending_tiles).abs() < 1e-3);
+
+    for score in compute_placements(&map, &groups).iter_best() {
+        let after = model.estimate_after(map.tile_stack_count, &groups, score);
+        assert_eq!(
+            after.tiles_left,
+            map.tile_stack_count - 1 + score.points.tiles
+        );
+        assert!(after.pending_tiles <= now.pending_tiles + 1e-3);
+        if score.points.tiles == 0 {
+            assert!(after.risk >= now.risk);
+        }
+    }
+}
+
+// ===========================================================================
+// Rollouts
+// ===========================================================================
+
+#[test]
+fn test_rollouts_rate_the_best_placements() {
+    use dorfromantische2_rs::rollout::{RankedPolicy, RolloutConfig, Rollouts};
+    use dorfromantische2_rs::tile_frequency::TileFrequencies;
+
+    let savegame = require_fixture!(load_dorfromantik());
+    let map = build_map(&savegame);
+    let groups = analyze_groups(&map);
+    let freqs = TileFrequencies::from_map(&map);
+    let placements = BestPlacements::compute(&map, &groups, &freqs);
+    let config = RolloutConfig::default();
+    let rollouts = Rollouts::run(&map, &groups, &freqs, &placements, config, &RankedPolicy);
+
+    assert_eq!(rollouts.results.len(), config.candidates);
+    let mut ranks: Vec<usize> = rollouts.results.iter().map(|result| result.rank).collect();
+    ranks.sort();
+    assert_eq!(ranks, (0..config.candidates).collect::<Vec<_>>());
+    let ranked: Vec<_> = placements.iter_best().collect();
+    for result in &rollouts.results {
+        assert_eq!(result.pos, ranked[result.rank].pos);
+        assert_eq!(result.rotation, ranked[result.rank].rotation);
+        assert!((0.0..=1.0).contains(&result.stuck_rate));
+    }
+    assert!(rollouts
+        .results
+        .windows(2)
+        .all(|pair| pair[0].mean_value >= pair[1].mean_value));
+}
+
+#[test]
+fn test_rollouts_are_reproducible() {
+    use dorfromantische2_rs::rollout::{
+        GreedyPolicy, PlacementPolicy, RankedPolicy, RolloutConfig, Rollouts,
+    };
+    use dorfromantische2_rs::tile_frequency::TileFrequencies;
+
+    let savegame = require_fixture!(load_dorfromantik());
+    let map = build_map(&savegame);
+    let groups = analyze_groups(&map);
+    let freqs = TileFrequencies::from_map(&map);
+    let placements = BestPlacements::compute(&map, &groups, &freqs);
+    let config = RolloutConfig {
+        candidates: 3,
+        rollouts: 8,
+        ..RolloutConfig::default()
+    };
+    for policy in [&RankedPolicy as &dyn PlacementPolicy, &GreedyPolicy] {
+        let run = || Rollouts::run(&map, &groups, &freqs, &placements, config, policy);
+        let parallel = run();
+        let single = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap()
+            .install(run);
+        assert_eq!(parallel.results, single.results, "{}", policy.name());
+    }
+
+    let reseeded = Rollouts::run(
+        &map,
+        &groups,
+        &freqs,
+        &placements,
+        RolloutConfig { seed: 7, ..config },
+        &GreedyPolicy,
+    );
+    assert_eq!(reseeded.results.len(), config.candidates);
+}
+
+// ===========================================================================
+// Quest feasibility
+// ===========================================================================
+
+#[test]
+fn test_quest_feasibility_matches_group_progress() {
+    use dorfromantische2_rs::map::QuestType;
+    use dorfromantische2_rs::quest_feasibility::{self, Infeasible, QuestStatus};
+    use dorfromantische2_rs::tile_frequency::TileFrequencies;
+
+    let savegame = require_fixture!(load_dorfromantik());
+    let map = build_map(&savegame);
+    let groups = analyze_groups(&map);
+    let freqs = TileFrequencies::from_map(&map);
+    let report = quest_feasibility::analyze(&map, &groups, &freqs);
+
+    let active: usize = groups
+        .groups
+        .iter()
+        .map(|group| group.quests.iter().filter(|quest| quest.active).count())
+        .sum();
+    assert_eq!(report.len(), active);
+    for feasibility in &report {
+        let group = &groups.groups[feasibility.group];
+        assert_eq!(feasibility.units, group.unit_count);
+        assert_eq!(
+            feasibility.remaining,
+            feasibility.quest.target_value - group.unit_count as i32
+        );
+        if feasibility.quest.quest_type == QuestType::Exact && feasibility.remaining < 0 {
+            assert_eq!(
+                feasibility.status,
+                QuestStatus::Infeasible(Infeasible::Overshot)
+            );
+        }
+        if let Some((min, max)) = feasibility.gain {
+            assert!(min <= max);
+        }
+    }
+}
+
+#[test]
+fn test_exact_hits_reach_the_target() {
+    use dorfromantische2_rs::quest_feasibility;
+    use dorfromantische2_rs::score::{self, ScoreEvent, ScoreRules};
+    use dorfromantische2_rs::tile_frequency::TileFrequencies;
+
+    let savegame = require_fixture!(load_dorfromantik());
+    let map = build_map(&savegame);
+    let groups = analyze_groups(&map);
+    let freqs = TileFrequencies::from_map(&map);
+    let report = quest_feasibility::analyze(&map, &groups, &freqs);
+
+    for feasibility in &report {
+        let group = &groups.groups[feasibility.group];
+        for hit in &feasibility.exact_hits {
+            assert!(group.open_edges.contains(&hit.pos));
+            assert!(!map.has(hit.pos));
+            let entry = freqs
+                .entries
+                .iter()
+                .find(|entry| entry.edges == hit.pattern)
+                .expect("hit pattern is a known pattern");
+            let estimate = score::simulate(
+                &map,
+                &groups,
+                &entry.segments,
+                hit.pos,
+                hit.rotation,
+                &ScoreRules::default(),
+            );
+            if hit.closes {
+                assert!(estimate.events.iter().any(|event| matches!(
+                    event,
+                    ScoreEvent::QuestCompleted(quest) if quest.quest_id == feasibility.quest.quest_id
+                )));
+            }
+            assert!(!estimate.events.iter().any(|event| matches!(
+                event,
+                ScoreEvent::QuestFailed(quest) if quest.quest_id == feasibility.quest.quest_id
+            )));
+        }
+    }
+}
+
+#[test]
+fn test_exact_quest_at_target_only_needs_closing() {
+    use dorfromantische2_rs::map::{Quest, QuestType};
+    use dorfromantische2_rs::quest_feasibility::{self, Infeasible, QuestStatus};
+    use dorfromantische2_rs::tile_frequency::TileFrequencies;
+
+    let savegame = require_fixture!(load_dorfromantik());
+    let map = build_map(&savegame);
+    let mut groups = analyze_groups(&map);
+    let freqs = TileFrequencies::from_map(&map);
+
+    let open: Vec<usize> = (0..groups.groups.len())
+        .filter(|&index| !groups.groups[index].is_closed())
+        .collect();
+    assert!(!open.is_empty());
+    for &group_index in &open {
+        let group = &mut groups.groups[group_index];
+        group.quests = vec![Quest {
+            terrain: group.terrain,
+            target_value: group.unit_count as i32,
+            active: true,
+            quest_type: QuestType::Exact,
+            quest_id: 0,
+            quest_level: 0,
+            quest_queue_index: 0,
+            unlocked_challenge_id: 0,
+        }];
+    }
+
+    for group_index in open {
+        let report = quest_feasibility::analyze_group(&map, &groups, &freqs, group_index);
+        assert_eq!(report.len(), 1);
+        let feasibility = &report[0];
+        assert_eq!(feasibility.remaining, 0);
+        if feasibility.blocked_edges == 0 {
+            assert_eq!(feasibility.status, QuestStatus::Feasible);
+            assert_eq!(feasibility.label(), "close");
+        } else {
+            assert_eq!(
+                feasibility.status,
+                QuestStatus::Infeasible(Infeasible::Blocked)
+            );
+        }
+    }
+}
+
+// ===========================================================================
+// Holes
+// ===========================================================================
+
+#[test]
+fn test_holes_are_enclosed_and_empty() {
+    use dorfromantische2_rs::holes::Holes;
+    use dorfromantische2_rs::tile_frequency::TileFrequencies;
+
+    let savegame = require_fixture!(load_dorfromantik());
+    let map = build_map(&savegame);
+    let freqs = TileFrequencies::from_map(&map);
+    let holes = Holes::find(&map, &freqs);
+
+    assert!(
+        !holes.holes.is_empty(),
+        "a finished game has enclosed holes"
+    );
+    let cells: std::collections::HashSet<HexPos> = holes
+        .holes
+        .iter()
+        .flat_map(|hole| hole.cells.iter().map(|cell| cell.pos))
+        .collect();
+    assert_eq!(cells.len(), holes.cell_count(), "holes do not overlap");
+    for hole in &holes.holes {
+        for cell in &hole.cells {
+            assert!(!map.has(cell.pos));
+            assert!(map.preplaced_at(cell.pos).is_none());
+            // Every neighbor is placed or part of the same hole.
+            for side in 0..HEX_SIDES {
+                let neighbor = Map::neighbor_pos_of(cell.pos, side);
+                assert!(
+                    map.has(neighbor)
+                        || map.preplaced_at(neighbor).is_some()
+                        || hole.cells.iter().any(|other| other.pos == neighbor),
+                    "{:?} leaks at {neighbor:?}",
+                    cell.pos
+                );
+            }
+            assert!(cell.perfect_chance <= cell.fit_chance);
+            assert!((0.0..=1.0).contains(&cell.fit_chance));
+        }
+    }
+}
+
+#[test]
+fn test_possible_placements_outside_holes_are_open() {
+    use dorfromantische2_rs::holes::Holes;
+    use dorfromantische2_rs::tile_frequency::TileFrequencies;
+
+    let savegame = require_fixture!(load_dorfromantik());
+    let map = build_map(&savegame);
+    let groups = analyze_groups(&map);
+    let freqs = TileFrequencies::from_map(&map);
+    let holes = Holes::find(&map, &freqs);
+
+    // Single-cell holes have six placed neighbors and are offered as placements.
+    for hole in holes.holes.iter().filter(|hole| hole.size() == 1) {
+        let cell = &hole.cells[0];
+        if cell.constraints.iter().all(Option::is_some) {
+            assert!(groups.possible_placements.contains(&cell.pos));
+        }
+    }
+}
+
+// ===========================================================================
+// Demand
+// ===========================================================================
+
+#[test]
+fn test_demand_covers_the_frontier() {
+    use dorfromantische2_rs::demand::Demand;
+    use dorfromantische2_rs::tile_frequency::TileFrequencies;
+
+    let savegame = require_fixture!(load_dorfromantik());
+    let map = build_map(&savegame);
+    let groups = analyze_groups(&map);
+    let freqs = TileFrequencies::from_map(&map);
+    let demand = Demand::compute(&map, &groups, &freqs);
+
+    assert_eq!(demand.cells.len(), groups.possible_placements.len());
+    assert!(demand
+        .cells
+        .windows(2)
+        .all(|pair| pair[0].supply <= pair[1].supply));
+    for cell in &demand.cells {
+        assert!(groups.possible_placements.contains(&cell.pos));
+        // Perfect fits are a subset of the legal ones.
+        let (fit_chance, fit_unique) = fit_chance_for_constraints(&freqs, &cell.constraints);
+        assert!(cell.patterns.len() <= fit_unique as usize);
+        assert!(cell.supply <= fit_chance + 1e-4);
+    }
+
+    // Every served cell hands out exactly one unit of demand.
+    let total: f32 = demand.patterns.iter().map(|need| need.demand).sum();
+    assert!((total - demand.served_cells() as f32).abs() < 1e-2);
+    assert!(demand
+        .patterns
+        .windows(2)
+        .all(|pair| pair[0].demand >= pair[1].demand));
+}
+
+#[test]
+fn test_demand_compares_with_supply() {
+    use dorfromantische2_rs::demand::Demand;
+    use dorfromantische2_rs::tile_frequency::TileFrequencies;
+
+    let savegame = require_fixture!(load_dorfromantik());
+    let map = build_map(&savegame);
+    let groups = analyze_groups(&map);
+    let freqs = TileFrequencies::from_map(&map);
+    let demand = Demand::compute(&map, &groups, &freqs);
+
+    for need in &demand.patterns {
+        let entry = freqs
+            .entries
+            .iter()
+            .find(|entry| entry.edges == need.pattern)
+            .expect("demanded pattern is known");
+        assert_eq!(need.supply, entry.fraction as f32);
+        let cells = demand
+            .cells
+            .iter()
+            .filter(|cell| cell.patterns.contains(&need.pattern))
+            .count();
+        assert_eq!(need.cells, cells);
+        assert!(need.pressure(demand.served_cells()) > 0.0);
+    }
+}
+
+// ===========================================================================
+// All rotations
+// ===========================================================================
+
+#[test]
+fn test_every_legal_rotation_is_kept() {
+    let savegame = require_fixture!(load_dorfromantik());
+    let map = build_map(&savegame);
+    let groups = analyze_groups(&map);
+    let placements = compute_placements(&map, &groups);
+
+    let mut legal = 0;
+    for best in placements.iter_best() {
+        let rotations = placements.by_position(best.pos);
+        assert!(!rotations.is_empty());
+        assert!(std::ptr::eq(&rotations[0], best));
+        let mut seen: Vec<usize> = rotations.iter().map(|score| score.rotation).collect();
+        seen.sort();
+        seen.dedup();
+        assert_eq!(seen.len(), rotations.len(), "rotations are unique");
+        for score in rotations {
+            assert_eq!(score.pos, best.pos);
+            assert_eq!(score.fit_chance, best.fit_chance);
+        }
+        assert!(rotations
+            .windows(2)
+            .all(|pair| placements.scorer().compare(&pair[0], &pair[1]).is_ge()));
+
+        let ties = placements.tied_rotations(best.pos);
+        assert!(!ties.is_empty() && ties.len() <= rotations.len());
+        legal += rotations.len();
+    }
+    assert_eq!(placements.legal_count(), legal);
+    assert!(
+        legal > placements.iter_best().count(),
+        "most cells fit several rotations"
+    );
+    assert!(placements.by_position(HexPos::new(0, 0)).is_empty());
+}
+
+#[test]
+fn test_placement_queries() {
+    let savegame = require_fixture!(load_dorfromantik());
+    let map = build_map(&savegame);
+    let groups = analyze_groups(&map);
+    let placements = compute_placements(&map, &groups);
+
+    let top: Vec<HexPos> = placements.top_n(5).map(|score| score.pos).collect();
+    let best: Vec<HexPos> = placements
+        .iter_best()
+        .take(5)
+        .map(|score| score.pos)
+        .collect();
+    assert_eq!(top, best);
+
+    let all = placements.filter(|_| true);
+    assert_eq!(all.len(), placements.legal_count());
+    assert!(all
+        .windows(2)
+        .all(|pair| placements.scorer().compare(pair[0], pair[1]).is_ge()));
+
+    let matching = placements.filter(|score| score.matching_edges >= 3);
+    assert!(matching.iter().all(|score| score.matching_edges >= 3));
+    let expected = all.iter().filter(|score| score.matching_edges >= 3).count();
+    assert_eq!(matching.len(), expected);
+}
+
+#[test]
+fn test_parallel_placements_match_single_thread() {
+    let savegame = require_fixture!(load_dorfromantik());
+    let map = build_map(&savegame);
+    let groups = analyze_groups(&map);
+    let with_threads = |threads| {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap()
+            .install(|| compute_placements(&map, &groups))
+    };
+    let (parallel, single) = (with_threads(4), with_threads(1));
+
+    let key = |score: &dorfromantische2_rs::best_placements::PlacementScore| {
+        (
+            score.pos,
+            score.rotation,
+            score.fit_chance,
+            score.fit_unique,
+            score.neighbor_fit_effects.len(),
+        )
+    };
+    let parallel: Vec<_> = parallel.filter(|_| true).into_iter().map(key).collect();
+    let single: Vec<_> = single.filter(|_| true).into_iter().map(key).collect();
+    assert_eq!(parallel, single);
+}
+
+// ===========================================================================
+// Map editing
+// ===========================================================================
+
+/// Groups by kind, size, member count and open edges, in a stable order.
+fn describe_groups(groups: &GroupAssignments) -> Vec<(String, u32, usize, usize)> {
+    let mut described: Vec<_> = groups
+        .groups
+        .iter()
+        .map(|group| {
+            (
+                format!("{:?}", group.kind),
+                group.unit_count,
+                group.segment_indices.len(),
+                group.open_edges.len(),
+            )
+        })
+        .collect();
+    described.sort();
+    described
+}
+
+#[test]
+fn test_removing_tiles_matches_an_older_save() {
+    let full = require_fixture!(load_dorfromantik());
+    let mut base = full.clone();
+    let removed = base.tiles.split_off(base.tiles.len() - 10);
+
+    let mut map = build_map(&full);
+    for tile in &removed {
+        let pos = dorfromantische2_rs::hex::offset_to_hex(tile.s, tile.t);
+        assert!(map.remove(pos).is_some(), "{pos:?} should hold a tile");
+    }
+    let older = build_map(&base);
+    assert_eq!(map.segments.len(), older.segments.len());
+    assert_eq!(map.quests.len(), older.quests.len());
+
+    let (groups, older_groups) = (analyze_groups(&map), analyze_groups(&older));
+    assert_eq!(describe_groups(&groups), describe_groups(&older_groups));
+    assert_eq!(groups.possible_placements, older_groups.possible_placements);
+
+    let placements_of = |map: &Map, groups: &GroupAssignments| {
+        compute_placements(map, groups)
+            .filter(|_| true)
+            .into_iter()
+            .map(|score| (score.pos, score.rotation, score.matching_edges))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        placements_of(&map, &groups),
+        placements_of(&older, &older_groups)
+    );
+}
+
+#[test]
+fn test_placing_removed_tiles_restores_the_map() {
+    let savegame = require_fixture!(load_dorfromantik());
+    let full = build_map(&savegame);
+    let mut map = full.clone();
+    let positions: Vec<HexPos> = savegame
+        .tiles
+        .iter()
+        .rev()
+        .take(10)
+        .map(|tile| dorfromantische2_rs::hex::offset_to_hex(tile.s, tile.t))
+        .collect();
+    let removed: Vec<Vec<Segment>> = positions
+        .iter()
+        .map(|&pos| map.remove(pos).unwrap())
+        .collect();
+
+    // The segments come back already rotated.
+    for (&pos, segments) in positions.iter().zip(&removed) {
+        assert!(map.place(pos, segments, 0));
+    }
+    assert_eq!(map.segments.len(), full.segments.len());
+    assert_eq!(map.index_size, full.index_size);
+    for pos in full.iter_tile_positions() {
+        let sides = |map: &Map| {
+            (0..HEX_SIDES)
+                .map(|side| map.segment_at(pos, side).map(|(_, s)| s.terrain))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(sides(&map), sides(&full), "{pos:?}");
+    }
+    assert_eq!(
+        describe_groups(&analyze_groups(&map)),
+        describe_groups(&analyze_groups(&full))
+    );
+
+    // Placing on a free frontier cell only touches the copy.
+    let groups = analyze_groups(&full);
+    let pos = *groups.possible_placements.iter().next().unwrap();
+    let edited = full.clone_with(pos, &full.next_tile, 0).unwrap();
+    assert!(edited.has(pos) && !full.has(pos));
+    assert!(full.clone_with(positions[0], &full.next_tile, 0).is_none());
+}
+
+// ===========================================================================
+// Timeline
+// ===========================================================================
+
+/// The fixture without its last `count` tiles, as an older save of the same game.
+fn earlier_save(full: &SaveGame, count: usize, score: i32) -> SaveGame {
+    let mut earlier = full.clone();
+    let removed = earlier.tiles.split_off(earlier.tiles.len() - count);
+    earlier.tile_stack.insert(0, removed[0].clone());
+    earlier.score = score;
+    earlier.tile_stack_count += 1;
+    earlier
+}
+
+#[test]
+fn test_timeline_records_the_steps_of_a_game() {
+    use dorfromantische2_rs::timeline::{Recorded, Timeline};
+
+    let full = require_fixture!(load_dorfromantik());
+    let first = earlier_save(&full, 20, full.score - 900);
+    let second = earlier_save(&full, 10, full.score - 400);
+
+    let mut timeline = Timeline::default();
+    assert_eq!(timeline.record(&first), Recorded::NewGame);
+    assert_eq!(timeline.record(&second), Recorded::Step);
+    assert_eq!(timeline.record(&second), Recorded::Unchanged);
+    assert_eq!(timeline.record(&full), Recorded::Step);
+    assert_eq!(timeline.len(), 3);
+
+    assert_eq!(timeline.steps[0].added.len(), first.tiles.len());
+    let placed: Vec<HexPos> = timeline.steps[2].positions().collect();
+    let expected: Vec<HexPos> = full.tiles[full.tiles.len() - 10..]
+        .iter()
+        .map(|tile| dorfromantische2_rs::hex::offset_to_hex(tile.s, tile.t))
+        .collect();
+    assert_eq!(placed, expected);
+
+    assert!(timeline.change(0).is_none());
+    let change = timeline.change(2).unwrap();
+    assert_eq!(change.score, 400);
+    assert_eq!(change.tile_stack_count, -1);
+    assert_eq!(change.placed_tile_count, 0);
+
+    // A save without the recorded tiles starts over.
+    let mut other = full.clone();
+    other.tiles.truncate(other.tiles.len() / 2);
+    assert_eq!(timeline.record(&other), Recorded::NewGame);
+    assert_eq!(timeline.len(), 1);
+}
+
+#[test]
+fn test_timeline_rebuilds_earlier_saves() {
+    use dorfromantische2_rs::timeline::Timeline;
+    use std::collections::BTreeSet;
+
+    let full = require_fixture!(load_dorfromantik());
+    let first = earlier_save(&full, 20, full.score - 900);
+    let second = earlier_save(&full, 10, full.score - 400);
+    let mut timeline = Timeline::default();
+    for savegame in [&first, &second, &full] {
+        timeline.record(savegame);
+    }
+
+    let positions = |map: &Map| -> BTreeSet<(i32, i32)> {
+        map.iter_tile_positions()
+            .map(|pos| (pos.x(), pos.y()))
+            .collect()
+    };
+    for (index, expected) in [&first, &second, &full].into_iter().enumerate() {
+        let rebuilt = timeline.savegame_at(index).unwrap();
+        assert_eq!(rebuilt.tiles.len(), expected.tiles.len());
+        assert_eq!(rebuilt.tile_stack.len(), expected.tile_stack.len());
+        assert_eq!(rebuilt.score, expected.score);
+        assert_eq!(rebuilt.tile_stack_count, expected.tile_stack_count);
+        assert_eq!(
+            positions(&build_map(&rebuilt)),
+            positions(&build_map(expected))
+        );
+    }
+    assert!(timeline.savegame_at(3).is_none());
+}
+
+#[test]
+fn test_timeline_patches_quests_of_earlier_tiles() {
+    use dorfromantische2_rs::timeline::Timeline;
+
+    let full = require_fixture!(load_dorfromantik());
+    let index = full
+        .tiles
+        .iter()
+        .position(|tile| tile.quest_tile.is_some())
+        .unwrap();
+    let tile = &full.tiles[index];
+    let pos = dorfromantische2_rs::hex::offset_to_hex(tile.s, tile.t);
+    let quest = tile.quest_tile.clone();
+
+    // The quest progressed between the first and the second save.
+    let mut first = earlier_save(&full, 20, full.score - 900);
+    first.tiles[index].quest_tile.as_mut().unwrap().target_value += 5;
+    let second = earlier_save(&full, 10, full.score - 400);
+    let mut timeline = Timeline::default();
+    for savegame in [&first, &second, &full] {
+        timeline.record(savegame);
+    }
+
+    assert!(timeline.steps[0].quests_changed.is_empty());
+    assert_eq!(timeline.steps[1].quests_changed, [(pos, quest.clone())]);
+    assert!(timeline.steps[2].quests_changed.is_empty());
+    let quest_at = |step: usize| {
+        let rebuilt = timeline.savegame_at(step).unwrap();
+        rebuilt
+            .tiles
+            .into_iter()
+            .find(|tile| dorfromantische2_rs::hex::offset_to_hex(tile.s, tile.t) == pos)
+            .unwrap()
+            .quest_tile
+    };
+    assert_eq!(quest_at(0), first.tiles[index].quest_tile);
+    assert_eq!(quest_at(1), quest);
+    assert_eq!(quest_at(2), quest);
+}
+
+// ===========================================================================
+// Replay review
+// ===========================================================================
+
+#[test]
+fn test_review_replays_the_placed_tiles() {
+    use dorfromantische2_rs::{replay::Review, scorer::DefaultScorer, timeline::Timeline};
+
+    let full = require_fixture!(load_dorfromantik());
+    let first = earlier_save(&full, 12, full.score - 900);
+    let second = earlier_save(&full, 5, full.score - 400);
+    let mut timeline = Timeline::default();
+    for savegame in [&first, &second, &full] {
+        timeline.record(savegame);
+    }
+
+    let review = Review::of(&timeline, Box::new(DefaultScorer));
+    assert_eq!(review.placements.len(), 12);
+    let placed: Vec<HexPos> = full.tiles[full.tiles.len() - 12..]
+        .iter()
+        .map(|tile| dorfromantische2_rs::hex::offset_to_hex(tile.s, tile.t))
+        .collect();
+    let reviewed: Vec<HexPos> = review.placements.iter().map(|r| r.pos).collect();
+    assert_eq!(reviewed, placed);
+    assert!(review.placements[..7].iter().all(|r| r.step == 1));
+    assert!(review.placements[7..].iter().all(|r| r.step == 2));
+
+    for placement in &review.placements {
+        // The game only allows legal rotations. Positions that would split the empty cells
+        // around them are not ranked at all.
+        assert_eq!(
+            placement.rotation_rank.is_some(),
+            placement.position_rank.is_some(),
+            "{:?} rotation {}",
+            placement.pos,
+            placement.rotation
+        );
+        assert!(placement.candidates > 0);
+        if placement.followed {
+            assert_eq!(placement.position_rank, Some(1));
+        }
+        assert!(placement.quests_possible >= placement.quests_completed);
+        assert!(placement.points >= 0);
+    }
+
+    let summary = review.summary();
+    assert_eq!(summary.placements, 12);
+    assert!(summary.unranked < summary.placements / 2);
+    assert!(summary.top_three >= summary.followed);
+}
+
+#[test]
+fn test_review_exports_json_and_csv() {
+    use dorfromantische2_rs::{replay::Review, scorer::DefaultScorer, timeline::Timeline};
+
+    let full = require_fixture!(load_dorfromantik());
+    let mut timeline = Timeline::default();
+    timeline.record(&earlier_save(&full, 4, full.score - 200));
+    timeline.record(&full);
+    let review = Review::of(&timeline, Box::new(DefaultScorer));
+
+    let json: serde_json::Value = serde_json::from_str(&review.to_json()).unwrap();
+    assert_eq!(json["version"], 1);
+    assert_eq!(json["summary"]["placements"], 4);
+    assert_eq!(json["placements"].as_array().unwrap().len(), 4);
+    assert_eq!(
+        json["placements"][0]["pos"][0],
+        review.placements[0].pos.x()
+    );
+
+    let csv = review.to_csv();
+    let columns = csv.lines().next().unwrap().split(',').count();
+    assert_eq!(csv.lines().count(), 5);
+    assert!(csv.lines().all(|line| line.split(',').count() == columns));
+
+    // A single save has nothing to review.
+    let mut single = Timeline::default();
+    single.record(&full);
+    assert!(Review::of(&single, Box::new(DefaultScorer))
+        .placements
+        .is_empty());
+}
+
+// ===========================================================================
+// Save slots
+// ===========================================================================
+
+#[test]
+fn test_scan_reads_every_slot() {
+    use dorfromantische2_rs::save_slots;
+    use std::time::{Duration, SystemTime};
+
+    let savegame = require_fixture!(load_dorfromantik());
+    let dir = std::env::temp_dir().join("dorfromantische2_rs_test_scan_slots");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::copy("tests/fixtures/dorfromantik.dump", dir.join("slot0.sav")).unwrap();
+    std::fs::write(dir.join("slot1.sav"), b"not a savegame").unwrap();
+    std::fs::File::options()
+        .write(true)
+        .open(dir.join("slot0.sav"))
+        .unwrap()
+        .set_modified(SystemTime::now() - Duration::from_secs(60))
+        .unwrap();
+
+    let slots = save_slots::scan(&dir).unwrap();
+    assert_eq!(slots.len(), 2);
+    assert_eq!(slots[0].name(), "slot1");
+    assert!(slots[0].info.is_err());
+
+    let info = slots[1].info.as_ref().unwrap();
+    assert_eq!(info.level, savegame.level);
+    assert_eq!(info.score, savegame.score);
+    assert_eq!(info.placed_tile_count, savegame.placed_tile_count);
+    assert_eq!(info.last_played_version, savegame.last_played_version);
+    assert!(!info.last_played_version.is_empty());
+}